@@ -0,0 +1,109 @@
+#![feature(test)]
+
+extern crate test;
+extern crate rlisp;
+
+use std::collections::HashMap;
+
+use test::Bencher;
+
+use rlisp::{Lisp, Backend, Symbol, Value};
+use rlisp::symbol::SymbolMap;
+use rlisp::parse;
+
+const FIB: &'static str = r#"
+(def {fib n}
+  {if (< n 2)
+    {id n}
+    {+ (fib (- n 1)) (fib (- n 2))}})
+"#;
+
+const SUM: &'static str = r#"
+(def {sum xs acc}
+  {if (empty? xs)
+    {id acc}
+    {sum (tail xs) (+ acc (head xs))}})
+"#;
+
+#[bench]
+fn fib_15(b: &mut Bencher) {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw(FIB).unwrap();
+
+    // Parse up front, so that only evaluation is measured
+    let code = parse::parse_str("(fib 15)").unwrap();
+    b.iter(|| lisp.eval_token_vec(code.clone()).unwrap());
+}
+
+#[bench]
+fn sum_list_200(b: &mut Bencher) {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw(SUM).unwrap();
+
+    let code = parse::parse_str("(sum (range 0 200) 0)").unwrap();
+    b.iter(|| lisp.eval_token_vec(code.clone()).unwrap());
+}
+
+#[bench]
+fn fib_15_bytecode(b: &mut Bencher) {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(Backend::Bytecode);
+    lisp.eval_raw(FIB).unwrap();
+
+    let code = parse::parse_str("(fib 15)").unwrap();
+    b.iter(|| lisp.eval_token_vec(code.clone()).unwrap());
+}
+
+#[bench]
+fn sum_list_200_bytecode(b: &mut Bencher) {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(Backend::Bytecode);
+    lisp.eval_raw(SUM).unwrap();
+
+    let code = parse::parse_str("(sum (range 0 200) 0)").unwrap();
+    b.iter(|| lisp.eval_token_vec(code.clone()).unwrap());
+}
+
+#[bench]
+fn deep_scope_lookup(b: &mut Bencher) {
+    let mut lisp = Lisp::new();
+    for _ in 0..50 {
+        lisp.sub_scope();
+    }
+
+    let code = parse::parse_str("(+ 1 2)").unwrap();
+    b.iter(|| lisp.eval_token_vec(code.clone()).unwrap());
+}
+
+// The two benches below compare the lookups done before and after symbols were interned
+const NAMES: &'static [&'static str] = &["fib", "n", "if", "<", "id", "+", "-", "head", "tail", "empty?"];
+
+#[bench]
+fn string_keyed_lookup(b: &mut Bencher) {
+    let mut map = HashMap::new();
+    for name in NAMES {
+        map.insert(name.to_string(), Value::Nil);
+    }
+
+    let keys: Vec<String> = NAMES.iter().map(|name| name.to_string()).collect();
+    b.iter(|| {
+        for key in &keys {
+            test::black_box(map.get(key));
+        }
+    });
+}
+
+#[bench]
+fn symbol_keyed_lookup(b: &mut Bencher) {
+    let mut map = SymbolMap::default();
+    for name in NAMES {
+        map.insert(Symbol::intern(name), Value::Nil);
+    }
+
+    let keys: Vec<Symbol> = NAMES.iter().map(|name| Symbol::intern(name)).collect();
+    b.iter(|| {
+        for key in &keys {
+            test::black_box(map.get(key));
+        }
+    });
+}
@@ -0,0 +1,60 @@
+// Times the same programs on both backends, run it with `cargo run --release --example backends`.
+// `benches/eval.rs` measures the same things more carefully, but needs a nightly compiler.
+
+extern crate rlisp;
+
+use std::time::{Duration, Instant};
+
+use rlisp::{Lisp, Backend};
+use rlisp::parse;
+
+const FIB: &'static str = r#"
+(def {fib n}
+  {if (< n 2)
+    {id n}
+    {+ (fib (- n 1)) (fib (- n 2))}})
+"#;
+
+const SUM: &'static str = r#"
+(def {sum xs acc}
+  {if (empty? xs)
+    {id acc}
+    {sum (tail xs) (+ acc (head xs))}})
+"#;
+
+const PROGRAMS: &'static [(&'static str, &'static str, u32)] = &[
+    (FIB, "(fib 15)", 100),
+    (SUM, "(sum (range 0 200) 0)", 1000),
+];
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs() as f64 * 1e3 + time.subsec_nanos() as f64 / 1e6
+}
+
+// The average time of one run, in milliseconds
+fn time(backend: Backend, defs: &str, call: &str, runs: u32) -> f64 {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(backend);
+    lisp.eval_raw(defs).unwrap();
+
+    // Parse up front, so that only evaluation is measured,
+    // and run it once first so the bytecode backend's compiling isn't counted either
+    let code = parse::parse_str(call).unwrap();
+    lisp.eval_token_vec(code.clone()).unwrap();
+
+    let start = Instant::now();
+    for _ in 0..runs {
+        lisp.eval_token_vec(code.clone()).unwrap();
+    }
+
+    millis(start.elapsed()) / runs as f64
+}
+
+fn main() {
+    for &(defs, call, runs) in PROGRAMS {
+        let walked = time(Backend::TreeWalker, defs, call, runs);
+        let compiled = time(Backend::Bytecode, defs, call, runs);
+
+        println!("{:<24} tree walker {:>8.3}ms  bytecode {:>8.3}ms  ({:.1}x)", call, walked, compiled, walked / compiled);
+    }
+}
@@ -0,0 +1,136 @@
+//! Formats rlisp source files.
+//!
+//! rlisp-fmt [--check] [--width N] [FILE...]
+//!
+//! Files are rewritten in place, or with no files, source is read from stdin and written to stdout.
+//! With --check nothing is written, the files that aren't formatted are listed and the exit code is 1.
+
+extern crate rlisp;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use rlisp::format;
+use rlisp::parse::FilePos;
+use rlisp::pretty::DEFAULT_WIDTH;
+
+struct Options {
+    check: bool,
+    width: usize,
+    files: Vec<String>,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: rlisp-fmt [--check] [--width N] [FILE...]");
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        check: false,
+        width: DEFAULT_WIDTH,
+        files: Vec::new(),
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--check" => options.check = true,
+            "--width" => {
+                options.width = match args.next().and_then(|width| width.parse().ok()) {
+                    Some(width) => width,
+                    None => usage(),
+                };
+            },
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with("-") && arg != "-" => usage(),
+            _ => options.files.push(arg),
+        }
+    }
+
+    options
+}
+
+// Returns the formatted source, or prints its syntax errors
+fn format(name: &str, code: &str, width: usize) -> Option<String> {
+    match format::format_source(code, width) {
+        Ok(formatted) => Some(formatted),
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                let pos = FilePos::from_offset(code, diagnostic.span.start);
+                eprintln!("{}:{}:{}: {:?}", name, pos.line, pos.col, diagnostic.error);
+            }
+
+            None
+        },
+    }
+}
+
+fn read_file(path: &str) -> io::Result<String> {
+    let mut code = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut code));
+
+    Ok(code)
+}
+
+fn main() {
+    let options = parse_args();
+
+    if options.files.is_empty() {
+        let mut code = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut code) {
+            eprintln!("<stdin>: {}", err);
+            process::exit(2);
+        }
+
+        match format("<stdin>", &code, options.width) {
+            Some(ref formatted) if options.check => process::exit(if *formatted == code { 0 } else { 1 }),
+            Some(formatted) => print!("{}", formatted),
+            None => process::exit(2),
+        }
+
+        return;
+    }
+
+    let mut unformatted = false;
+    let mut failed = false;
+
+    for path in &options.files {
+        let code = match read_file(path) {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            },
+        };
+
+        let formatted = match format(path, &code, options.width) {
+            Some(formatted) => formatted,
+            None => {
+                failed = true;
+                continue;
+            },
+        };
+
+        if formatted == code {
+            continue;
+        }
+
+        if options.check {
+            println!("{}", path);
+            unformatted = true;
+        } else if let Err(err) = File::create(path).and_then(|mut file| file.write_all(formatted.as_bytes())) {
+            eprintln!("{}: {}", path, err);
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(2);
+    } else if unformatted {
+        process::exit(1);
+    }
+}
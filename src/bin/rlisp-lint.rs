@@ -0,0 +1,107 @@
+//! Checks rlisp source files for common mistakes.
+//!
+//! rlisp-lint [--json] [FILE...]
+//!
+//! With no files, source is read from stdin. Each lint is printed as `file:line:col: kind: message`,
+//! or with --json, every lint is printed as an object in a single JSON array.
+//! The exit code is 1 if anything was found.
+
+extern crate rlisp;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+
+use rlisp::Lisp;
+use rlisp::lint;
+use rlisp::parse::FilePos;
+
+struct Options {
+    json: bool,
+    files: Vec<String>,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: rlisp-lint [--json] [FILE...]");
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        json: false,
+        files: Vec::new(),
+    };
+
+    for arg in env::args().skip(1) {
+        match &arg[..] {
+            "--json" => options.json = true,
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with("-") && arg != "-" => usage(),
+            _ => options.files.push(arg),
+        }
+    }
+
+    options
+}
+
+fn read_file(path: &str) -> io::Result<String> {
+    let mut code = String::new();
+
+    match path {
+        "-" => try!(io::stdin().read_to_string(&mut code)),
+        _ => try!(try!(File::open(path)).read_to_string(&mut code)),
+    };
+
+    Ok(code)
+}
+
+fn main() {
+    let mut options = parse_args();
+    if options.files.is_empty() {
+        options.files.push("-".to_string());
+    }
+
+    let lisp = Lisp::new();
+
+    let mut found = false;
+    let mut failed = false;
+    let mut objects = Vec::new();
+
+    for path in &options.files {
+        let name = if path == "-" { "<stdin>" } else { path };
+
+        let code = match read_file(path) {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                failed = true;
+                continue;
+            },
+        };
+
+        for lint in lint::lint_source(&lisp, &code) {
+            found = true;
+
+            if options.json {
+                objects.push(lint.to_json(name, &code).to_string());
+            } else {
+                let pos = FilePos::from_offset(&code, lint.span.start);
+                println!("{}:{}:{}: {}", name, pos.line, pos.col, lint);
+            }
+        }
+    }
+
+    if options.json {
+        match objects.is_empty() {
+            true => println!("[]"),
+            false => println!("[\n  {}\n]", objects.join(",\n  ")),
+        }
+    }
+
+    if failed {
+        process::exit(2);
+    } else if found {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,26 @@
+//! A language server for rlisp, over stdio.
+//!
+//! rlisp-lsp
+//!
+//! Point an editor's LSP client at this binary, for diagnostics, hover, go to definition,
+//! completion and document symbols in .lisp files.
+
+extern crate rlisp;
+
+use std::io;
+use std::process;
+
+use rlisp::lsp::Server;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    match Server::new().run(stdin.lock(), stdout.lock()) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("rlisp-lsp: {}", err);
+            process::exit(1);
+        },
+    }
+}
@@ -0,0 +1,413 @@
+//! A small line editor for the REPL.
+//!
+//! The terminal is switched out of line mode with `stty` while a line is being edited,
+//! so nothing beyond the standard library is needed. If that can't be done (stdin isn't a terminal,
+//! or there's no `stty`), lines are read as they are.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write, BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+const MAX_HISTORY: usize = 1000;
+
+pub enum Input {
+    Line(String),
+    /// Ctrl-C, which throws away the line
+    Interrupted,
+    /// Ctrl-D on an empty line, or the end of stdin
+    Eof,
+}
+
+/// Whether stdin is a terminal that can be edited on
+pub fn is_terminal() -> bool {
+    terminal_settings().is_some()
+}
+
+fn terminal_settings() -> Option<String> {
+    let output = match Command::new("stty").arg("-g").stdin(Stdio::inherit()).stderr(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(_) => return None,
+    };
+
+    match output.status.success() {
+        true => String::from_utf8(output.stdout).ok().map(|settings| settings.trim().to_string()),
+        false => None,
+    }
+}
+
+fn stty(args: &[&str]) -> bool {
+    Command::new("stty").args(args).stdin(Stdio::inherit()).status().map(|status| status.success()).unwrap_or(false)
+}
+
+/// Where history is kept between sessions, $HOME/.rlisp_history
+pub fn history_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlisp_history"))
+}
+
+pub struct Editor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+    // How to put the terminal back the way it was, None if lines are read as they are
+    settings: Option<String>,
+}
+
+impl Editor {
+    pub fn new(history_file: Option<PathBuf>) -> Editor {
+        let mut history = Vec::new();
+
+        if let Some(file) = history_file.as_ref().and_then(|path| File::open(path).ok()) {
+            history.extend(BufReader::new(file).lines().filter_map(Result::ok).filter(|line| !line.is_empty()));
+        }
+
+        if history.len() > MAX_HISTORY {
+            let excess = history.len() - MAX_HISTORY;
+            history.drain(..excess);
+
+            if let Some(ref path) = history_file {
+                let _ = fs::write(path, history.join("\n") + "\n");
+            }
+        }
+
+        Editor {
+            history: history,
+            history_file: history_file,
+            settings: terminal_settings(),
+        }
+    }
+
+    pub fn add_history(&mut self, line: &str) {
+        let line = line.trim_right();
+        if line.is_empty() || self.history.last().map_or(false, |last| last == line) {
+            return;
+        }
+
+        self.history.push(line.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+
+        if let Some(ref path) = self.history_file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    /// Reads a line, `complete` gives the possible completions of the word before the cursor
+    pub fn read_line<F>(&mut self, prompt: &str, complete: F) -> io::Result<Input>
+        where F: Fn(&str) -> Vec<String>
+    {
+        let settings = match self.settings.clone() {
+            Some(settings) => settings,
+            None => return read_plain_line(prompt),
+        };
+
+        if !stty(&["-icanon", "-echo", "-isig", "-ixon", "min", "1", "time", "0"]) {
+            self.settings = None;
+            return read_plain_line(prompt);
+        }
+
+        let result = LineState::new(prompt, &self.history).edit(&complete);
+        stty(&[&settings]);
+
+        result
+    }
+}
+
+fn read_plain_line(prompt: &str) -> io::Result<Input> {
+    print!("{}", prompt);
+    try!(io::stdout().flush());
+
+    let mut line = String::new();
+    match try!(io::stdin().read_line(&mut line)) {
+        0 => Ok(Input::Eof),
+        _ => Ok(Input::Line(line.trim_right_matches(|ch| ch == '\n' || ch == '\r').to_string())),
+    }
+}
+
+// Everything but these can be part of a symbol
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || "(){}[]'\";".contains(ch)
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let first = &words[0];
+    let mut len = first.len();
+
+    for word in &words[1..] {
+        len = first.char_indices()
+            .zip(word.chars())
+            .take_while(|&((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, ch), _)| index + ch.len_utf8())
+            .min(len);
+    }
+
+    first[..len].to_string()
+}
+
+struct LineState<'a> {
+    prompt: &'a str,
+    line: Vec<char>,
+    cursor: usize,
+
+    history: &'a [String],
+    history_index: usize, // history.len() while editing a new line
+    draft: Vec<char>, // The new line, while looking back through history
+}
+
+impl<'a> LineState<'a> {
+    fn new(prompt: &'a str, history: &'a [String]) -> LineState<'a> {
+        LineState {
+            prompt: prompt,
+            line: Vec::new(),
+            cursor: 0,
+            history: history,
+            history_index: history.len(),
+            draft: Vec::new(),
+        }
+    }
+
+    fn edit<F: Fn(&str) -> Vec<String>>(mut self, complete: &F) -> io::Result<Input> {
+        let stdin = io::stdin();
+        let mut input = stdin.lock();
+
+        try!(self.redraw());
+
+        loop {
+            let byte = match try!(next_byte(&mut input)) {
+                Some(byte) => byte,
+                None => return Ok(Input::Eof),
+            };
+
+            match byte {
+                b'\r' | b'\n' => {
+                    try!(write_out("\r\n"));
+                    return Ok(Input::Line(self.line.iter().collect()));
+                },
+                3 => { // Ctrl-C
+                    try!(write_out("^C\r\n"));
+                    return Ok(Input::Interrupted);
+                },
+                4 if self.line.is_empty() => { // Ctrl-D
+                    try!(write_out("\r\n"));
+                    return Ok(Input::Eof);
+                },
+                4 => self.delete(),
+                1 => self.cursor = 0, // Ctrl-A
+                5 => self.cursor = self.line.len(), // Ctrl-E
+                2 => self.left(), // Ctrl-B
+                6 => self.right(), // Ctrl-F
+                8 | 127 => self.backspace(),
+                11 => self.line.truncate(self.cursor), // Ctrl-K
+                21 => { // Ctrl-U
+                    self.line.drain(..self.cursor);
+                    self.cursor = 0;
+                },
+                23 => self.delete_word(), // Ctrl-W
+                12 => try!(write_out("\x1b[H\x1b[2J")), // Ctrl-L
+                16 => self.history_prev(), // Ctrl-P
+                14 => self.history_next(), // Ctrl-N
+                b'\t' => try!(self.complete(complete)),
+                27 => try!(self.escape(&mut input)),
+                byte if byte >= 0x20 => {
+                    let ch = try!(read_char(&mut input, byte));
+                    self.line.insert(self.cursor, ch);
+                    self.cursor += 1;
+                },
+                _ => (),
+            }
+
+            try!(self.redraw());
+        }
+    }
+
+    // The rest of an escape sequence, for the arrow, home, end and delete keys
+    fn escape<R: Read>(&mut self, input: &mut R) -> io::Result<()> {
+        let kind = try!(next_byte(input));
+        if kind != Some(b'[') && kind != Some(b'O') {
+            return Ok(());
+        }
+
+        let mut sequence = Vec::new();
+        while let Some(byte) = try!(next_byte(input)) {
+            sequence.push(byte);
+
+            if byte >= 0x40 && byte <= 0x7e {
+                break;
+            }
+        }
+
+        match &sequence[..] {
+            b"A" => self.history_prev(),
+            b"B" => self.history_next(),
+            b"C" => self.right(),
+            b"D" => self.left(),
+            b"H" | b"1~" | b"7~" => self.cursor = 0,
+            b"F" | b"4~" | b"8~" => self.cursor = self.line.len(),
+            b"3~" => self.delete(),
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn left(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+        }
+    }
+
+    fn right(&mut self) {
+        if self.cursor < self.line.len() {
+            self.cursor += 1;
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.line.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.line.len() {
+            self.line.remove(self.cursor);
+        }
+    }
+
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.line[start - 1].is_whitespace() {
+            start -= 1;
+        }
+
+        self.line.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn history_prev(&mut self) {
+        if self.history_index == 0 {
+            return;
+        }
+
+        if self.history_index == self.history.len() {
+            self.draft = self.line.clone();
+        }
+
+        self.history_index -= 1;
+        let line = self.history[self.history_index].chars().collect();
+        self.set_line(line);
+    }
+
+    fn history_next(&mut self) {
+        if self.history_index == self.history.len() {
+            return;
+        }
+
+        self.history_index += 1;
+        let line = match self.history.get(self.history_index) {
+            Some(line) => line.chars().collect(),
+            None => self.draft.clone(),
+        };
+
+        self.set_line(line);
+    }
+
+    fn set_line(&mut self, line: Vec<char>) {
+        self.cursor = line.len();
+        self.line = line;
+    }
+
+    fn complete<F: Fn(&str) -> Vec<String>>(&mut self, complete: &F) -> io::Result<()> {
+        let start = self.line[..self.cursor].iter().rposition(|&ch| is_delimiter(ch)).map_or(0, |pos| pos + 1);
+        let word: String = self.line[start..self.cursor].iter().collect();
+
+        let candidates = complete(&word);
+        if candidates.is_empty() {
+            return write_out("\x07");
+        }
+
+        let prefix = common_prefix(&candidates);
+        if prefix.chars().count() > word.chars().count() {
+            for ch in prefix.chars().skip(word.chars().count()) {
+                self.line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+
+            return Ok(());
+        }
+
+        // Nothing more can be filled in, so the choices are listed instead
+        let width = candidates.iter().map(|name| name.chars().count()).max().unwrap_or(0) + 2;
+        let columns = ::std::cmp::max(1, 80 / width);
+
+        let mut out = String::from("\r\n");
+        for (index, name) in candidates.iter().enumerate() {
+            out.push_str(&format!("{:1$}", name, width));
+
+            if (index + 1) % columns == 0 || index + 1 == candidates.len() {
+                out.push_str("\r\n");
+            }
+        }
+
+        write_out(&out)
+    }
+
+    fn redraw(&self) -> io::Result<()> {
+        let line: String = self.line.iter().collect();
+        let mut out = format!("\r{}{}\x1b[K", self.prompt, line);
+
+        if self.cursor < self.line.len() {
+            out.push_str(&format!("\x1b[{}D", self.line.len() - self.cursor));
+        }
+
+        write_out(&out)
+    }
+}
+
+fn write_out(text: &str) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    try!(out.write_all(text.as_bytes()));
+    out.flush()
+}
+
+fn next_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+
+    match try!(input.read(&mut byte)) {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+// Reads the rest of a UTF-8 character that starts with `first`
+fn read_char<R: Read>(input: &mut R, first: u8) -> io::Result<char> {
+    let len = if first >= 0xf0 {
+        4
+    } else if first >= 0xe0 {
+        3
+    } else if first >= 0xc0 {
+        2
+    } else {
+        1
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match try!(next_byte(input)) {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).chars().next().unwrap_or('\u{FFFD}'))
+}
@@ -0,0 +1,500 @@
+//! The rlisp interpreter.
+//!
+//! rlisp                  starts a REPL, or runs stdin as a script if it isn't a terminal
+//! rlisp -i               starts a REPL even if stdin isn't a terminal, reading plain lines from it
+//! rlisp FILE             runs a script, - reads it from stdin
+//! rlisp -e EXPR          evaluates an expression and prints its value
+//! rlisp -d FILE          runs a script under the debugger, paused before its first form
+//! rlisp --profile FILE   runs a script, then prints how long was spent in each function to stderr
+//! rlisp --folded FILE    the same, but as folded stacks for flame graph tools
+//! rlisp --test [--filter TEXT] [--junit FILE] [PATH...]
+//!                        runs the tests in the test files in each path (. by default)
+//! rlisp --docs [FILE...]
+//!                        runs the files, then prints a Markdown reference for everything defined
+
+extern crate rlisp;
+
+mod editor;
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::process;
+
+use rlisp::{Lisp, Env, Value, Symbol, Reader};
+use rlisp::parse::{self, FilePos};
+use rlisp::reader::Next;
+use rlisp::debug::{Debugger, Breakpoint};
+use rlisp::testing;
+use rlisp::docs;
+
+use editor::{Editor, Input};
+
+const USAGE: &'static str = "usage: rlisp [-i | FILE | -e EXPR | -d FILE | --profile FILE | --folded FILE]
+       rlisp --test [--filter TEXT] [--junit FILE] [PATH...]
+       rlisp --docs [FILE...]";
+
+const COMMANDS: [(&'static str, &'static str); 10] = [
+    (":help", "shows this message"),
+    (":doc NAME", "shows how a function is called and what it does"),
+    (":load FILE", "runs a file in the current environment"),
+    (":reset", "starts over with a fresh environment"),
+    (":env", "lists everything you've defined"),
+    (":debug", "turns the debugger on or off, it pauses on errors, breakpoints and (break)"),
+    (":break [BP]", "adds a breakpoint on a function NAME or a FILE:LINE, or lists them"),
+    (":delete N", "removes breakpoint N"),
+    (":profile EXPR", "evaluates an expression, showing the time spent in each function"),
+    (":quit", "leaves the REPL, as does Ctrl-D"),
+];
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().map_or(false, |arg| arg == "--test") {
+        return run_tests(&args[1..]);
+    }
+
+    if args.first().map_or(false, |arg| arg == "--docs") {
+        return print_docs(&args[1..]);
+    }
+
+    match args.len() {
+        0 if editor::is_terminal() => repl(),
+        0 => run_script("-"),
+        1 if args[0] == "-h" || args[0] == "--help" => println!("{}", USAGE),
+        1 if args[0] == "-i" => repl(),
+        1 if !args[0].starts_with("-") || args[0] == "-" => run_script(&args[0]),
+        2 if args[0] == "-e" => eval_expr(&args[1]),
+        2 if args[0] == "-d" => debug_script(&args[1]),
+        2 if args[0] == "--profile" => profile_script(&args[1], false),
+        2 if args[0] == "--folded" => profile_script(&args[1], true),
+        _ => usage(),
+    }
+}
+
+fn read_source(path: &str) -> io::Result<String> {
+    let mut code = String::new();
+
+    match path {
+        "-" => try!(io::stdin().read_to_string(&mut code)),
+        _ => try!(try!(File::open(path)).read_to_string(&mut code)),
+    };
+
+    Ok(code)
+}
+
+/// Runs each top level form of the source in turn, registering it with the debugger if there is one.
+/// Errors are given as `name:line:col: error`, pointing at the form that failed.
+fn eval_source(lisp: &mut Lisp, debugger: Option<&Debugger>, name: &str, code: &str) -> Result<Value, Vec<String>> {
+    let (nodes, diagnostics) = parse::parse_recovering(code);
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics.iter()
+            .map(|diagnostic| {
+                let pos = FilePos::from_offset(code, diagnostic.span.start);
+                format!("{}:{}:{}: {:?}", name, pos.line, pos.col, diagnostic.error)
+            })
+            .collect());
+    }
+
+    // There aren't any syntax errors, so there's a form for every node
+    let forms = match debugger {
+        Some(debugger) => debugger.register(name, code, &nodes),
+        None => nodes.iter().map(|node| node.to_value().unwrap()).collect(),
+    };
+
+    let mut result = Value::Nil;
+
+    for (node, form) in nodes.iter().zip(forms) {
+        result = match lisp.eval_token(form) {
+            Ok(value) => value,
+            Err(err) => {
+                lisp.scopes.truncate(1);
+
+                let pos = FilePos::from_offset(code, node.span.start);
+                return Err(vec![format!("{}:{}:{}: {:?}", name, pos.line, pos.col, err)]);
+            },
+        };
+    }
+
+    Ok(result)
+}
+
+fn run_script(path: &str) {
+    process::exit(run_file(&mut Lisp::new(), path, None));
+}
+
+fn debug_script(path: &str) {
+    let debugger = Debugger::new();
+    debugger.step();
+
+    process::exit(run_file(&mut Lisp::new(), path, Some(&debugger)));
+}
+
+// The profile is printed even if the script fails
+fn profile_script(path: &str, folded: bool) {
+    let mut lisp = Lisp::new();
+    lisp.start_profiling();
+
+    let status = run_file(&mut lisp, path, None);
+
+    let profile = lisp.stop_profiling().unwrap();
+    match folded {
+        true => eprint!("{}", profile.folded()),
+        false => eprint!("{}", profile.table()),
+    }
+
+    process::exit(status);
+}
+
+// Gives the exit code, unless the file can't be read
+fn run_file(lisp: &mut Lisp, path: &str, debugger: Option<&Debugger>) -> i32 {
+    let name = if path == "-" { "<stdin>" } else { path };
+
+    let code = match read_source(path) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}: {}", name, err);
+            process::exit(2);
+        },
+    };
+
+    if let Some(debugger) = debugger {
+        debugger.attach(lisp);
+    }
+
+    match eval_source(lisp, debugger, name, &code) {
+        Ok(_) => 0,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+
+            1
+        },
+    }
+}
+
+fn run_tests(args: &[String]) {
+    let mut filter = None;
+    let mut junit = None;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--filter" => filter = Some(args.next().unwrap_or_else(|| usage()).clone()),
+            "--junit" => junit = Some(args.next().unwrap_or_else(|| usage()).clone()),
+            _ if arg.starts_with("-") => usage(),
+            _ => paths.push(arg.clone()),
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push(".".to_string());
+    }
+
+    let mut results = Vec::new();
+    let mut status = 0;
+
+    for path in testing::discover(&paths) {
+        let name = path.to_string_lossy().into_owned();
+
+        let code = match read_source(&name) {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                status = 2;
+                continue;
+            },
+        };
+
+        for result in testing::run_source(&name, &code, filter.as_ref().map(|filter| &filter[..])) {
+            println!("test {} {} ... {}", name, result.name, if result.passed() { "ok" } else { "FAILED" });
+            results.push(result);
+        }
+    }
+
+    let failed: Vec<&testing::TestResult> = results.iter().filter(|result| !result.passed()).collect();
+
+    if !failed.is_empty() {
+        println!("\nfailures:");
+
+        for result in &failed {
+            let location = match result.line {
+                Some(line) => format!("{}:{}", result.file, line),
+                None => result.file.clone(),
+            };
+
+            println!("\n---- {} {} ----\n{}", location, result.name, result.failure().unwrap_or_default().trim_right());
+        }
+
+        status = 1;
+    }
+
+    println!("\ntest result: {}. {} passed; {} failed", if failed.is_empty() { "ok" } else { "FAILED" },
+             results.len() - failed.len(), failed.len());
+
+    if let Some(junit) = junit {
+        if let Err(err) = fs::write(&junit, testing::junit_xml(&results)) {
+            eprintln!("{}: {}", junit, err);
+            status = 2;
+        }
+    }
+
+    process::exit(status);
+}
+
+// Documents the standard library, along with whatever the files define
+fn print_docs(paths: &[String]) {
+    let mut lisp = Lisp::new();
+
+    for path in paths {
+        if path.starts_with("-") && path != "-" {
+            usage();
+        }
+
+        let status = run_file(&mut lisp, path, None);
+        if status != 0 {
+            process::exit(status);
+        }
+    }
+
+    print!("{}", docs::markdown(&lisp.scopes[0], "rlisp reference"));
+}
+
+fn eval_expr(expr: &str) {
+    match eval_source(&mut Lisp::new(), None, "-e", expr) {
+        Ok(Value::Nil) => (),
+        Ok(value) => println!("{:#?}", value),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+
+            process::exit(1);
+        },
+    }
+}
+
+fn repl() {
+    println!("rlisp {}, :help for help, Ctrl-D to quit", env!("CARGO_PKG_VERSION"));
+
+    let mut editor = Editor::new(editor::history_file());
+    let mut lisp = Lisp::new();
+    let mut reader = Reader::new();
+    let mut debugger: Option<Debugger> = None;
+
+    loop {
+        // A different prompt while the previous line left a form open
+        let prompt = if reader.is_empty() { ">>> " } else { "... " };
+
+        let line = {
+            let lisp = &lisp;
+            let at_start = reader.is_empty();
+
+            match editor.read_line(prompt, |word| complete(lisp, word, at_start)) {
+                Ok(Input::Line(line)) => line,
+                Ok(Input::Interrupted) => {
+                    reader.clear();
+                    continue;
+                },
+                Ok(Input::Eof) => break,
+                Err(err) => {
+                    eprintln!("Error reading input: {}", err);
+                    break;
+                },
+            }
+        };
+
+        editor.add_history(&line);
+
+        if reader.is_empty() && line.trim_left().starts_with(':') {
+            match command(&mut lisp, &mut debugger, line.trim()) {
+                true => continue,
+                false => break,
+            }
+        }
+
+        reader.feed(&line);
+        reader.feed("\n");
+
+        loop {
+            match reader.next_form() {
+                Ok(Next::Form(form)) => {
+                    match lisp.eval_token(form) {
+                        Ok(val) => println!("=> {:#?}", val),
+                        Err(err) => {
+                            println!("Error: {:?}", err);
+                            lisp.scopes.truncate(1);
+                        },
+                    }
+
+                    println!("");
+                },
+                Ok(Next::NeedMoreInput) => break,
+                Err(err) => {
+                    println!("Error: {:?}\n", err);
+                    reader.clear();
+                    break;
+                },
+            }
+        }
+    }
+}
+
+// Runs a meta command, returning false if the REPL should stop
+fn command(lisp: &mut Lisp, debugger: &mut Option<Debugger>, line: &str) -> bool {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let (name, arg) = (parts.next().unwrap_or(""), parts.next().unwrap_or("").trim());
+
+    match name {
+        ":help" | ":h" => {
+            println!("Type an expression to evaluate it, it can span as many lines as it needs.\n");
+            for &(command, help) in COMMANDS.iter() {
+                println!("  {:14} {}", command, help);
+            }
+        },
+        ":doc" if !arg.is_empty() => {
+            match docs::lookup(lisp, Symbol::intern(arg)) {
+                Some(doc) => println!("{}", doc.to_text()),
+                None => println!("{} isn't a function", arg),
+            }
+        },
+        ":doc" => println!("usage: :doc NAME"),
+        ":load" | ":l" if !arg.is_empty() => {
+            match read_source(arg).map_err(|err| vec![format!("{}: {}", arg, err)])
+                .and_then(|code| eval_source(lisp, debugger.as_ref(), arg, &code))
+            {
+                Ok(val) => println!("=> {:#?}", val),
+                Err(errors) => for error in errors {
+                    println!("Error: {}", error);
+                },
+            }
+        },
+        ":load" | ":l" => println!("usage: :load FILE"),
+        ":reset" => {
+            *lisp = Lisp::new();
+            if let Some(ref debugger) = *debugger {
+                debugger.attach(lisp);
+            }
+
+            println!("The environment has been reset");
+        },
+        ":env" => {
+            let defined = user_definitions(lisp);
+            if defined.is_empty() {
+                println!("Nothing has been defined yet");
+            }
+
+            for (name, value) in defined {
+                println!("{} = {:?}", name, value);
+            }
+        },
+        ":debug" => {
+            match debugger.take() {
+                Some(_) => {
+                    lisp.take_hook();
+                    println!("The debugger is off");
+                },
+                None => {
+                    let new = Debugger::new();
+                    new.attach(lisp);
+                    *debugger = Some(new);
+
+                    println!("The debugger is on, it pauses on errors, breakpoints and (break)");
+                },
+            }
+        },
+        ":break" | ":b" => {
+            let debugger = match *debugger {
+                Some(ref debugger) => debugger,
+                None => {
+                    println!("The debugger is off, :debug turns it on\n");
+                    return true;
+                },
+            };
+
+            if arg.is_empty() {
+                let breakpoints = debugger.breakpoints();
+                if breakpoints.is_empty() {
+                    println!("No breakpoints");
+                }
+
+                for (number, breakpoint) in breakpoints {
+                    println!("{}: {}", number, breakpoint);
+                }
+            } else {
+                let breakpoint = Breakpoint::parse(arg);
+                println!("Breakpoint {} on {}", debugger.add_breakpoint(breakpoint.clone()), breakpoint);
+            }
+        },
+        ":delete" => {
+            let deleted = match (debugger.as_ref(), arg.parse()) {
+                (Some(debugger), Ok(number)) => debugger.remove_breakpoint(number),
+                _ => false,
+            };
+
+            match deleted {
+                true => println!("Deleted breakpoint {}", arg),
+                false => println!("No breakpoint {}", arg),
+            }
+        },
+        ":profile" if !arg.is_empty() => {
+            lisp.start_profiling();
+            let result = eval_source(lisp, None, ":profile", arg);
+            let profile = lisp.stop_profiling().unwrap();
+
+            match result {
+                Ok(val) => println!("=> {:#?}\n", val),
+                Err(errors) => for error in errors {
+                    println!("Error: {}\n", error);
+                },
+            }
+
+            print!("{}", profile.table());
+        },
+        ":profile" => println!("usage: :profile EXPR"),
+        ":quit" | ":q" => return false,
+        _ => println!("Unknown command {}, :help lists them", name),
+    }
+
+    println!("");
+    true
+}
+
+// Global bindings that aren't part of the standard library, or that have been redefined
+fn user_definitions(lisp: &Lisp) -> Vec<(String, Value)> {
+    let std_lib = Env::std_lib();
+
+    let mut defined: Vec<(String, Value)> = lisp.scopes[0].map.iter()
+        .filter(|&(&name, value)| std_lib.get(name) != Some(value))
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+
+    defined.sort_by(|a, b| a.0.cmp(&b.0));
+    defined
+}
+
+fn complete(lisp: &Lisp, word: &str, at_start: bool) -> Vec<String> {
+    if at_start && word.starts_with(':') {
+        return COMMANDS.iter()
+            .map(|&(command, _)| command.split_whitespace().next().unwrap().to_string())
+            .filter(|command| command.starts_with(word))
+            .collect();
+    }
+
+    let names: BTreeSet<&str> = lisp.scopes.iter()
+        .flat_map(|scope| scope.map.keys())
+        .map(|name| name.as_str())
+        .filter(|name| name.starts_with(word))
+        .collect();
+
+    names.into_iter().map(String::from).collect()
+}
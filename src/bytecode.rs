@@ -0,0 +1,499 @@
+//! A binary format for compiled code, so scripts can be loaded without being parsed again.
+//!
+//! Every file starts with a fixed header:
+//!
+//! ```text
+//! magic     8 bytes   "\0rlispbc"
+//! version   u16       FORMAT_VERSION of the writer
+//! checksum  u32       FNV-1a of everything after this field
+//! source    u8 flag, then a u32 length and that many bytes of UTF-8 if the flag is set
+//! ```
+//!
+//! The header's layout never changes between versions, so the embedded source (if any) can always be
+//! recovered and used as a fallback when the rest of the file was written by a different version.
+//! After the header comes the chunk itself: the number of local slots it uses, its constants, then its code.
+//! All integers are little endian.
+
+use compile::{Op, Chunk};
+use eval::FuncError;
+use list::List;
+use symbol::Symbol;
+use value::Value;
+
+pub const MAGIC: &'static [u8; 8] = b"\0rlispbc";
+
+/// Bumped whenever the encoding of chunks, or the meaning of any op, changes
+pub const FORMAT_VERSION: u16 = 2;
+
+/// How deeply lists and quotes can be nested in a constant, deeper ones aren't written and aren't read,
+/// since both are done recursively
+pub const MAX_DEPTH: usize = 256;
+
+/// A compiled file that's been read back in
+pub enum Compiled {
+    Chunk(Chunk),
+    /// The file was written by another version, but it came with its source
+    Source(String),
+}
+
+pub fn write(chunk: &Chunk, source: Option<&str>) -> Result<Vec<u8>, FuncError> {
+    let mut body = Writer { bytes: Vec::new() };
+
+    match source {
+        Some(source) => {
+            body.u8(1);
+            body.str(source);
+        },
+        None => body.u8(0),
+    }
+
+    body.u32(chunk.locals as u32);
+
+    body.u32(chunk.constants.len() as u32);
+    for constant in &chunk.constants {
+        try!(body.value(constant, 0));
+    }
+
+    body.u32(chunk.code.len() as u32);
+    for op in &chunk.code {
+        body.op(op);
+    }
+
+    let mut out = Writer { bytes: Vec::with_capacity(body.bytes.len() + 14) };
+    out.bytes.extend_from_slice(MAGIC);
+    out.u16(FORMAT_VERSION);
+    out.u32(checksum(&body.bytes));
+    out.bytes.extend_from_slice(&body.bytes);
+
+    Ok(out.bytes)
+}
+
+pub fn read(bytes: &[u8]) -> Result<Compiled, FuncError> {
+    if bytes.len() < 14 || &bytes[..8] != MAGIC {
+        return Err(invalid("not a compiled rlisp file"));
+    }
+
+    let mut reader = Reader { bytes: bytes, pos: 8 };
+    let version = try!(reader.u16());
+    let expected_sum = try!(reader.u32());
+
+    if checksum(&bytes[14..]) != expected_sum {
+        return Err(invalid("checksum mismatch, the file is corrupt"));
+    }
+
+    let source = match try!(reader.u8()) {
+        0 => None,
+        _ => Some(try!(reader.str())),
+    };
+
+    if version != FORMAT_VERSION {
+        return match source {
+            Some(source) => Ok(Compiled::Source(source)),
+            None => Err(FuncError::BytecodeVersion {
+                expected: FORMAT_VERSION,
+                found: version,
+            }),
+        };
+    }
+
+    let mut chunk = Chunk::new();
+    chunk.locals = try!(reader.index());
+
+    let constants = try!(reader.u32());
+    for _ in 0..constants {
+        chunk.constants.push(try!(reader.value(0)));
+    }
+
+    let ops = try!(reader.u32());
+    for _ in 0..ops {
+        chunk.code.push(try!(reader.op()));
+    }
+
+    if reader.pos != bytes.len() {
+        return Err(invalid("unexpected data after the end of the code"));
+    }
+
+    try!(validate(&chunk));
+    Ok(Compiled::Chunk(chunk))
+}
+
+// The VM trusts its chunks, so make sure nothing in a loaded one can index out of bounds or underflow
+fn validate(chunk: &Chunk) -> Result<(), FuncError> {
+    if chunk.code.last() != Some(&Op::Return) {
+        return Err(invalid("code does not end with a return"));
+    }
+
+    let consts = chunk.constants.len();
+    let ops = chunk.code.len();
+
+    for op in &chunk.code {
+        let in_bounds = match *op {
+            Op::Const(index) => index < consts,
+            Op::Call { form, .. } | Op::TailCall { form, .. } => form < consts,
+            Op::Jump(target) | Op::JumpIfFalse(target) => target < ops,
+            Op::LoadLocal(slot) | Op::StoreLocal(slot) => slot < chunk.locals,
+            _ => true,
+        };
+
+        if !in_bounds {
+            return Err(invalid(&format!("invalid instruction {:?}", op)));
+        }
+    }
+
+    // Every slot comes from a binding, which takes at least one instruction
+    if chunk.locals > ops {
+        return Err(invalid("more local slots than instructions"));
+    }
+
+    check_depths(chunk)
+}
+
+// Follows every path through the code, including both sides of each jump, keeping track of how deep the stack and
+// the scopes pushed by the chunk are. Compiled code never pops what isn't there, gets to each instruction
+// with the same depths whichever way it's reached, and returns exactly one value with all of its scopes popped.
+fn check_depths(chunk: &Chunk) -> Result<(), FuncError> {
+    // The (stack, scope) depth on the way into each instruction, None until it's been reached
+    let mut depths: Vec<Option<(usize, usize)>> = vec![None; chunk.code.len()];
+    let mut pending = vec![(0, 0, 0)];
+
+    while let Some((ip, stack, scopes)) = pending.pop() {
+        match depths[ip] {
+            Some(seen) if seen == (stack, scopes) => continue,
+            Some(_) => return Err(invalid(&format!("instruction {} is reached with different stack depths", ip))),
+            None => depths[ip] = Some((stack, scopes)),
+        }
+
+        let op = chunk.code[ip];
+
+        // How many values and scopes the op takes, then how many it leaves
+        let (pops, pushes, scope_pops, scope_pushes) = match op {
+            Op::Const(_) | Op::LoadLocal(_) | Op::LoadName(_) => (0, 1, 0, 0),
+            Op::StoreLocal(_) => (1, 1, 0, 0),
+            Op::PushScope => (0, 0, 0, 1),
+            Op::PopScope => (0, 0, 1, 0),
+            Op::Bind(_) | Op::Pop | Op::JumpIfFalse(_) | Op::Return => (1, 0, 0, 0),
+            Op::Jump(_) | Op::EvalEmptyList => (0, 0, 0, 0),
+            // A call also ends the scope pushed before its arguments
+            Op::Call { args, .. } | Op::TailCall { args, .. } => (args.saturating_add(1), 1, 1, 0),
+        };
+
+        if pops > stack {
+            return Err(invalid(&format!("instruction {} ({:?}) pops more values than there are on the stack", ip, op)));
+        }
+
+        if scope_pops > scopes {
+            return Err(invalid(&format!("instruction {} ({:?}) pops a scope it didn't push", ip, op)));
+        }
+
+        let stack = stack - pops + pushes;
+        let scopes = scopes - scope_pops + scope_pushes;
+
+        match op {
+            Op::Return if stack != 0 || scopes != 0 => {
+                return Err(invalid(&format!("instruction {} returns with values or scopes left over", ip)));
+            },
+            Op::Return | Op::EvalEmptyList => (),
+            Op::Jump(target) => pending.push((target, stack, scopes)),
+            Op::JumpIfFalse(target) => {
+                pending.push((target, stack, scopes));
+                pending.push((ip + 1, stack, scopes));
+            },
+            // Only a return can be last, so there's always a next instruction
+            _ => pending.push((ip + 1, stack, scopes)),
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid(reason: &str) -> FuncError {
+    FuncError::InvalidBytecode(reason.to_string())
+}
+
+// 32 bit FNV-1a, which is plenty for catching truncated or damaged files
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+
+    hash
+}
+
+mod tag {
+    pub const NIL: u8 = 0;
+    pub const BOOL: u8 = 1;
+    pub const NUMBER: u8 = 2;
+    pub const CHAR: u8 = 3;
+    pub const SYMBOL: u8 = 4;
+    pub const STRING: u8 = 5;
+    pub const BYTES: u8 = 6;
+    pub const LIST: u8 = 7;
+    pub const QUOTE: u8 = 8;
+
+    pub const CONST: u8 = 0;
+    pub const LOAD_LOCAL: u8 = 1;
+    pub const LOAD_NAME: u8 = 2;
+    pub const PUSH_SCOPE: u8 = 3;
+    pub const POP_SCOPE: u8 = 4;
+    pub const BIND: u8 = 5;
+    pub const POP: u8 = 6;
+    pub const JUMP: u8 = 7;
+    pub const JUMP_IF_FALSE: u8 = 8;
+    pub const CALL: u8 = 9;
+    pub const TAIL_CALL: u8 = 10;
+    pub const RETURN: u8 = 11;
+    pub const EVAL_EMPTY_LIST: u8 = 12;
+    pub const STORE_LOCAL: u8 = 13;
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, n: u8) {
+        self.bytes.push(n);
+    }
+
+    fn u16(&mut self, n: u16) {
+        self.bytes.push(n as u8);
+        self.bytes.push((n >> 8) as u8);
+    }
+
+    fn u32(&mut self, n: u32) {
+        for shift in 0..4 {
+            self.bytes.push((n >> (shift * 8)) as u8);
+        }
+    }
+
+    fn blob(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn str(&mut self, string: &str) {
+        self.blob(string.as_bytes());
+    }
+
+    // Only values that can appear in source code are written, anything else only exists at runtime
+    fn value(&mut self, value: &Value, depth: usize) -> Result<(), FuncError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("constant is nested too deeply"));
+        }
+
+        match value {
+            &Value::Nil => self.u8(tag::NIL),
+            &Value::Bool(val) => {
+                self.u8(tag::BOOL);
+                self.u8(val as u8);
+            },
+            &Value::Number(num) => {
+                self.u8(tag::NUMBER);
+                self.u32(num.to_bits());
+            },
+            &Value::Char(ch) => {
+                self.u8(tag::CHAR);
+                self.u32(ch as u32);
+            },
+            &Value::Symbol(sym) => {
+                self.u8(tag::SYMBOL);
+                self.str(sym.as_str());
+            },
+            &Value::String(ref string) => {
+                self.u8(tag::STRING);
+                self.str(string);
+            },
+            &Value::Bytes(ref bytes) => {
+                self.u8(tag::BYTES);
+                self.blob(bytes);
+            },
+            &Value::List(ref list) => {
+                self.u8(tag::LIST);
+                self.u32(list.len() as u32);
+
+                for item in list {
+                    try!(self.value(item, depth + 1));
+                }
+            },
+            &Value::Quote(ref inner) => {
+                self.u8(tag::QUOTE);
+                try!(self.value(inner, depth + 1));
+            },
+            other => return Err(invalid(&format!("{:?} cannot be written to a compiled file", other))),
+        }
+
+        Ok(())
+    }
+
+    fn op(&mut self, op: &Op) {
+        match *op {
+            Op::Const(index) => {
+                self.u8(tag::CONST);
+                self.u32(index as u32);
+            },
+            Op::LoadLocal(slot) => {
+                self.u8(tag::LOAD_LOCAL);
+                self.u32(slot as u32);
+            },
+            Op::StoreLocal(slot) => {
+                self.u8(tag::STORE_LOCAL);
+                self.u32(slot as u32);
+            },
+            Op::LoadName(sym) => {
+                self.u8(tag::LOAD_NAME);
+                self.str(sym.as_str());
+            },
+            Op::PushScope => self.u8(tag::PUSH_SCOPE),
+            Op::PopScope => self.u8(tag::POP_SCOPE),
+            Op::Bind(sym) => {
+                self.u8(tag::BIND);
+                self.str(sym.as_str());
+            },
+            Op::Pop => self.u8(tag::POP),
+            Op::Jump(target) => {
+                self.u8(tag::JUMP);
+                self.u32(target as u32);
+            },
+            Op::JumpIfFalse(target) => {
+                self.u8(tag::JUMP_IF_FALSE);
+                self.u32(target as u32);
+            },
+            Op::Call { args, form } => {
+                self.u8(tag::CALL);
+                self.u32(args as u32);
+                self.u32(form as u32);
+            },
+            Op::TailCall { args, form } => {
+                self.u8(tag::TAIL_CALL);
+                self.u32(args as u32);
+                self.u32(form as u32);
+            },
+            Op::Return => self.u8(tag::RETURN),
+            Op::EvalEmptyList => self.u8(tag::EVAL_EMPTY_LIST),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FuncError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(invalid("unexpected end of file"));
+        }
+
+        let taken = &self.bytes[self.pos .. self.pos + len];
+        self.pos += len;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, FuncError> {
+        Ok(try!(self.take(1))[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, FuncError> {
+        let bytes = try!(self.take(2));
+        Ok(bytes[0] as u16 | (bytes[1] as u16) << 8)
+    }
+
+    fn u32(&mut self) -> Result<u32, FuncError> {
+        let bytes = try!(self.take(4));
+        Ok(bytes.iter().rev().fold(0, |n, &byte| n << 8 | byte as u32))
+    }
+
+    fn index(&mut self) -> Result<usize, FuncError> {
+        self.u32().map(|n| n as usize)
+    }
+
+    fn blob(&mut self) -> Result<&'a [u8], FuncError> {
+        let len = try!(self.index());
+        self.take(len)
+    }
+
+    fn str(&mut self) -> Result<String, FuncError> {
+        let bytes = try!(self.blob());
+
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(invalid("string is not valid UTF-8")),
+        }
+    }
+
+    fn symbol(&mut self) -> Result<Symbol, FuncError> {
+        let name = try!(self.str());
+        Symbol::try_intern(&name).ok_or_else(|| invalid("too many distinct symbols"))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, FuncError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("constant is nested too deeply"));
+        }
+
+        // Only lists and quotes recurse, everything else is read by atom so this frame stays small
+        match try!(self.u8()) {
+            tag::LIST => {
+                let len = try!(self.index());
+
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(try!(self.value(depth + 1)));
+                }
+
+                Ok(Value::List(List::from(items)))
+            },
+            tag::QUOTE => Ok(Value::Quote(Box::new(try!(self.value(depth + 1))))),
+            other => self.atom(other),
+        }
+    }
+
+    fn atom(&mut self, tag: u8) -> Result<Value, FuncError> {
+        let value = match tag {
+            tag::NIL => Value::Nil,
+            tag::BOOL => Value::Bool(try!(self.u8()) != 0),
+            tag::NUMBER => Value::Number(f32::from_bits(try!(self.u32()))),
+            tag::CHAR => {
+                let code = try!(self.u32());
+
+                match ::std::char::from_u32(code) {
+                    Some(ch) => Value::Char(ch),
+                    None => return Err(invalid("invalid character")),
+                }
+            },
+            tag::SYMBOL => Value::Symbol(try!(self.symbol())),
+            tag::STRING => Value::String(try!(self.str())),
+            tag::BYTES => Value::Bytes(try!(self.blob()).to_vec()),
+            other => return Err(invalid(&format!("unknown value tag {}", other))),
+        };
+
+        Ok(value)
+    }
+
+    fn op(&mut self) -> Result<Op, FuncError> {
+        let op = match try!(self.u8()) {
+            tag::CONST => Op::Const(try!(self.index())),
+            tag::LOAD_LOCAL => Op::LoadLocal(try!(self.index())),
+            tag::STORE_LOCAL => Op::StoreLocal(try!(self.index())),
+            tag::LOAD_NAME => Op::LoadName(try!(self.symbol())),
+            tag::PUSH_SCOPE => Op::PushScope,
+            tag::POP_SCOPE => Op::PopScope,
+            tag::BIND => Op::Bind(try!(self.symbol())),
+            tag::POP => Op::Pop,
+            tag::JUMP => Op::Jump(try!(self.index())),
+            tag::JUMP_IF_FALSE => Op::JumpIfFalse(try!(self.index())),
+            tag::CALL => Op::Call { args: try!(self.index()), form: try!(self.index()) },
+            tag::TAIL_CALL => Op::TailCall { args: try!(self.index()), form: try!(self.index()) },
+            tag::RETURN => Op::Return,
+            tag::EVAL_EMPTY_LIST => Op::EvalEmptyList,
+            other => return Err(invalid(&format!("unknown instruction tag {}", other))),
+        };
+
+        Ok(op)
+    }
+}
@@ -0,0 +1,309 @@
+use std::rc::Rc;
+use std::cmp;
+use std::collections::HashMap;
+
+use value::{Value, Lambda};
+use list::List;
+use symbol::Symbol;
+use eval::Lisp;
+use resolve::{self, Address, CoreForm, Scopes, is_quote, unquote, let_defs};
+
+/// A single bytecode instruction, indices refer to the chunk's constant pool or code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Const(usize),
+    LoadLocal(usize), // Names with a lexical address (see the `resolve` module) live in slots, rather than being looked up by name
+    StoreLocal(usize), // Copies the top of the stack into a slot, without popping it
+    LoadName(Symbol),
+
+    // Mirrors the tree walker, which gives each list it evaluates its own scope
+    PushScope,
+    PopScope,
+    Bind(Symbol),
+
+    Pop,
+    Jump(usize),
+    JumpIfFalse(usize),
+
+    // The callee and its arguments are on the stack, form is the constant holding the callee's source
+    // (which is what's reported when it isn't callable)
+    Call { args: usize, form: usize },
+    TailCall { args: usize, form: usize },
+    Return,
+
+    EvalEmptyList,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<Value>,
+    pub locals: usize, // The number of slots a frame running this chunk needs, including the lambda's arguments
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk {
+            code: Vec::new(),
+            constants: Vec::new(),
+            locals: 0,
+        }
+    }
+}
+
+pub struct Compiler<'a> {
+    lisp: &'a Lisp,
+    chunk: Chunk,
+    scopes: Scopes,
+}
+
+/// Compiles top level code, which is run in whatever scope is current
+pub fn compile_body(lisp: &Lisp, body: &[Value]) -> Chunk {
+    let mut compiler = Compiler::new(lisp);
+    compiler.compile_forms(body, false);
+
+    compiler.finish()
+}
+
+/// Compiles the body of a lambda, its arguments are accessed through slots
+pub fn compile_lambda(lisp: &Lisp, lambda: &Lambda) -> Chunk {
+    let mut compiler = Compiler::new(lisp);
+
+    // Anything that can define into the lambda's scope can change what a parameter's name refers to,
+    // in which case every name has to be looked up the same way the tree walker would
+    let slotted = !lambda.body.iter().any(|form| resolve::mentions_redefinition(lisp, &lambda.args, form));
+
+    compiler.scopes.push(lambda.args.clone(), slotted);
+    compiler.chunk.locals = lambda.args.len();
+    compiler.compile_forms(&lambda.body, true);
+
+    compiler.finish()
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(lisp: &'a Lisp) -> Compiler<'a> {
+        Compiler {
+            lisp: lisp,
+            chunk: Chunk::new(),
+            scopes: Scopes::new(),
+        }
+    }
+
+    pub fn finish(mut self) -> Chunk {
+        self.emit(Op::Return);
+        self.chunk
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.code.len() - 1
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        if let Some(index) = self.chunk.constants.iter().position(|constant| *constant == value) {
+            return index;
+        }
+
+        self.chunk.constants.push(value);
+        self.chunk.constants.len() - 1
+    }
+
+    // Jumps are emitted before their target is known, and patched once it is
+    fn patch_jump(&mut self, at: usize) {
+        let target = self.chunk.code.len();
+
+        self.chunk.code[at] = match self.chunk.code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            _ => unreachable!(),
+        };
+    }
+
+    /// Compiles a sequence of forms, leaving only the value of the last one on the stack
+    pub fn compile_forms(&mut self, forms: &[Value], tail: bool) {
+        if forms.len() == 0 {
+            let nil = self.constant(Value::Nil);
+            self.emit(Op::Const(nil));
+            return;
+        }
+
+        for (index, form) in forms.iter().enumerate() {
+            let is_last = index == forms.len() - 1;
+            self.compile(form, tail && is_last);
+
+            if !is_last {
+                self.emit(Op::Pop);
+            }
+        }
+    }
+
+    pub fn compile(&mut self, form: &Value, tail: bool) {
+        match form {
+            &Value::Symbol(sym) => self.compile_symbol(sym),
+            &Value::List(ref list) => self.compile_list(list, tail),
+            &Value::Quote(ref inner) => {
+                let index = self.constant((**inner).clone());
+                self.emit(Op::Const(index));
+            },
+            other => {
+                let index = self.constant(other.clone());
+                self.emit(Op::Const(index));
+            },
+        }
+    }
+
+    fn compile_symbol(&mut self, sym: Symbol) {
+        match self.scopes.lookup(sym) {
+            Some(Address::Local { depth, slot }) => {
+                let slot = self.scopes.flat_slot(depth, slot);
+                self.emit(Op::LoadLocal(slot))
+            },
+            _ => self.emit(Op::LoadName(sym)),
+        };
+    }
+
+    fn compile_list(&mut self, list: &List, tail: bool) {
+        let (head, args) = match (list.head(), list.tail()) {
+            (Some(head), Some(tail)) => (head, tail.to_vec()),
+            _ => {
+                self.emit(Op::EvalEmptyList);
+                return;
+            },
+        };
+
+        if let Some(form) = resolve::core_form(self.lisp, head) {
+            if self.compile_core_form(form, &args, tail) {
+                return;
+            }
+        }
+
+        self.compile(head, false);
+        self.emit(Op::PushScope);
+
+        for arg in &args {
+            self.compile(arg, false);
+        }
+
+        let form = self.constant(head.clone());
+        if tail {
+            self.emit(Op::TailCall { args: args.len(), form: form });
+        } else {
+            self.emit(Op::Call { args: args.len(), form: form });
+        }
+    }
+
+    // Returns false if the arguments aren't in a shape that can be inlined,
+    // in which case the form is compiled as a regular call
+    fn compile_core_form(&mut self, form: CoreForm, args: &[Value], tail: bool) -> bool {
+        match form {
+            CoreForm::If => {
+                if args.len() != 3 || !is_quote(&args[1]) || !is_quote(&args[2]) {
+                    return false;
+                }
+
+                self.emit(Op::PushScope);
+                self.compile(&args[0], false);
+
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.compile(unquote(&args[1]), tail);
+
+                let to_end = self.emit(Op::Jump(0));
+                self.patch_jump(to_else);
+                self.compile(unquote(&args[2]), tail);
+
+                self.patch_jump(to_end);
+                self.emit(Op::PopScope);
+            },
+            CoreForm::Seq => {
+                if args.len() == 0 || !args.iter().all(is_quote) {
+                    return false;
+                }
+
+                let body: Vec<Value> = args.iter().map(|arg| unquote(arg).clone()).collect();
+
+                self.emit(Op::PushScope);
+                self.compile_forms(&body, tail);
+                self.emit(Op::PopScope);
+            },
+            CoreForm::Let => {
+                if args.len() < 2 || !args.iter().all(is_quote) {
+                    return false;
+                }
+
+                let defs = match let_defs(unquote(&args[0])) {
+                    Some(defs) => defs,
+                    None => return false,
+                };
+
+                let body: Vec<Value> = args[1..].iter().map(|arg| unquote(arg).clone()).collect();
+
+                let slotted = !resolve::let_redefines(self.lisp, &self.scopes.names(), &defs, &body);
+                self.scopes.push(Vec::new(), slotted);
+                self.emit(Op::PushScope);
+
+                for (name, value) in defs {
+                    self.compile(&value, false);
+
+                    // The binding still goes into the scope as well, so that anything called from the body can see it
+                    let slot = self.scopes.bind(name);
+                    if self.scopes.is_slotted() {
+                        let slot = self.scopes.flat_slot(0, slot);
+                        self.emit(Op::StoreLocal(slot));
+                    }
+
+                    self.emit(Op::Bind(name));
+                    self.chunk.locals = cmp::max(self.chunk.locals, self.scopes.slots());
+                }
+
+                self.compile_forms(&body, tail);
+                self.emit(Op::PopScope);
+
+                self.scopes.pop();
+            },
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// Returns the lambda's compiled body, compiling it the first time it's needed
+pub fn lambda_chunk(lisp: &Lisp, lambda: &Lambda) -> Rc<Chunk> {
+    if let Some(ref chunk) = *lambda.compiled.borrow() {
+        return chunk.clone();
+    }
+
+    let chunk = Rc::new(compile_lambda(lisp, lambda));
+    *lambda.compiled.borrow_mut() = Some(chunk.clone());
+
+    chunk
+}
+
+// Once this many forms have been compiled the cache starts over, so code built at runtime and passed to `eval` can't fill it up
+const FORM_CACHE_SIZE: usize = 1024;
+
+/// The chunks of forms run outside of a lambda (lambdas keep their own), so running a form again doesn't compile it again.
+/// Forms are told apart by the address of their list, which is kept alive while it's cached so no other list can get the same address.
+pub struct FormCache {
+    chunks: HashMap<usize, (List, Rc<Chunk>)>,
+}
+
+impl FormCache {
+    pub fn new() -> FormCache {
+        FormCache {
+            chunks: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, form: &List) -> Option<Rc<Chunk>> {
+        self.chunks.get(&form.addr()).map(|&(_, ref chunk)| chunk.clone())
+    }
+
+    pub fn insert(&mut self, form: &List, chunk: Rc<Chunk>) {
+        if self.chunks.len() >= FORM_CACHE_SIZE {
+            self.chunks.clear();
+        }
+
+        self.chunks.insert(form.addr(), (form.clone(), chunk));
+    }
+}
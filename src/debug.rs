@@ -0,0 +1,553 @@
+//! An interactive debugger, built on the evaluator's `Hook`.
+//!
+//! Once attached to an interpreter, the debugger pauses on `(break)`, on breakpoints
+//! (calls to a function, or reaching a line of a file loaded through `Debugger::register`), and on errors.
+//! While paused it reads commands, which step through evaluation, inspect the scopes and stack,
+//! and evaluate expressions where evaluation stopped. `help` lists them.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use eval::{Lisp, Hook, FuncError};
+use list::WeakList;
+use parse::{Node, NodeKind};
+use symbol::Symbol;
+use value::Value;
+
+const HELP: &'static str = "\
+c, continue         run until the next breakpoint
+s, step             evaluate the next form, stepping into calls
+n, next             step over the current form, to the next one at the same depth or above
+o, out              run until the form this one is inside of returns
+bt, backtrace       show the forms being evaluated, innermost first
+l, locals           show the bindings of each scope, innermost first
+p, print EXPR       evaluate an expression where evaluation is paused
+b, break NAME       pause whenever the function NAME is called
+b, break FILE:LINE  pause when evaluation reaches a line (the file can be left out)
+breakpoints         list the breakpoints
+d, delete N         remove breakpoint N
+q, quit             stop evaluating
+An empty line repeats the last command.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Calls to the function with this name
+    Function(Symbol),
+    /// The first form evaluated on a line, in the given file or any of them
+    Line {
+        file: Option<String>,
+        line: usize,
+    },
+}
+
+impl Breakpoint {
+    /// Parses `name`, `file:line` or `line`
+    pub fn parse(text: &str) -> Breakpoint {
+        if let Ok(line) = text.parse() {
+            return Breakpoint::Line { file: None, line: line };
+        }
+
+        if let Some(colon) = text.rfind(':') {
+            if let Ok(line) = text[colon + 1..].parse() {
+                return Breakpoint::Line {
+                    file: Some(text[..colon].to_string()),
+                    line: line,
+                };
+            }
+        }
+
+        Breakpoint::Function(Symbol::intern(text))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Breakpoint::Function(name) => write!(fmt, "calls to {}", name),
+            &Breakpoint::Line { file: Some(ref file), line } => write!(fmt, "{}:{}", file, line),
+            &Breakpoint::Line { file: None, line } => write!(fmt, "line {}", line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Continue,
+    Step,
+    /// Pause at the next form no deeper than this
+    Next(usize),
+    /// Pause once the evaluation stack is shallower than this
+    Out(usize),
+}
+
+// Where a form was written
+#[derive(Clone)]
+struct Position {
+    file: Rc<String>,
+    line: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}", self.file, self.line)
+    }
+}
+
+struct State {
+    breakpoints: Vec<Option<Breakpoint>>, // Deleted ones are left as None so the numbers don't change
+    // By the address of the list. The weak reference stops the address being reused without keeping the form alive.
+    positions: HashMap<usize, (WeakList, Position)>,
+
+    stack: Vec<Value>, // The forms being evaluated, innermost last
+    mode: Mode,
+    pause_on_error: bool,
+    paused_on_error: bool, // Only the innermost form an error passes through is paused on
+    quitting: bool,
+    last_command: String,
+
+    input: Option<Box<BufRead>>, // None reads from stdin
+    output: Box<Write>,
+}
+
+/// A handle to a debugger, clones share the same breakpoints and state
+#[derive(Clone)]
+pub struct Debugger {
+    state: Rc<RefCell<State>>,
+}
+
+impl Debugger {
+    /// A debugger that reads commands from stdin and writes to stdout.
+    /// Lines are read straight from stdin's own buffer, so none are taken from anything else reading it (like the REPL).
+    pub fn new() -> Debugger {
+        Debugger::with_input(None, Box::new(io::stdout()))
+    }
+
+    pub fn with_io(input: Box<BufRead>, output: Box<Write>) -> Debugger {
+        Debugger::with_input(Some(input), output)
+    }
+
+    fn with_input(input: Option<Box<BufRead>>, output: Box<Write>) -> Debugger {
+        Debugger {
+            state: Rc::new(RefCell::new(State {
+                breakpoints: Vec::new(),
+                positions: HashMap::new(),
+                stack: Vec::new(),
+                mode: Mode::Continue,
+                pause_on_error: true,
+                paused_on_error: false,
+                quitting: false,
+                last_command: String::new(),
+                input: input,
+                output: output,
+            })),
+        }
+    }
+
+    /// Installs the debugger as the interpreter's hook
+    pub fn attach(&self, lisp: &mut Lisp) {
+        lisp.set_hook(Box::new(self.clone()));
+    }
+
+    /// Returns the number the breakpoint can be deleted by
+    pub fn add_breakpoint(&self, breakpoint: Breakpoint) -> usize {
+        let mut state = self.state.borrow_mut();
+        state.breakpoints.push(Some(breakpoint));
+
+        state.breakpoints.len()
+    }
+
+    pub fn remove_breakpoint(&self, number: usize) -> bool {
+        self.state.borrow_mut().remove_breakpoint(number)
+    }
+
+    /// Each breakpoint along with its number
+    pub fn breakpoints(&self) -> Vec<(usize, Breakpoint)> {
+        self.state.borrow().numbered_breakpoints()
+    }
+
+    /// Pauses at the next form evaluated
+    pub fn step(&self) {
+        self.state.borrow_mut().mode = Mode::Step;
+    }
+
+    pub fn set_pause_on_error(&self, pause: bool) {
+        self.state.borrow_mut().pause_on_error = pause;
+    }
+
+    /// Converts parsed nodes to the forms to evaluate, remembering which line of `file` each one came from
+    /// so that line breakpoints work and pauses can say where they are.
+    /// Nodes with syntax errors are left out.
+    pub fn register(&self, file: &str, code: &str, nodes: &[Node]) -> Vec<Value> {
+        let file = Rc::new(file.to_string());
+        let line_starts: Vec<usize> = Some(0).into_iter()
+            .chain(code.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        let mut state = self.state.borrow_mut();
+        let mut forms = Vec::new();
+
+        // Forms that have been dropped since the last time don't need their positions any more
+        state.positions.retain(|_, &mut (ref list, _)| list.upgrade().is_some());
+
+        for node in nodes {
+            if let Ok(form) = node.to_value() {
+                register_positions(&mut state.positions, &file, &line_starts, node, &form);
+                forms.push(form);
+            }
+        }
+
+        forms
+    }
+}
+
+fn register_positions(positions: &mut HashMap<usize, (WeakList, Position)>, file: &Rc<String>, line_starts: &[usize], node: &Node, form: &Value) {
+    match (&node.kind, form) {
+        (&NodeKind::Quote(ref inner_node), &Value::Quote(ref inner)) => {
+            register_positions(positions, file, line_starts, inner_node, inner)
+        },
+        (&NodeKind::QuotedList(ref items), &Value::Quote(ref inner)) => {
+            register_list(positions, file, line_starts, node.span.start, items, inner)
+        },
+        (&NodeKind::List(ref items), _) => register_list(positions, file, line_starts, node.span.start, items, form),
+        _ => (),
+    }
+}
+
+fn register_list(positions: &mut HashMap<usize, (WeakList, Position)>, file: &Rc<String>, line_starts: &[usize],
+                 start: usize, items: &[Node], form: &Value) {
+    if let &Value::List(ref list) = form {
+        let line = match line_starts.binary_search(&start) {
+            Ok(index) => index + 1,
+            Err(index) => index,
+        };
+
+        positions.insert(list.addr(), (list.downgrade(), Position {
+            file: file.clone(),
+            line: line,
+        }));
+
+        for (item, value) in items.iter().zip(list.iter()) {
+            register_positions(positions, file, line_starts, item, value);
+        }
+    }
+}
+
+impl State {
+    fn remove_breakpoint(&mut self, number: usize) -> bool {
+        match self.breakpoints.get_mut(number.wrapping_sub(1)) {
+            Some(slot) => slot.take().is_some(),
+            None => false,
+        }
+    }
+
+    // Once everything that was being evaluated has stopped, whatever's evaluated next can run
+    fn finish_quitting(&mut self) {
+        if self.stack.is_empty() {
+            self.quitting = false;
+        }
+    }
+
+    fn numbered_breakpoints(&self) -> Vec<(usize, Breakpoint)> {
+        self.breakpoints.iter()
+            .enumerate()
+            .filter_map(|(index, breakpoint)| breakpoint.clone().map(|breakpoint| (index + 1, breakpoint)))
+            .collect()
+    }
+
+    fn position(&self, form: &Value) -> Option<Position> {
+        match form {
+            &Value::List(ref list) => self.positions.get(&list.addr()).map(|&(_, ref position)| position.clone()),
+            _ => None,
+        }
+    }
+
+    fn hits_line(&self, position: &Position) -> bool {
+        self.breakpoints.iter().any(|breakpoint| match breakpoint {
+            &Some(Breakpoint::Line { ref file, line }) => {
+                line == position.line && file.as_ref().map_or(true, |file| file_matches(&position.file, file))
+            },
+            _ => false,
+        })
+    }
+
+    fn hits_function(&self, name: Symbol) -> bool {
+        self.breakpoints.iter().any(|breakpoint| *breakpoint == Some(Breakpoint::Function(name)))
+    }
+
+    fn print(&mut self, text: &str) {
+        let _ = writeln!(self.output, "{}", text);
+        let _ = self.output.flush();
+    }
+
+    fn describe(&self, form: &Value) -> String {
+        let mut text = format!("{:?}", form);
+        if text.chars().count() > 70 {
+            text = text.chars().take(67).collect::<String>() + "...";
+        }
+
+        match self.position(form) {
+            Some(position) => format!("{} at {}", text, position),
+            None => text,
+        }
+    }
+
+    // Reads and runs commands until one of them carries on evaluating
+    // `entered` is whether the function of the form on top of the stack is already running,
+    // in which case stepping out finishes that form rather than the one around it
+    fn pause(&mut self, lisp: &mut Lisp, reason: &str, entered: bool) -> Result<(), FuncError> {
+        let location = match self.stack.last() {
+            Some(form) => format!("\n  {}", self.describe(form)),
+            None => String::new(),
+        };
+
+        self.print(&format!("Paused, {}{}", reason, location));
+
+        loop {
+            let _ = write!(self.output, "debug> ");
+            let _ = self.output.flush();
+
+            let mut line = String::new();
+            let read = match self.input {
+                Some(ref mut input) => input.read_line(&mut line),
+                None => io::stdin().read_line(&mut line),
+            };
+
+            match read {
+                Ok(0) | Err(_) => {
+                    // No more input, so there's nothing to do but carry on
+                    self.mode = Mode::Continue;
+                    return Ok(());
+                },
+                Ok(_) => (),
+            }
+
+            let mut line = line.trim().to_string();
+            if line.is_empty() {
+                line = self.last_command.clone();
+            }
+            self.last_command = line.clone();
+
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let (command, arg) = (parts.next().unwrap_or(""), parts.next().unwrap_or("").trim());
+
+            match command {
+                "c" | "continue" => self.mode = Mode::Continue,
+                "s" | "step" => self.mode = Mode::Step,
+                "n" | "next" => self.mode = Mode::Next(self.stack.len()),
+                "o" | "out" => self.mode = Mode::Out(if entered { self.stack.len() } else { self.stack.len().saturating_sub(1) }),
+                "q" | "quit" => {
+                    self.quitting = true;
+                    self.mode = Mode::Continue;
+                    return Err(FuncError::StoppedByDebugger);
+                },
+                "bt" | "backtrace" => {
+                    let frames: Vec<String> = self.stack.iter().rev()
+                        .enumerate()
+                        .map(|(depth, form)| format!("#{} {}", depth, self.describe(form)))
+                        .collect();
+
+                    for frame in frames {
+                        self.print(&frame);
+                    }
+                    continue;
+                },
+                "l" | "locals" => {
+                    self.print_locals(lisp);
+                    continue;
+                },
+                "p" | "print" if !arg.is_empty() => {
+                    // Nothing evaluated here should leave a scope behind, even if it fails part way through
+                    let depth = lisp.scopes.len();
+                    let result = lisp.eval_raw(arg);
+                    lisp.scopes.truncate(depth);
+
+                    match result {
+                        Ok(value) => self.print(&format!("=> {:?}", value)),
+                        Err(err) => self.print(&format!("Error: {:?}", err)),
+                    }
+                    continue;
+                },
+                "b" | "break" if !arg.is_empty() => {
+                    let breakpoint = Breakpoint::parse(arg);
+                    self.breakpoints.push(Some(breakpoint.clone()));
+
+                    let message = format!("Breakpoint {} on {}", self.breakpoints.len(), breakpoint);
+                    self.print(&message);
+                    continue;
+                },
+                "breakpoints" | "b" | "break" => {
+                    let lines: Vec<String> = self.numbered_breakpoints().iter()
+                        .map(|&(number, ref breakpoint)| format!("{}: {}", number, breakpoint))
+                        .collect();
+
+                    match lines.is_empty() {
+                        true => self.print("No breakpoints"),
+                        false => self.print(&lines.join("\n")),
+                    }
+                    continue;
+                },
+                "d" | "delete" => {
+                    let message = match arg.parse().map(|number| self.remove_breakpoint(number)) {
+                        Ok(true) => "Deleted",
+                        _ => "No breakpoint with that number",
+                    };
+
+                    self.print(message);
+                    continue;
+                },
+                "h" | "help" => {
+                    self.print(HELP);
+                    continue;
+                },
+                _ => {
+                    self.print("Unknown command, help lists them");
+                    continue;
+                },
+            }
+
+            return Ok(());
+        }
+    }
+
+    fn print_locals(&mut self, lisp: &Lisp) {
+        let mut lines = Vec::new();
+
+        // The global scope is left out, it's everything that's ever been defined
+        for (depth, scope) in lisp.scopes.iter().skip(1).rev().enumerate() {
+            if scope.map.is_empty() {
+                continue;
+            }
+
+            let mut bindings: Vec<(&str, &Value)> = scope.map.iter().map(|(name, value)| (name.as_str(), value)).collect();
+            bindings.sort_by_key(|&(name, _)| name);
+
+            lines.push(format!("scope {}:", depth));
+            for (name, value) in bindings {
+                lines.push(format!("  {} = {:?}", name, value));
+            }
+        }
+
+        match lines.is_empty() {
+            true => self.print("No local bindings"),
+            false => self.print(&lines.join("\n")),
+        }
+    }
+}
+
+// A breakpoint's file matches if it's the same path, or the end of it (e.g. just the file name)
+fn file_matches(path: &str, file: &str) -> bool {
+    path == file || path.ends_with(&format!("/{}", file))
+}
+
+impl Hook for Debugger {
+    fn before_eval(&mut self, lisp: &mut Lisp, form: &Value) -> Result<(), FuncError> {
+        let mut state = self.state.borrow_mut();
+        if state.quitting {
+            return Err(FuncError::StoppedByDebugger);
+        }
+
+        state.paused_on_error = false;
+
+        let depth = state.stack.len();
+        let reason = match state.mode {
+            Mode::Step => Some("stepped".to_string()),
+            Mode::Next(paused) if depth < paused => Some("stepped".to_string()),
+            Mode::Out(paused) if depth < paused => Some("stepped out".to_string()),
+            _ => None,
+        };
+
+        // Line breakpoints only pause on the outermost form on the line
+        let position = state.position(form);
+        let line_hit = match position {
+            Some(ref position) if state.hits_line(position) => {
+                let enclosing = state.stack.last().and_then(|parent| state.position(parent));
+                enclosing.map_or(true, |parent| parent.line != position.line || parent.file != position.file)
+            },
+            _ => false,
+        };
+
+        state.stack.push(form.clone());
+
+        let reason = reason.or_else(|| if line_hit { Some("at a breakpoint".to_string()) } else { None });
+        if let Some(reason) = reason {
+            state.mode = Mode::Continue;
+
+            // The form won't be evaluated, so on_error won't be called for it
+            if let Err(err) = state.pause(lisp, &reason, false) {
+                state.stack.pop();
+                state.finish_quitting();
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn after_eval(&mut self, lisp: &mut Lisp, _form: &Value, result: &Value) {
+        let mut state = self.state.borrow_mut();
+        state.stack.pop();
+
+        if let Mode::Out(paused) = state.mode {
+            if state.stack.len() < paused {
+                state.mode = Mode::Continue;
+
+                let reason = format!("stepped out, returned {:?}", result);
+                let _ = state.pause(lisp, &reason, true); // Too late to stop this form, quitting stops the next one
+            }
+        }
+
+        state.finish_quitting();
+    }
+
+    fn on_call(&mut self, lisp: &mut Lisp, _func: &Value, args: &[Value]) -> Result<(), FuncError> {
+        let mut state = self.state.borrow_mut();
+
+        let name = match state.stack.last() {
+            Some(&Value::List(ref list)) => match list.head() {
+                Some(&Value::Symbol(name)) => name,
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        match state.hits_function(name) {
+            true => {
+                state.mode = Mode::Continue;
+                state.pause(lisp, &format!("at a breakpoint on {}, called with {:?}", name, args), true)
+            },
+            false => Ok(()),
+        }
+    }
+
+    fn on_error(&mut self, lisp: &mut Lisp, _form: &Value, err: &FuncError) {
+        let mut state = self.state.borrow_mut();
+
+        if state.pause_on_error && !state.paused_on_error && !state.quitting {
+            state.paused_on_error = true;
+            state.mode = Mode::Continue;
+
+            let reason = format!("error: {:?}", err);
+            let _ = state.pause(lisp, &reason, false);
+        }
+
+        state.stack.pop();
+        state.finish_quitting();
+    }
+
+    fn on_break(&mut self, lisp: &mut Lisp) -> Result<(), FuncError> {
+        let mut state = self.state.borrow_mut();
+        state.mode = Mode::Continue;
+
+        // The (break) itself is on the stack, where evaluation stopped is the form around it
+        let call = state.stack.pop();
+        let result = state.pause(lisp, "at (break)", true);
+        if let Some(call) = call {
+            state.stack.push(call);
+        }
+
+        result
+    }
+}
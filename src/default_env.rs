@@ -2,10 +2,21 @@
 use std::process;
 use std::io::{self, Write};
 use std::ops;
-
-use value::{Value, Args, ToLisp, FromLisp};
+use std::char;
+use std::f32;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use value::{func, Value, Lambda, Params, Args, Regex, ForeignType, ToLisp, FromLisp};
+use list::List;
+use symbol::Symbol;
 use valtype::Type;
 use eval::{Lisp, FuncError, FuncResult};
+use encoding;
+use testing::{self, Test};
+use docs;
+use pattern::Pattern;
 
 macro_rules! math {
     ($name:ident, $op:path) => {
@@ -21,17 +32,67 @@ macro_rules! math {
     }
 }
 
-fn make_lambda(args: Vec<Value>, body: Vec<Value>) -> FuncResult {
-    let mut arg_strs = Vec::new();
-    for arg in args {
-        let sym = try!(arg.as_sym());
-        arg_strs.push(sym);
+// Like math!, but every value after the first is a divisor, and so can't be zero
+macro_rules! division {
+    ($name:ident, $op:expr) => {
+        pub fn $name(mut items: Vec<Value>, _: &mut Lisp) -> FuncResult {
+            let mut total = try!(f32::from_lisp(items.remove(0)));
+
+            for item in items {
+                total = $op(total, try!(divisor(item)));
+            }
+
+            Ok(total.to_lisp())
+        }
+    }
+}
+
+macro_rules! unary_math {
+    ($name:ident, $op:path) => {
+        pub fn $name(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+            let num = try!(f32::from_lisp(vals.remove(0)));
+            Ok($op(num).to_lisp())
+        }
     }
+}
 
-    Ok(Value::Lambda {
-        args: arg_strs,
-        body: body,
-    })
+// Checks that each number is related to the next one by the given operator, e.g. (< 1 2 3)
+macro_rules! compare {
+    ($name:ident, $op:tt) => {
+        pub fn $name(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+            let mut nums = Vec::new();
+            for val in vals {
+                nums.push(try!(f32::from_lisp(val)));
+            }
+
+            for pair in nums.windows(2) {
+                if !(pair[0] $op pair[1]) {
+                    return Ok(false.to_lisp());
+                }
+            }
+
+            Ok(true.to_lisp())
+        }
+    }
+}
+
+// A string at the start of the body is the lambda's docstring, unless it's all there is
+fn make_lambda(args: List, mut body: Vec<Value>) -> FuncResult {
+    let params = try!(Params::parse(args.into_iter().collect()));
+
+    let doc = match body.first() {
+        Some(&Value::String(ref doc)) if body.len() > 1 => Some(doc.clone()),
+        _ => None,
+    };
+
+    if doc.is_some() {
+        body.remove(0);
+    }
+
+    let mut lambda = Lambda::with_params(params, body);
+    lambda.doc = doc;
+
+    Ok(Value::Lambda(Rc::new(lambda)))
 }
 
 // Core functions
@@ -40,13 +101,35 @@ pub fn define(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     match sym {
         Value::Symbol(sym) => {
             let val = vals.remove(0);
-            lisp.parent_scope().set(&sym, val);
+            lisp.parent_scope().set(sym, val);
+        },
+        // A pattern in place of the name destructures the value, e.g. (def {(a b)} {1 2})
+        Value::List(ref sig) if sig.head().map_or(false, |head| head.typ() == Type::List) => {
+            if sig.len() != 1 {
+                return Err(FuncError::InvalidPattern(Value::List(sig.clone())));
+            }
+
+            if vals.len() != 1 {
+                return Err(FuncError::InvalidArguments {
+                    expected: Args::Fixed(2),
+                    got: vals.len() + 1,
+                });
+            }
+
+            let pattern = try!(Pattern::parse(sig.head().unwrap()));
+            for (name, value) in try!(pattern.destructure(vals.remove(0))) {
+                lisp.parent_scope().set(name, value);
+            }
         },
-        Value::List(mut args) => {
-            let name = try!(args.remove(0).as_sym());
+        Value::List(args) => {
+            let (name, args) = match (args.head(), args.tail()) {
+                (Some(name), Some(args)) => (try!(name.clone().as_sym()), args),
+                _ => return Err(FuncError::GivenEmptyList),
+            };
+
             let func = try!(make_lambda(args, vals));
 
-            lisp.parent_scope().set(&name, func);
+            lisp.parent_scope().set(name, func);
         },
         _ => return Err(FuncError::InvalidType {
             expected: vec![Type::Symbol, Type::List],
@@ -61,18 +144,24 @@ pub fn let_fn(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     let defs = try!(vals.remove(0).as_list());
 
     for def in defs {
-        let mut def = try!(def.as_list());
+        let def = try!(def.as_list());
         if def.len() != 2 {
             return Err(FuncError::InvalidArguments {
                 expected: Args::Fixed(2),
                 got: def.len(),
             });
         }
-        
-        let name = try!(def.remove(0).as_sym());
-        let value = try!(lisp.eval_token(def.remove(0)));
 
-        lisp.cur_scope().set(&name, value);
+        let mut def = def.into_iter();
+        let target = def.next().unwrap();
+        let value = try!(lisp.eval_token(def.next().unwrap()));
+
+        match target {
+            Value::Symbol(name) => lisp.cur_scope().set(name, value),
+            pattern => for (name, value) in try!(try!(Pattern::parse(&pattern)).destructure(value)) {
+                lisp.cur_scope().set(name, value);
+            },
+        }
     }
 
     lisp.eval_token_vec(vals)
@@ -103,6 +192,275 @@ pub fn if_fn(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     })
 }
 
+// Pauses in the debugger, if one is attached
+pub fn break_fn(_: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    if let Some(Err(err)) = lisp.call_hook(|hook, lisp| hook.on_break(lisp)) {
+        return Err(err);
+    }
+
+    Ok(Value::Nil)
+}
+
+// Traces each of the named functions, giving the list of everything that's traced
+pub fn trace(vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    for val in vals {
+        let name = try!(val.as_sym());
+        lisp.trace(name);
+    }
+
+    Ok(Value::List(lisp.traced().into_iter().map(Value::Symbol).collect::<Vec<_>>().into()))
+}
+
+// Without any names, everything stops being traced
+pub fn untrace(vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    if vals.is_empty() {
+        lisp.untrace_all();
+    }
+
+    for val in vals {
+        let name = try!(val.as_sym());
+        lisp.untrace(name);
+    }
+
+    Ok(Value::Nil)
+}
+
+// The test's forms are quoted, they're only evaluated when it's run
+pub fn deftest(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let name = vals.remove(0);
+    let name = match testing::test_name(name.clone()) {
+        Some(name) => name,
+        None => return Err(FuncError::InvalidType {
+            expected: vec![Type::Symbol, Type::String],
+            got: name,
+        }),
+    };
+
+    lisp.add_test(Test {
+        name: name,
+        body: vals,
+    });
+
+    Ok(Value::Nil)
+}
+
+pub fn assert(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    if try!(bool::from_lisp(vals.remove(0))) {
+        return Ok(Value::Nil);
+    }
+
+    Err(FuncError::AssertionFailed(match vals.pop() {
+        Some(message) => try!(String::from_lisp(message)),
+        None => "the condition is false".to_string(),
+    }))
+}
+
+pub fn assert_equal(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let actual = vals.pop().unwrap();
+    let expected = vals.pop().unwrap();
+
+    match expected == actual {
+        true => Ok(Value::Nil),
+        false => Err(FuncError::NotEqual {
+            expected: expected,
+            actual: actual,
+        }),
+    }
+}
+
+// Evaluates a quoted form, which has to fail (with an error containing the given text, if there is any)
+pub fn assert_error(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let form = vals.remove(0);
+    let expected = match vals.pop() {
+        Some(text) => Some(try!(String::from_lisp(text))),
+        None => None,
+    };
+
+    let depth = lisp.scopes.len();
+    let result = lisp.eval_token(form);
+    lisp.scopes.truncate(depth);
+
+    match (result, expected) {
+        (Ok(value), _) => Err(FuncError::AssertionFailed(format!("expected an error, but got {:?}", value))),
+        (Err(err), Some(ref expected)) if !format!("{:?}", err).contains(&expected[..]) => {
+            Err(FuncError::AssertionFailed(format!("expected an error containing {:?}, but got: {:?}", expected, err)))
+        },
+        (Err(_), _) => Ok(Value::Nil),
+    }
+}
+
+pub fn doc(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let name = try!(vals.remove(0).as_sym());
+
+    match docs::lookup(lisp, name) {
+        Some(doc) => Ok(doc.to_text().to_lisp()),
+        None => Ok(Value::Nil),
+    }
+}
+
+pub fn apropos(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let text = try!(String::from_lisp(vals.remove(0)));
+    let names: Vec<Value> = docs::apropos(lisp, &text).into_iter().map(Value::Symbol).collect();
+
+    Ok(Value::List(names.into()))
+}
+
+// Takes a function, or the name of one
+pub fn arity(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = match vals.remove(0) {
+        Value::Symbol(name) => try!(lisp.lookup(name)),
+        other => other,
+    };
+
+    match docs::arity(&func) {
+        Some(args) => Ok(args.to_string().to_lisp()),
+        None => Err(FuncError::InvalidType {
+            expected: vec![Type::HardFunc, Type::Lambda],
+            got: func,
+        }),
+    }
+}
+
+// Functions
+pub fn apply(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let last = try!(vals.pop().unwrap().as_list());
+
+    vals.extend(last);
+    lisp.call(&func, vals)
+}
+
+fn expect_function(value: &Value) -> Result<(), FuncError> {
+    match value {
+        &Value::HardFunc(_) | &Value::Lambda(_) => Ok(()),
+        _ => Err(FuncError::InvalidType {
+            expected: vec![Type::HardFunc, Type::Lambda],
+            got: value.clone(),
+        }),
+    }
+}
+
+// Bound to the arguments of the lambdas below. It can't be read as a symbol, so code called
+// from their bodies can't see it through dynamic scoping by accident.
+const ARGS_NAME: &'static str = "#args";
+
+// Since scoping is dynamic there aren't any closures, so functions made out of other functions are
+// lambdas taking any number of arguments as `ARGS_NAME`, with the values they use quoted into the body
+fn variadic_lambda(body: Value) -> Value {
+    let mut params = Params::new(Vec::new());
+    params.rest = Some(Symbol::intern(ARGS_NAME));
+
+    Value::Lambda(Rc::new(Lambda::with_params(params, vec![body])))
+}
+
+// The form (apply 'f arg... args), which calls f with the given arguments followed by the lambda's
+fn apply_form(func_val: &Value, args: Vec<Value>) -> Value {
+    let mut form = vec![Value::HardFunc(func(apply, Args::Atleast(2))), Value::Quote(Box::new(func_val.clone()))];
+    form.extend(args.into_iter().map(|arg| Value::Quote(Box::new(arg))));
+    form.push(Value::Symbol(Symbol::intern(ARGS_NAME)));
+
+    Value::List(form.into())
+}
+
+pub fn partial(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    try!(expect_function(&func));
+
+    Ok(variadic_lambda(apply_form(&func, vals)))
+}
+
+pub fn comp(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    for val in &vals {
+        try!(expect_function(val));
+    }
+
+    let mut funcs = vals.into_iter().rev();
+    let mut body = match funcs.next() {
+        Some(innermost) => apply_form(&innermost, Vec::new()),
+        None => return Ok(Value::HardFunc(func(id, Args::Fixed(1)))),
+    };
+
+    for func in funcs {
+        body = Value::List(vec![Value::Quote(Box::new(func)), body].into());
+    }
+
+    Ok(variadic_lambda(body))
+}
+
+pub fn constantly(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    Ok(variadic_lambda(Value::Quote(Box::new(vals.remove(0)))))
+}
+
+pub fn juxt(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut body = vec![Value::HardFunc(func(list, Args::Variant))];
+
+    for val in &vals {
+        try!(expect_function(val));
+        body.push(apply_form(val, Vec::new()));
+    }
+
+    Ok(variadic_lambda(Value::List(body.into())))
+}
+
+// What a memoized function remembers, its results by the index of the arguments they were for
+#[derive(Debug)]
+struct Memo {
+    func: Value,
+    args: RefCell<ValueIndex>,
+    results: RefCell<Vec<Option<Value>>>,
+}
+
+impl ForeignType for Memo { }
+
+pub fn memoize(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let func_val = vals.remove(0);
+    try!(expect_function(&func_val));
+
+    let memo = Rc::new(Memo {
+        func: func_val,
+        args: RefCell::new(ValueIndex::new()),
+        results: RefCell::new(Vec::new()),
+    });
+
+    let form = vec![
+        Value::HardFunc(func(memoized_call, Args::Fixed(2))),
+        Value::Quote(Box::new(memo.to_lisp())),
+        Value::Symbol(Symbol::intern(ARGS_NAME)),
+    ];
+
+    Ok(variadic_lambda(Value::List(form.into())))
+}
+
+// Arguments are the same if they're equal values, calls that failed are made again
+fn memoized_call(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let args = vals.pop().unwrap();
+
+    let (func, index, known) = {
+        let memo = try!(vals[0].as_foreign::<Memo>());
+        let index = match memo.args.borrow_mut().index(&args) {
+            Ok(index) | Err(index) => index,
+        };
+        let known = memo.results.borrow().get(index).cloned().and_then(|result| result);
+
+        (memo.func.clone(), index, known)
+    };
+
+    if let Some(result) = known {
+        return Ok(result);
+    }
+
+    let result = try!(lisp.call(&func, try!(args.as_list()).into_iter().collect()));
+
+    let memo = try!(vals[0].as_foreign::<Memo>());
+    let mut results = memo.results.borrow_mut();
+    if results.len() <= index {
+        results.resize(index + 1, None);
+    }
+    results[index] = Some(result.clone());
+
+    Ok(result)
+}
+
 pub fn eval(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     lisp.eval_token_vec(vals)
 }
@@ -125,6 +483,7 @@ pub fn print(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     for val in vals {
         match val {
             Value::String(string) => print!("{}", string),
+            Value::Char(ch) => print!("{}", ch),
             _ => print!("{:?}", val),
         }
     }
@@ -174,6 +533,7 @@ pub fn str_fn(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     for val in vals {
         let string = match val {
             Value::String(str) => str,
+            Value::Char(ch) => ch.to_string(),
             other => format!("{:?}", other),
         };
 
@@ -183,6 +543,40 @@ pub fn str_fn(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     Ok(result.to_lisp())
 }
 
+// Char ops
+pub fn char_to_integer(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let ch = try!(char::from_lisp(vals.remove(0)));
+    Ok((ch as u32 as f32).to_lisp())
+}
+
+pub fn integer_to_char(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let code = try!(f32::from_lisp(vals.remove(0)));
+
+    if code < 0.0 || code.fract() != 0.0 {
+        return Err(FuncError::InvalidCharCode(code));
+    }
+
+    match char::from_u32(code as u32) {
+        Some(ch) => Ok(ch.to_lisp()),
+        None => Err(FuncError::InvalidCharCode(code)),
+    }
+}
+
+pub fn is_alphabetic(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let ch = try!(char::from_lisp(vals.remove(0)));
+    Ok(ch.is_alphabetic().to_lisp())
+}
+
+pub fn is_whitespace(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let ch = try!(char::from_lisp(vals.remove(0)));
+    Ok(ch.is_whitespace().to_lisp())
+}
+
+pub fn is_digit(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let ch = try!(char::from_lisp(vals.remove(0)));
+    Ok(ch.is_digit(10).to_lisp())
+}
+
 pub fn eq(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     for i in (0 .. vals.len() - 1) {
         if vals[i] != vals[i + 1] {
@@ -193,18 +587,10 @@ pub fn eq(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     Ok(true.to_lisp())
 }
 
-pub fn greater_than(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    let a = try!(f32::from_lisp(vals.remove(0)));
-    let b = try!(f32::from_lisp(vals.remove(0)));
-
-    Ok((a > b).to_lisp())
-}
-
-pub fn less_than(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    let a = try!(f32::from_lisp(vals.remove(0)));
-    let b = try!(f32::from_lisp(vals.remove(0)));
-
-    Ok((a < b).to_lisp())
+// True unless every value is equal, i.e. the opposite of =
+pub fn not_eq(vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let equal = try!(bool::from_lisp(try!(eq(vals, lisp))));
+    Ok((!equal).to_lisp())
 }
 
 pub fn and(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
@@ -238,7 +624,7 @@ pub fn not(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
 
 // List ops
 pub fn list(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    Ok(Value::List(vals))
+    Ok(Value::List(vals.into()))
 }
 
 pub fn range(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
@@ -260,34 +646,34 @@ pub fn range(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
 }
 
 pub fn len(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    let list = try!(vals.remove(0).as_list());
-    Ok(Value::Number(list.len() as f32))
+    let len = match vals.remove(0) {
+        Value::List(list) => list.len(),
+        Value::Bytes(bytes) => bytes.len(),
+        other => return Err(FuncError::InvalidType {
+            expected: vec![Type::List, Type::Bytes],
+            got: other,
+        }),
+    };
+
+    Ok(Value::Number(len as f32))
 }
 
 pub fn head(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    let mut list = try!(vals.remove(0).as_list());
+    let list = try!(vals.remove(0).as_list());
 
-    if list.len() == 0 {
-        return Err(FuncError::GivenEmptyList);
+    match list.head() {
+        Some(head) => Ok(head.clone()),
+        None => Err(FuncError::GivenEmptyList),
     }
-
-    Ok(list.remove(0))
 }
 
 pub fn tail(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    if vals.len() == 0 {
-        return Err(FuncError::GivenEmptyList);
-    }
-
-    let mut list = try!(vals.remove(0).as_list());
+    let list = try!(vals.remove(0).as_list());
 
-    if list.len() == 0 {
-        return Err(FuncError::GivenEmptyList);
+    match list.tail() {
+        Some(tail) => Ok(Value::List(tail)),
+        None => Err(FuncError::GivenEmptyList),
     }
-
-    list.remove(0);
-
-    Ok(Value::List(list))
 }
 
 pub fn is_empty(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
@@ -297,42 +683,118 @@ pub fn is_empty(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
 
 pub fn cons(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
     let new_head = vals.remove(0);
-    let mut list = try!(vals.remove(0).as_list());
+    let list = try!(vals.remove(0).as_list());
 
-    list.insert(0, new_head);
-    Ok(Value::List(list))
+    Ok(Value::List(list.cons(new_head)))
 }
 
+// Unlike cons, this has to copy the whole list
 pub fn join(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
-    let mut list = try!(vals.remove(0).as_list());
+    let mut list = try!(vals.remove(0).as_list()).to_vec();
     let new_last = vals.remove(0);
 
     list.push(new_last);
-    Ok(Value::List(list))
+    Ok(Value::List(list.into()))
+}
+
+// Calls a predicate on a value through lisp.call, so the value isn't evaluated again, and wants a bool back
+fn call_predicate(func: &Value, val: &Value, lisp: &mut Lisp) -> Result<bool, FuncError> {
+    let result = try!(lisp.call(func, vec![val.clone()]));
+    bool::from_lisp(result)
+}
+
+// The ordering used by sort when no comparator is given
+fn natural_less(a: &Value, b: &Value) -> Result<bool, FuncError> {
+    match (a, b) {
+        (&Value::Number(a), &Value::Number(b)) => Ok(a < b),
+        (&Value::String(ref a), &Value::String(ref b)) => Ok(a < b),
+        (&Value::Char(a), &Value::Char(b)) => Ok(a < b),
+        _ => Err(FuncError::InvalidType {
+            expected: vec![a.typ()],
+            got: b.clone(),
+        }),
+    }
+}
+
+// A stable merge sort, which unlike slice::sort_by lets the comparison fail
+fn merge_sort<T, F>(mut list: Vec<T>, less: &mut F) -> Result<Vec<T>, FuncError>
+    where F: FnMut(&T, &T) -> Result<bool, FuncError>
+{
+    if list.len() <= 1 {
+        return Ok(list);
+    }
+
+    let right = list.split_off(list.len() / 2);
+    let left = try!(merge_sort(list, less));
+    let right = try!(merge_sort(right, less));
+
+    let mut result = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    loop {
+        // Only take from the right when it's strictly less, which keeps the sort stable
+        let take_right = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => try!(less(r, l)),
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => break,
+        };
+
+        if take_right {
+            result.extend(right.next());
+        } else {
+            result.extend(left.next());
+        }
+    }
+
+    Ok(result)
 }
 
+// Given several lists, the function is called with an element from each list, stopping at the shortest one
 pub fn map(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     let func = vals.remove(0);
-    let list = try!(vals.remove(0).as_list());
+
+    let mut lists = Vec::new();
+    for val in vals {
+        lists.push(try!(val.as_list()).into_iter());
+    }
 
     let mut new_list = Vec::new();
-    for val in list {
-        let s_expr = Value::List(vec!(func.clone(), val));
-        new_list.push(try!(lisp.eval_token(s_expr))); 
+    'outer: loop {
+        let mut args = Vec::with_capacity(lists.len());
+
+        for list in lists.iter_mut() {
+            match list.next() {
+                Some(val) => args.push(val),
+                None => break 'outer,
+            }
+        }
+
+        new_list.push(try!(lisp.call(&func, args)));
     }
 
-    Ok(Value::List(new_list))
+    Ok(Value::List(new_list.into()))
 }
 
-
+// Without an initial accumulator, the first element of the list is used instead
 pub fn fold(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
     let func = vals.remove(0);
-    let mut acc = vals.remove(0);
-    let list = try!(vals.remove(0).as_list());
+
+    let (mut acc, list) = if vals.len() == 2 {
+        let acc = vals.remove(0);
+        (acc, try!(vals.remove(0).as_list()))
+    } else {
+        let list = try!(vals.remove(0).as_list());
+
+        match (list.head(), list.tail()) {
+            (Some(head), Some(tail)) => (head.clone(), tail),
+            _ => return Err(FuncError::GivenEmptyList),
+        }
+    };
 
     for val in list {
-        let s_expr = Value::List(vec![func.clone(), acc.clone(), val.clone()]);
-        acc = try!(lisp.eval_token(s_expr));
+        acc = try!(lisp.call(&func, vec![acc, val]));
     }
 
     Ok(acc)
@@ -344,20 +806,795 @@ pub fn filter(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
 
     let mut new_list = Vec::new();
     for val in list {
-        let s_expr = Value::List(vec![func.clone(), val.clone()]);
-        let result = try!(lisp.eval_token(s_expr));
-
-        let bool_result = try!(bool::from_lisp(result));
-
-        if bool_result {
+        if try!(call_predicate(&func, &val, lisp)) {
             new_list.push(val);
         }
     }
 
-    Ok(Value::List(new_list))
+    Ok(Value::List(new_list.into()))
 }
 
-math!(add, ops::Add::add);
-math!(sub, ops::Sub::sub);
-math!(mul, ops::Mul::mul);
-math!(div, ops::Div::div);
+pub fn reverse(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    let mut reversed = List::new();
+    for val in list {
+        reversed = reversed.cons(val);
+    }
+
+    Ok(Value::List(reversed))
+}
+
+pub fn append(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut result = Vec::new();
+
+    for val in vals {
+        result.extend(try!(val.as_list()));
+    }
+
+    Ok(Value::List(result.into()))
+}
+
+pub fn nth(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+    let index = try!(index_arg(vals.remove(0), list.len()));
+
+    match list.iter().nth(index) {
+        Some(val) => Ok(val.clone()),
+        None => Err(FuncError::IndexOutOfBounds {
+            index: index as f32,
+            len: list.len(),
+        }),
+    }
+}
+
+pub fn last(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    match list.iter().last() {
+        Some(val) => Ok(val.clone()),
+        None => Err(FuncError::GivenEmptyList),
+    }
+}
+
+// take and drop clamp the count to the length of the list
+pub fn take(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let count = try!(f32::from_lisp(vals.remove(0))).max(0.0) as usize;
+    let list = try!(vals.remove(0).as_list());
+
+    Ok(Value::List(list.into_iter().take(count).collect()))
+}
+
+// Skips the first count elements, sharing the rest of the list rather than copying it
+fn skip_list(mut list: List, count: usize) -> List {
+    for _ in 0..count {
+        list = match list.tail() {
+            Some(tail) => tail,
+            None => break,
+        };
+    }
+
+    list
+}
+
+pub fn drop_fn(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let count = try!(f32::from_lisp(vals.remove(0))).max(0.0) as usize;
+    let list = try!(vals.remove(0).as_list());
+
+    Ok(Value::List(skip_list(list, count)))
+}
+
+pub fn take_while(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut new_list = Vec::new();
+    for val in list {
+        if !try!(call_predicate(&func, &val, lisp)) {
+            break;
+        }
+
+        new_list.push(val);
+    }
+
+    Ok(Value::List(new_list.into()))
+}
+
+pub fn drop_while(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut count = 0;
+    for val in list.iter() {
+        if !try!(call_predicate(&func, val, lisp)) {
+            break;
+        }
+
+        count += 1;
+    }
+
+    Ok(Value::List(skip_list(list, count)))
+}
+
+pub fn zip(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut lists = Vec::new();
+    for val in vals {
+        lists.push(try!(val.as_list()).into_iter());
+    }
+
+    let mut zipped = Vec::new();
+    'outer: loop {
+        let mut tuple = Vec::with_capacity(lists.len());
+
+        for list in lists.iter_mut() {
+            match list.next() {
+                Some(val) => tuple.push(val),
+                None => break 'outer,
+            }
+        }
+
+        zipped.push(Value::List(tuple.into()));
+    }
+
+    Ok(Value::List(zipped.into()))
+}
+
+fn flatten_into(list: List, result: &mut Vec<Value>) {
+    for val in list {
+        match val {
+            Value::List(inner) => flatten_into(inner, result),
+            other => result.push(other),
+        }
+    }
+}
+
+pub fn flatten(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    let mut result = Vec::new();
+    flatten_into(list, &mut result);
+
+    Ok(Value::List(result.into()))
+}
+
+pub fn find(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    for val in list {
+        if try!(call_predicate(&func, &val, lisp)) {
+            return Ok(val);
+        }
+    }
+
+    Ok(Value::Nil)
+}
+
+pub fn any(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    for val in list {
+        if try!(call_predicate(&func, &val, lisp)) {
+            return Ok(true.to_lisp());
+        }
+    }
+
+    Ok(false.to_lisp())
+}
+
+pub fn every(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    for val in list {
+        if !try!(call_predicate(&func, &val, lisp)) {
+            return Ok(false.to_lisp());
+        }
+    }
+
+    Ok(true.to_lisp())
+}
+
+pub fn count(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut count = 0;
+    for val in list {
+        if try!(call_predicate(&func, &val, lisp)) {
+            count += 1;
+        }
+    }
+
+    Ok((count as f32).to_lisp())
+}
+
+// What a value is compared by when looking for ones that are equal to it, for the values that can be hashed
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Nil,
+    Number(u32),
+    Bool(bool),
+    Char(char),
+    Symbol(Symbol),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<Key>),
+    Quote(Box<Key>),
+}
+
+// None for anything that can only be compared with ==, like functions (or lists containing them)
+fn key(value: &Value) -> Option<Key> {
+    let key = match value {
+        &Value::Nil => Key::Nil,
+        // NaN isn't equal to anything, not even itself
+        &Value::Number(num) if num.is_nan() => return None,
+        // -0 and 0 are equal but have different bits
+        &Value::Number(num) if num == 0.0 => Key::Number(0),
+        &Value::Number(num) => Key::Number(num.to_bits()),
+        &Value::Bool(val) => Key::Bool(val),
+        &Value::Char(ch) => Key::Char(ch),
+        &Value::Symbol(sym) => Key::Symbol(sym),
+        &Value::String(ref string) => Key::String(string.clone()),
+        &Value::Bytes(ref bytes) => Key::Bytes(bytes.clone()),
+        &Value::List(ref list) => {
+            let mut keys = Vec::with_capacity(list.len());
+            for item in list {
+                match key(item) {
+                    Some(key) => keys.push(key),
+                    None => return None,
+                }
+            }
+
+            Key::List(keys)
+        },
+        &Value::Quote(ref inner) => match key(inner) {
+            Some(key) => Key::Quote(Box::new(key)),
+            None => return None,
+        },
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+// Numbers the distinct values it's given, so that distinct, group-by, frequencies and memoize don't have to compare
+// each value with every one before it. Values without a key are still compared with ==, but there are rarely many of them.
+#[derive(Debug)]
+struct ValueIndex {
+    keyed: HashMap<Key, usize>,
+    others: Vec<(Value, usize)>,
+}
+
+impl ValueIndex {
+    fn new() -> ValueIndex {
+        ValueIndex {
+            keyed: HashMap::new(),
+            others: Vec::new(),
+        }
+    }
+
+    // The index of the value if an equal one has been seen, otherwise it's given the next index
+    fn index(&mut self, value: &Value) -> Result<usize, usize> {
+        let next = self.keyed.len() + self.others.len();
+
+        match key(value) {
+            Some(key) => match self.keyed.get(&key) {
+                Some(&index) => Ok(index),
+                None => {
+                    self.keyed.insert(key, next);
+                    Err(next)
+                },
+            },
+            None => match self.others.iter().find(|&&(ref other, _)| other == value) {
+                Some(&(_, index)) => Ok(index),
+                None => {
+                    self.others.push((value.clone(), next));
+                    Err(next)
+                },
+            },
+        }
+    }
+}
+
+pub fn distinct(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    let mut seen = ValueIndex::new();
+    let mut result: Vec<Value> = Vec::new();
+    for val in list {
+        if seen.index(&val).is_err() {
+            result.push(val);
+        }
+    }
+
+    Ok(Value::List(result.into()))
+}
+
+// Returns an alist of (key values) pairs, in the order each key was first seen
+pub fn group_by(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut keys = ValueIndex::new();
+    let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+    for val in list {
+        let key = try!(lisp.call(&func, vec![val.clone()]));
+
+        match keys.index(&key) {
+            Ok(index) => groups[index].1.push(val),
+            Err(_) => groups.push((key, vec![val])),
+        }
+    }
+
+    let alist = groups.into_iter()
+        .map(|(key, group)| Value::List(vec![key, Value::List(group.into())].into()))
+        .collect();
+
+    Ok(Value::List(alist))
+}
+
+// Splits a list into two lists, the elements that satisfy the predicate and the ones that don't
+pub fn partition(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut matching = Vec::new();
+    let mut rest = Vec::new();
+
+    for val in list {
+        if try!(call_predicate(&func, &val, lisp)) {
+            matching.push(val);
+        } else {
+            rest.push(val);
+        }
+    }
+
+    Ok(Value::List(vec![Value::List(matching.into()), Value::List(rest.into())].into()))
+}
+
+// (sort list) sorts numbers, strings or chars in ascending order,
+// (sort less? list) sorts using the given "less than" function instead
+pub fn sort(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let sorted = if vals.len() == 2 {
+        let func = vals.remove(0);
+        let list = try!(vals.remove(0).as_list()).to_vec();
+
+        try!(merge_sort(list, &mut |a, b| {
+            let result = try!(lisp.call(&func, vec![a.clone(), b.clone()]));
+            bool::from_lisp(result)
+        }))
+    } else {
+        let list = try!(vals.remove(0).as_list()).to_vec();
+        try!(merge_sort(list, &mut natural_less))
+    };
+
+    Ok(Value::List(sorted.into()))
+}
+
+// Sorts by the result of calling the key function on each element, which is only called once per element
+pub fn sort_by(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let func = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    let mut keyed = Vec::new();
+    for val in list {
+        let key = try!(lisp.call(&func, vec![val.clone()]));
+        keyed.push((key, val));
+    }
+
+    let sorted = try!(merge_sort(keyed, &mut |a, b| natural_less(&a.0, &b.0)));
+    Ok(Value::List(sorted.into_iter().map(|(_, val)| val).collect()))
+}
+
+pub fn index_of(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let target = vals.remove(0);
+    let list = try!(vals.remove(0).as_list());
+
+    match list.iter().position(|val| *val == target) {
+        Some(index) => Ok((index as f32).to_lisp()),
+        None => Ok(Value::Nil),
+    }
+}
+
+// Returns an alist of (value count) pairs, in the order each value was first seen
+pub fn frequencies(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    let mut seen = ValueIndex::new();
+    let mut counts: Vec<(Value, usize)> = Vec::new();
+    for val in list {
+        match seen.index(&val) {
+            Ok(index) => counts[index].1 += 1,
+            Err(_) => counts.push((val, 1)),
+        }
+    }
+
+    let alist = counts.into_iter()
+        .map(|(val, count)| Value::List(vec![val, (count as f32).to_lisp()].into()))
+        .collect();
+
+    Ok(Value::List(alist))
+}
+
+// Byte ops
+fn index_arg(val: Value, len: usize) -> Result<usize, FuncError> {
+    let index = try!(f32::from_lisp(val));
+
+    if index < 0.0 || index.fract() != 0.0 || index as usize > len {
+        return Err(FuncError::IndexOutOfBounds {
+            index: index,
+            len: len,
+        });
+    }
+
+    Ok(index as usize)
+}
+
+fn byte_arg(val: Value) -> Result<u8, FuncError> {
+    let byte = try!(f32::from_lisp(val));
+
+    if byte < 0.0 || byte > 255.0 || byte.fract() != 0.0 {
+        return Err(FuncError::InvalidByte(byte));
+    }
+
+    Ok(byte as u8)
+}
+
+pub fn bytes(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut bytes = Vec::new();
+
+    for val in vals {
+        bytes.push(try!(byte_arg(val)));
+    }
+
+    Ok(bytes.to_lisp())
+}
+
+pub fn bytes_ref(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let bytes = try!(Vec::<u8>::from_lisp(vals.remove(0)));
+    let index = try!(index_arg(vals.remove(0), bytes.len()));
+
+    match bytes.get(index) {
+        Some(&byte) => Ok((byte as f32).to_lisp()),
+        None => Err(FuncError::IndexOutOfBounds {
+            index: index as f32,
+            len: bytes.len(),
+        }),
+    }
+}
+
+pub fn bytes_slice(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let bytes = try!(Vec::<u8>::from_lisp(vals.remove(0)));
+    let start = try!(index_arg(vals.remove(0), bytes.len()));
+
+    let end = if vals.len() == 1 {
+        try!(index_arg(vals.remove(0), bytes.len()))
+    } else {
+        bytes.len()
+    };
+
+    if end < start {
+        return Err(FuncError::IndexOutOfBounds {
+            index: end as f32,
+            len: bytes.len(),
+        });
+    }
+
+    Ok(bytes[start..end].to_lisp())
+}
+
+pub fn bytes_append(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut result = Vec::new();
+
+    for val in vals {
+        result.extend(try!(Vec::<u8>::from_lisp(val)));
+    }
+
+    Ok(result.to_lisp())
+}
+
+pub fn utf8_to_string(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let bytes = try!(Vec::<u8>::from_lisp(vals.remove(0)));
+
+    match String::from_utf8(bytes) {
+        Ok(string) => Ok(string.to_lisp()),
+        Err(err) => Err(FuncError::DecodeError(format!("{}", err.utf8_error()))),
+    }
+}
+
+pub fn string_to_utf8(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let string = try!(String::from_lisp(vals.remove(0)));
+    Ok(string.into_bytes().to_lisp())
+}
+
+pub fn bytes_to_hex(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let bytes = try!(Vec::<u8>::from_lisp(vals.remove(0)));
+    Ok(encoding::hex_encode(&bytes).to_lisp())
+}
+
+pub fn hex_to_bytes(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let text = try!(String::from_lisp(vals.remove(0)));
+    encoding::hex_decode(&text).map(ToLisp::to_lisp).map_err(FuncError::DecodeError)
+}
+
+pub fn base64_encode(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let bytes = try!(Vec::<u8>::from_lisp(vals.remove(0)));
+    Ok(encoding::base64_encode(&bytes).to_lisp())
+}
+
+pub fn base64_decode(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let text = try!(String::from_lisp(vals.remove(0)));
+    encoding::base64_decode(&text).map(ToLisp::to_lisp).map_err(FuncError::DecodeError)
+}
+
+// Regex ops
+fn regex_arg(val: Value, lisp: &mut Lisp) -> Result<Regex, FuncError> {
+    match val {
+        Value::Regex(re) => Ok(re),
+        Value::String(pattern) => lisp.regex(&pattern),
+        _ => Err(FuncError::InvalidType {
+            expected: vec![Type::Regex, Type::String],
+            got: val,
+        }),
+    }
+}
+
+pub fn re_compile(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    Ok(re.to_lisp())
+}
+
+pub fn re_is_match(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+
+    Ok(re.is_match(&text).to_lisp())
+}
+
+pub fn re_find(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+
+    match re.find(&text) {
+        Some(found) => Ok(found.as_str().to_string().to_lisp()),
+        None => Ok(Value::Nil),
+    }
+}
+
+pub fn re_find_all(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+
+    let found = re.find_iter(&text)
+        .map(|found| found.as_str().to_string().to_lisp())
+        .collect();
+
+    Ok(Value::List(found))
+}
+
+// Returns an alist of (group value) pairs, where group is the group's name if it has one,
+// or its index otherwise. Groups that didn't participate in the match are nil.
+pub fn re_captures(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+
+    let caps = match re.captures(&text) {
+        Some(caps) => caps,
+        None => return Ok(Value::Nil),
+    };
+
+    let mut groups = Vec::new();
+    for (index, name) in re.capture_names().enumerate() {
+        let key = match name {
+            Some(name) => name.to_string().to_lisp(),
+            None => (index as f32).to_lisp(),
+        };
+
+        let value = match caps.get(index) {
+            Some(group) => group.as_str().to_string().to_lisp(),
+            None => Value::Nil,
+        };
+
+        groups.push(Value::List(vec![key, value].into()));
+    }
+
+    Ok(Value::List(groups.into()))
+}
+
+// The replacement can either be a string (which may refer to groups with $name),
+// or a function which is called with each matched string and returns its replacement
+pub fn re_replace(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+    let replacement = vals.remove(0);
+
+    if let Value::String(replacement) = replacement {
+        return Ok(re.replace_all(&text, &replacement[..]).to_string().to_lisp());
+    }
+
+    let mut result = String::new();
+    let mut last_end = 0;
+
+    for found in re.find_iter(&text) {
+        let replaced = try!(lisp.call(&replacement, vec![found.as_str().to_string().to_lisp()]));
+        let replaced = try!(String::from_lisp(replaced));
+
+        result.push_str(&text[last_end .. found.start()]);
+        result.push_str(&replaced);
+        last_end = found.end();
+    }
+
+    result.push_str(&text[last_end..]);
+    Ok(result.to_lisp())
+}
+
+pub fn re_split(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let re = try!(regex_arg(vals.remove(0), lisp));
+    let text = try!(String::from_lisp(vals.remove(0)));
+
+    let parts = re.split(&text)
+        .map(|part| part.to_string().to_lisp())
+        .collect();
+
+    Ok(Value::List(parts))
+}
+
+// Math ops
+fn divisor(val: Value) -> Result<f32, FuncError> {
+    let num = try!(f32::from_lisp(val));
+
+    if num == 0.0 {
+        return Err(FuncError::DivisionByZero);
+    }
+
+    Ok(num)
+}
+
+pub fn min(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut result = f32::INFINITY;
+
+    for val in vals {
+        result = result.min(try!(f32::from_lisp(val)));
+    }
+
+    Ok(result.to_lisp())
+}
+
+pub fn max(vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let mut result = f32::NEG_INFINITY;
+
+    for val in vals {
+        result = result.max(try!(f32::from_lisp(val)));
+    }
+
+    Ok(result.to_lisp())
+}
+
+pub fn expt(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let base = try!(f32::from_lisp(vals.remove(0)));
+    let power = try!(f32::from_lisp(vals.remove(0)));
+
+    Ok(base.powf(power).to_lisp())
+}
+
+// Natural log, unless a base is given as the second argument
+pub fn log(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let num = try!(f32::from_lisp(vals.remove(0)));
+
+    if vals.len() == 1 {
+        let base = try!(f32::from_lisp(vals.remove(0)));
+        Ok(num.log(base).to_lisp())
+    } else {
+        Ok(num.ln().to_lisp())
+    }
+}
+
+pub fn atan2(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    let y = try!(f32::from_lisp(vals.remove(0)));
+    let x = try!(f32::from_lisp(vals.remove(0)));
+
+    Ok(y.atan2(x).to_lisp())
+}
+
+// Random
+pub fn random(_: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    Ok(lisp.rng.next_f32().to_lisp())
+}
+
+/// Integers any bigger than this can't all be told apart as f32s, so seeds and random ranges are kept within it
+pub const MAX_EXACT_INTEGER: f32 = 16777216.0; // 2^24
+
+fn exact_integer(num: f32) -> Result<f32, FuncError> {
+    match num.abs() <= MAX_EXACT_INTEGER {
+        true => Ok(num),
+        false => Err(FuncError::InexactInteger(num)),
+    }
+}
+
+// With one argument, returns an integer in [0, end), otherwise an integer in [start, end)
+pub fn random_int(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let start = if vals.len() == 2 {
+        try!(exact_integer(try!(f32::from_lisp(vals.remove(0))).floor()))
+    } else {
+        0.0
+    };
+
+    let end = try!(exact_integer(try!(f32::from_lisp(vals.remove(0))).floor()));
+
+    if end <= start {
+        return Err(FuncError::EmptyRange {
+            start: start,
+            end: end,
+        });
+    }
+
+    let offset = lisp.rng.below((end - start) as u64);
+    // The offset can be more than an f32 holds exactly, the result can't
+    Ok(((start as f64 + offset as f64) as f32).to_lisp())
+}
+
+pub fn random_choice(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let list = try!(vals.remove(0).as_list());
+
+    if list.len() == 0 {
+        return Err(FuncError::GivenEmptyList);
+    }
+
+    let index = lisp.rng.below(list.len() as u64) as usize;
+    Ok(list.iter().nth(index).unwrap().clone())
+}
+
+pub fn shuffle(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    let mut list = try!(vals.remove(0).as_list()).to_vec();
+
+    // Fisher-Yates
+    for i in (1 .. list.len()).rev() {
+        let j = lisp.rng.below(i as u64 + 1) as usize;
+        list.swap(i, j);
+    }
+
+    Ok(Value::List(list.into()))
+}
+
+pub fn seed_random(mut vals: Vec<Value>, lisp: &mut Lisp) -> FuncResult {
+    // Seeds are whole numbers so that no two seeds that are told apart give the same numbers
+    let seed = try!(exact_integer(try!(f32::from_lisp(vals.remove(0)))));
+    if seed.fract() != 0.0 {
+        return Err(FuncError::InexactInteger(seed));
+    }
+
+    lisp.seed_random(seed as i64 as u64);
+
+    Ok(Value::Nil)
+}
+
+math!(add, ops::Add::add);
+math!(sub, ops::Sub::sub);
+math!(mul, ops::Mul::mul);
+
+division!(div, ops::Div::div);
+division!(modulo, |a: f32, b: f32| a - b * (a / b).floor()); // Result has the sign of the divisor
+division!(rem, ops::Rem::rem); // Result has the sign of the dividend
+division!(quot, |a: f32, b: f32| (a / b).trunc());
+
+unary_math!(abs, f32::abs);
+unary_math!(floor, f32::floor);
+unary_math!(ceil, f32::ceil);
+unary_math!(round, f32::round);
+unary_math!(truncate, f32::trunc);
+unary_math!(sqrt, f32::sqrt);
+unary_math!(exp, f32::exp);
+unary_math!(sin, f32::sin);
+unary_math!(cos, f32::cos);
+unary_math!(tan, f32::tan);
+
+compare!(greater_than, >);
+compare!(less_than, <);
+compare!(greater_eq, >=);
+compare!(less_eq, <=);
+
+unary_math!(is_nan, f32::is_nan);
+unary_math!(is_infinite, f32::is_infinite);
+unary_math!(is_finite, f32::is_finite);
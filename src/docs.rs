@@ -0,0 +1,143 @@
+//! Documentation for functions, from the docstrings of builtins and of functions made with `def`.
+//!
+//! `(doc 'name)`, `(apropos "text")` and `(arity 'name)` look things up from rlisp,
+//! and `markdown` writes a reference for everything in an environment.
+
+use std::fmt::Write;
+
+use env::Env;
+use eval::Lisp;
+use symbol::Symbol;
+use value::{Value, Args};
+
+/// What there is to know about a function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncDoc {
+    pub name: Symbol,
+    /// How it's called, e.g. `(map f list...)`
+    pub usage: String,
+    pub args: Args,
+    /// What it does, if it's documented
+    pub text: Option<String>,
+    pub builtin: bool,
+}
+
+impl FuncDoc {
+    /// Documents a function, None if the value isn't one
+    pub fn new(name: Symbol, value: &Value) -> Option<FuncDoc> {
+        match value {
+            &Value::HardFunc(ref func) => {
+                let (usage, text) = match func.doc {
+                    Some(doc) => match doc.find('\n') {
+                        Some(newline) => (doc[..newline].to_string(), Some(doc[newline + 1..].to_string())),
+                        None => (doc.to_string(), None),
+                    },
+                    None => (format!("({} ...)", name), None),
+                };
+
+                Some(FuncDoc {
+                    name: name,
+                    usage: usage,
+                    args: func.args.clone(),
+                    text: text,
+                    builtin: true,
+                })
+            },
+            &Value::Lambda(ref lambda) => {
+                let params = lambda.params.to_string();
+                let usage = match params.is_empty() {
+                    true => format!("({})", name),
+                    false => format!("({} {})", name, params),
+                };
+
+                Some(FuncDoc {
+                    name: name,
+                    usage: usage,
+                    args: arity(value).unwrap(),
+                    text: lambda.doc.clone(),
+                    builtin: false,
+                })
+            },
+            _ => None,
+        }
+    }
+
+    /// The usage, then what it does and how many arguments it takes, as `(doc 'name)` gives it
+    pub fn to_text(&self) -> String {
+        let mut text = self.usage.clone();
+        if let Some(ref doc) = self.text {
+            text.push('\n');
+            text.push_str(doc);
+        }
+
+        let _ = write!(text, "\nTakes {}.", self.args);
+        text
+    }
+}
+
+/// How many arguments a function takes, None if the value isn't a function
+pub fn arity(value: &Value) -> Option<Args> {
+    match value {
+        &Value::HardFunc(ref func) => Some(func.args.clone()),
+        &Value::Lambda(ref lambda) => Some(lambda.params.arity()),
+        _ => None,
+    }
+}
+
+/// The documentation of whatever `name` is bound to
+pub fn lookup(lisp: &Lisp, name: Symbol) -> Option<FuncDoc> {
+    lisp.lookup(name).ok().and_then(|value| FuncDoc::new(name, &value))
+}
+
+/// The names of everything defined that contain the text, sorted and without duplicates
+pub fn apropos(lisp: &Lisp, text: &str) -> Vec<Symbol> {
+    let mut names: Vec<Symbol> = lisp.scopes.iter()
+        .flat_map(|scope| scope.map.keys())
+        .filter(|name| name.as_str().contains(text))
+        .cloned()
+        .collect();
+
+    names.sort_by_key(|name| name.as_str());
+    names.dedup();
+
+    names
+}
+
+/// A Markdown reference for everything in an environment, functions first and then the other values
+pub fn markdown(env: &Env, title: &str) -> String {
+    let mut bindings: Vec<(Symbol, &Value)> = env.map.iter().map(|(&name, value)| (name, value)).collect();
+    bindings.sort_by_key(|&(name, _)| name.as_str());
+
+    let mut out = format!("# {}\n", title);
+
+    let funcs: Vec<FuncDoc> = bindings.iter().filter_map(|&(name, value)| FuncDoc::new(name, value)).collect();
+    if !funcs.is_empty() {
+        out.push_str("\n## Functions\n");
+    }
+
+    for func in &funcs {
+        let _ = write!(out, "\n### `{}`\n\n```lisp\n{}\n```\n\n", func.name, func.usage);
+
+        if let Some(ref text) = func.text {
+            let _ = write!(out, "{}\n\n", text);
+        }
+
+        let _ = writeln!(out, "Takes {}.", func.args);
+    }
+
+    let values: Vec<&(Symbol, &Value)> = bindings.iter()
+        .filter(|&&(_, value)| match value {
+            &Value::HardFunc(_) | &Value::Lambda(_) => false,
+            _ => true,
+        })
+        .collect();
+    if !values.is_empty() {
+        out.push_str("\n## Values\n\n| Name | Value |\n| --- | --- |\n");
+    }
+
+    for &&(name, value) in &values {
+        let _ = writeln!(out, "| `{}` | `{}` |", name, format!("{:?}", value).replace('|', "\\|"));
+    }
+
+    out
+}
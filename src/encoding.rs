@@ -0,0 +1,97 @@
+const HEX_DIGITS: &'static [u8] = b"0123456789abcdef";
+const BASE64_DIGITS: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        result.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        result.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+    }
+
+    result
+}
+
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    if text.len() % 2 != 0 {
+        return Err(format!("hex string {:?} has an odd number of digits", text));
+    }
+
+    let mut result = Vec::with_capacity(text.len() / 2);
+
+    for pair in text.as_bytes().chunks(2) {
+        let high = try!(hex_digit(pair[0]));
+        let low = try!(hex_digit(pair[1]));
+
+        result.push(high << 4 | low);
+    }
+
+    Ok(result)
+}
+
+fn hex_digit(digit: u8) -> Result<u8, String> {
+    match digit {
+        b'0' ... b'9' => Ok(digit - b'0'),
+        b'a' ... b'f' => Ok(digit - b'a' + 10),
+        b'A' ... b'F' => Ok(digit - b'A' + 10),
+        _ => Err(format!("{:?} is not a hex digit", digit as char)),
+    }
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0] as usize;
+        let b1 = if chunk.len() > 1 { chunk[1] as usize } else { 0 };
+        let b2 = if chunk.len() > 2 { chunk[2] as usize } else { 0 };
+
+        result.push(BASE64_DIGITS[b0 >> 2] as char);
+        result.push(BASE64_DIGITS[(b0 & 0x3) << 4 | b1 >> 4] as char);
+
+        if chunk.len() > 1 {
+            result.push(BASE64_DIGITS[(b1 & 0xf) << 2 | b2 >> 6] as char);
+        } else {
+            result.push('=');
+        }
+
+        if chunk.len() > 2 {
+            result.push(BASE64_DIGITS[b2 & 0x3f] as char);
+        } else {
+            result.push('=');
+        }
+    }
+
+    result
+}
+
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim_right_matches('=');
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for ch in text.chars() {
+        let value = match BASE64_DIGITS.iter().position(|&digit| digit as char == ch) {
+            Some(value) => value as u32,
+            None => return Err(format!("{:?} is not a base64 digit", ch)),
+        };
+
+        buffer = buffer << 6 | value;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    // Leftover bits can only be padding, anything more means the input was truncated
+    if bits >= 6 || buffer != 0 {
+        return Err(format!("base64 string {:?} has an invalid length", text));
+    }
+
+    Ok(result)
+}
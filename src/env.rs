@@ -1,19 +1,20 @@
 
-use std::collections::HashMap;
+use std::f32;
 
 use value::{func, Value, Args, ToLisp};
+use symbol::{Symbol, SymbolMap};
 use valtype::Type;
 use default_env;
 
 #[derive(Debug)]
 pub struct Env {
-    pub map: HashMap<String, Value>,
+    pub map: SymbolMap<Value>,
 }
 
 impl Env {
     pub fn new() -> Env {
         Env {
-            map: HashMap::new()
+            map: SymbolMap::default()
         }
     }
 
@@ -29,67 +30,316 @@ impl Env {
         // Types
         env.set("Number", Type::Number);
         env.set("Bool", Type::Bool);
+        env.set("Char", Type::Char);
         env.set("String", Type::String);
+        env.set("Bytes", Type::Bytes);
         env.set("Symbol", Type::Symbol);
         env.set("List", Type::List);
         env.set("Nil", Type::Nil);
         env.set("ForeignFunc", Type::HardFunc);
         env.set("Lambda", Type::Lambda);
+        env.set("Regex", Type::Regex);
 
-        env.set("type", func(default_env::type_of, Args::Fixed(1)));
+        env.set("type", func(default_env::type_of, Args::Fixed(1))
+            .with_doc("(type value)\nThe type of a value, e.g. Number or List."));
 
         // Core functions
-        env.set("eval", func(default_env::eval, Args::Fixed(1)));
-        env.set("id", func(default_env::id, Args::Fixed(1)));
-        env.set("if", func(default_env::if_fn, Args::Fixed(3)));
-        env.set("def", func(default_env::define, Args::Atleast(2)));
-        env.set("let", func(default_env::let_fn, Args::Atleast(2)));
-        env.set(r"\", func(default_env::lambda, Args::Atleast(2)));
+        env.set("eval", func(default_env::eval, Args::Fixed(1))
+            .with_doc("(eval form)\nEvaluates a form, so (eval {+ 1 2}) is 3."));
+        env.set("id", func(default_env::id, Args::Fixed(1))
+            .with_doc("(id value)\nGives back its argument unchanged."));
+        env.set("if", func(default_env::if_fn, Args::Fixed(3))
+            .with_doc("(if cond then else)\nEvaluates then if cond is true, otherwise else. Quote both branches with braces so that only the chosen one is evaluated."));
+        env.set("def", func(default_env::define, Args::Atleast(2))
+            .with_doc("(def 'name value) or (def {name params...} body...)\nDefines a variable, or a function taking the given parameters. A string at the start of a function's body is its docstring. (def {(a b . rest)} list) destructures a list into several variables, and (def {(&keys a b)} alist) looks them up by key."));
+        env.set("let", func(default_env::let_fn, Args::Atleast(2))
+            .with_doc("(let {(name value)...} body...)\nBinds each name to its value, then evaluates the body with them. A pattern like (a (b c) . rest) can be used in place of a name to take a list apart."));
+        env.set(r"\", func(default_env::lambda, Args::Atleast(2))
+            .with_doc("(\\ {params...} body...)\nMakes an anonymous function (a lambda). Parameters after &optional can be left out, &rest takes a list of the remaining arguments, and &key parameters are passed as 'name value. Required parameters can be patterns, which destructure their argument."));
 
-        env.set("seq", func(default_env::seq, Args::Atleast(1)));
+        env.set("seq", func(default_env::seq, Args::Atleast(1))
+            .with_doc("(seq form...)\nEvaluates each form in order, giving the value of the last one."));
+        env.set("break", func(default_env::break_fn, Args::Fixed(0))
+            .with_doc("(break)\nPauses in the debugger, if one is attached. Does nothing otherwise."));
+        env.set("trace", func(default_env::trace, Args::Variant)
+            .with_doc("(trace 'name...)\nLogs every call to the named functions and what they return, giving the list of everything traced."));
+        env.set("untrace", func(default_env::untrace, Args::Variant)
+            .with_doc("(untrace 'name...)\nStops tracing the named functions, or every function if no names are given."));
+        env.set("deftest", func(default_env::deftest, Args::Atleast(1))
+            .with_doc("(deftest 'name form...)\nRegisters a test, whose quoted forms are evaluated when it's run by `rlisp --test`."));
+        env.set("assert", func(default_env::assert, Args::Multiple(vec![1, 2]))
+            .with_doc("(assert cond [message])\nFails with the message if cond is false."));
+        env.set("assert-equal", func(default_env::assert_equal, Args::Fixed(2))
+            .with_doc("(assert-equal expected actual)\nFails if the values aren't equal, showing the difference."));
+        env.set("assert-error", func(default_env::assert_error, Args::Multiple(vec![1, 2]))
+            .with_doc("(assert-error {form} [text])\nFails unless evaluating the quoted form gives an error (containing the text, if it's given)."));
+
+        // Functions
+        env.set("apply", func(default_env::apply, Args::Atleast(2))
+            .with_doc("(apply f arg... list)\nCalls f with the arguments followed by the items of the list, which aren't evaluated again."));
+        env.set("partial", func(default_env::partial, Args::Atleast(1))
+            .with_doc("(partial f arg...)\nA function that calls f with the arguments, followed by whatever it's given."));
+        env.set("comp", func(default_env::comp, Args::Variant)
+            .with_doc("(comp f...)\nComposes functions, so ((comp f g) x) is (f (g x)). The last function can take any number of arguments."));
+        env.set("identity", func(default_env::id, Args::Fixed(1))
+            .with_doc("(identity value)\nGives back its argument unchanged, the same as id."));
+        env.set("constantly", func(default_env::constantly, Args::Fixed(1))
+            .with_doc("(constantly value)\nA function that takes any arguments and always gives the value."));
+        env.set("juxt", func(default_env::juxt, Args::Atleast(1))
+            .with_doc("(juxt f...)\nA function that calls each function with its arguments, giving a list of the results."));
+        env.set("memoize", func(default_env::memoize, Args::Fixed(1))
+            .with_doc("(memoize f)\nA function that calls f once for each different set of arguments, remembering the result."));
+
+        // Documentation
+        env.set("doc", func(default_env::doc, Args::Fixed(1))
+            .with_doc("(doc 'name)\nHow a function is called and what it does, or nil if the name isn't a function."));
+        env.set("apropos", func(default_env::apropos, Args::Fixed(1))
+            .with_doc("(apropos text)\nThe names of everything defined that contain the text, sorted."));
+        env.set("arity", func(default_env::arity, Args::Fixed(1))
+            .with_doc("(arity f)\nHow many arguments a function (or the function with the given name) takes, e.g. \"at least 2 arguments\"."));
 
         // Booleans
-        env.set("and", func(default_env::and, Args::Variant));
-        env.set("or", func(default_env::or, Args::Variant));
-        env.set("not", func(default_env::not, Args::Fixed(1)));
+        env.set("and", func(default_env::and, Args::Variant)
+            .with_doc("(and bool...)\nTrue if every argument is true."));
+        env.set("or", func(default_env::or, Args::Variant)
+            .with_doc("(or bool...)\nTrue if any argument is true."));
+        env.set("not", func(default_env::not, Args::Fixed(1))
+            .with_doc("(not bool)\nTrue if the argument is false."));
 
         // Ops
-        env.set("+", func(default_env::add, Args::Atleast(2)));
-        env.set("-", func(default_env::sub, Args::Atleast(2)));
-        env.set("*", func(default_env::mul, Args::Atleast(2)));
-        env.set("/", func(default_env::div, Args::Atleast(2)));
+        env.set("+", func(default_env::add, Args::Atleast(2))
+            .with_doc("(+ num num...)\nAdds the numbers."));
+        env.set("-", func(default_env::sub, Args::Atleast(2))
+            .with_doc("(- num num...)\nSubtracts the rest of the numbers from the first."));
+        env.set("*", func(default_env::mul, Args::Atleast(2))
+            .with_doc("(* num num...)\nMultiplies the numbers."));
+        env.set("/", func(default_env::div, Args::Atleast(2))
+            .with_doc("(/ num num...)\nDivides the first number by the rest, failing on division by zero."));
+
+        env.set("mod", func(default_env::modulo, Args::Fixed(2))
+            .with_doc("(mod a b)\nThe remainder of dividing a by b, with the sign of b."));
+        env.set("rem", func(default_env::rem, Args::Fixed(2))
+            .with_doc("(rem a b)\nThe remainder of dividing a by b, with the sign of a."));
+        env.set("quot", func(default_env::quot, Args::Fixed(2))
+            .with_doc("(quot a b)\nDivides a by b, rounding towards zero."));
+
+        env.set("=", func(default_env::eq, Args::Atleast(2))
+            .with_doc("(= value value...)\nTrue if all of the values are equal."));
+        env.set("!=", func(default_env::not_eq, Args::Atleast(2))
+            .with_doc("(!= value value...)\nTrue unless all of the values are equal."));
+        env.set(">", func(default_env::greater_than, Args::Atleast(2))
+            .with_doc("(> num num...)\nTrue if each number is greater than the next."));
+        env.set("<", func(default_env::less_than, Args::Atleast(2))
+            .with_doc("(< num num...)\nTrue if each number is less than the next."));
+        env.set(">=", func(default_env::greater_eq, Args::Atleast(2))
+            .with_doc("(>= num num...)\nTrue if each number is greater than or equal to the next."));
+        env.set("<=", func(default_env::less_eq, Args::Atleast(2))
+            .with_doc("(<= num num...)\nTrue if each number is less than or equal to the next."));
+
+        // Math
+        env.set("pi", f32::consts::PI);
+        env.set("e", f32::consts::E);
+
+        env.set("abs", func(default_env::abs, Args::Fixed(1))
+            .with_doc("(abs num)\nThe absolute value of a number."));
+        env.set("min", func(default_env::min, Args::Atleast(1))
+            .with_doc("(min num...)\nThe smallest of the numbers."));
+        env.set("max", func(default_env::max, Args::Atleast(1))
+            .with_doc("(max num...)\nThe largest of the numbers."));
+
+        env.set("floor", func(default_env::floor, Args::Fixed(1))
+            .with_doc("(floor num)\nRounds down to an integer."));
+        env.set("ceil", func(default_env::ceil, Args::Fixed(1))
+            .with_doc("(ceil num)\nRounds up to an integer."));
+        env.set("round", func(default_env::round, Args::Fixed(1))
+            .with_doc("(round num)\nRounds to the nearest integer, halfway cases away from zero."));
+        env.set("truncate", func(default_env::truncate, Args::Fixed(1))
+            .with_doc("(truncate num)\nRounds towards zero."));
+
+        env.set("sqrt", func(default_env::sqrt, Args::Fixed(1))
+            .with_doc("(sqrt num)\nThe square root of a number."));
+        env.set("expt", func(default_env::expt, Args::Fixed(2))
+            .with_doc("(expt base power)\nRaises base to a power."));
+        env.set("exp", func(default_env::exp, Args::Fixed(1))
+            .with_doc("(exp num)\ne raised to a power."));
+        env.set("log", func(default_env::log, Args::Multiple(vec![1, 2]))
+            .with_doc("(log num [base])\nThe natural log of a number, or its log in the given base."));
+
+        env.set("sin", func(default_env::sin, Args::Fixed(1))
+            .with_doc("(sin radians)\nThe sine of an angle."));
+        env.set("cos", func(default_env::cos, Args::Fixed(1))
+            .with_doc("(cos radians)\nThe cosine of an angle."));
+        env.set("tan", func(default_env::tan, Args::Fixed(1))
+            .with_doc("(tan radians)\nThe tangent of an angle."));
+        env.set("atan2", func(default_env::atan2, Args::Fixed(2))
+            .with_doc("(atan2 y x)\nThe angle from the x axis to the point (x, y), in radians."));
+
+        env.set("nan?", func(default_env::is_nan, Args::Fixed(1))
+            .with_doc("(nan? num)\nTrue if the number is NaN."));
+        env.set("infinite?", func(default_env::is_infinite, Args::Fixed(1))
+            .with_doc("(infinite? num)\nTrue if the number is positive or negative infinity."));
+        env.set("finite?", func(default_env::is_finite, Args::Fixed(1))
+            .with_doc("(finite? num)\nTrue unless the number is infinite or NaN."));
 
-        env.set("=", func(default_env::eq, Args::Atleast(2)));
-        env.set(">", func(default_env::greater_than, Args::Fixed(2)));
-        env.set("<", func(default_env::less_than, Args::Fixed(2)));
+        // Random
+        env.set("random", func(default_env::random, Args::Fixed(0))
+            .with_doc("(random)\nA random number from 0 up to (but not including) 1."));
+        env.set("random-int", func(default_env::random_int, Args::Multiple(vec![1, 2]))
+            .with_doc("(random-int [start] end)\nA random integer from start (or 0) up to, but not including, end. Both are rounded down, and have to be within 2^24 (16777216) of 0."));
+        env.set("random-choice", func(default_env::random_choice, Args::Fixed(1))
+            .with_doc("(random-choice list)\nA random element of a list."));
+        env.set("shuffle", func(default_env::shuffle, Args::Fixed(1))
+            .with_doc("(shuffle list)\nThe list in a random order."));
+        env.set("seed-random!", func(default_env::seed_random, Args::Fixed(1))
+            .with_doc("(seed-random! seed)\nSeeds the random number generator, so the same numbers come out each time. The seed is a whole number within 2^24 (16777216) of 0, for more seeds than that use Lisp::seed_random from Rust."));
 
-        env.set("print", func(default_env::print, Args::Variant));
-        env.set("input", func(default_env::input, Args::Multiple(vec![0, 1])));
-        env.set("exit", func(default_env::exit, Args::Multiple(vec![0, 1])));
+        env.set("print", func(default_env::print, Args::Variant)
+            .with_doc("(print value...)\nPrints the values and a newline. Strings and chars are printed without quotes."));
+        env.set("input", func(default_env::input, Args::Multiple(vec![0, 1]))
+            .with_doc("(input [prompt])\nPrints the prompt, then reads a line from stdin."));
+        env.set("exit", func(default_env::exit, Args::Multiple(vec![0, 1]))
+            .with_doc("(exit [code])\nExits the program with the given code, or 0."));
 
         // String operations
-        env.set("str", func(default_env::str_fn, Args::Variant));
+        env.set("str", func(default_env::str_fn, Args::Variant)
+            .with_doc("(str value...)\nJoins the values into a string. Strings and chars are added as they are."));
+
+        // Char ops
+        env.set("char->integer", func(default_env::char_to_integer, Args::Fixed(1))
+            .with_doc("(char->integer char)\nThe code point of a char."));
+        env.set("integer->char", func(default_env::integer_to_char, Args::Fixed(1))
+            .with_doc("(integer->char code)\nThe char with the given code point."));
+        env.set("alphabetic?", func(default_env::is_alphabetic, Args::Fixed(1))
+            .with_doc("(alphabetic? char)\nTrue if the char is a letter."));
+        env.set("whitespace?", func(default_env::is_whitespace, Args::Fixed(1))
+            .with_doc("(whitespace? char)\nTrue if the char is whitespace."));
+        env.set("digit?", func(default_env::is_digit, Args::Fixed(1))
+            .with_doc("(digit? char)\nTrue if the char is a decimal digit."));
 
         // List ops
-        env.set("list", func(default_env::list, Args::Variant));
-        env.set("range", func(default_env::range, Args::Multiple(vec![2, 3])));
-        env.set("len", func(default_env::len, Args::Fixed(1)));
-        env.set("empty?", func(default_env::is_empty, Args::Fixed(1)));
+        env.set("list", func(default_env::list, Args::Variant)
+            .with_doc("(list value...)\nA list of the values."));
+        env.set("range", func(default_env::range, Args::Multiple(vec![2, 3]))
+            .with_doc("(range [step] start end)\nA list of the integers from start up to, but not including, end."));
+        env.set("len", func(default_env::len, Args::Fixed(1))
+            .with_doc("(len list)\nThe length of a list or bytes."));
+        env.set("empty?", func(default_env::is_empty, Args::Fixed(1))
+            .with_doc("(empty? list)\nTrue if the list is empty."));
+
+        env.set("cons", func(default_env::cons, Args::Fixed(2))
+            .with_doc("(cons value list)\nThe list with the value added to the front."));
+        env.set("join", func(default_env::join, Args::Fixed(2))
+            .with_doc("(join list value)\nThe list with the value added to the end."));
+
+        env.set("head", func(default_env::head, Args::Fixed(1))
+            .with_doc("(head list)\nThe first element of a list."));
+        env.set("tail", func(default_env::tail, Args::Fixed(1))
+            .with_doc("(tail list)\nEverything but the first element of a list."));
+
+        env.set("map", func(default_env::map, Args::Atleast(2))
+            .with_doc("(map f list...)\nCalls f with an element from each list in turn, stopping at the end of the shortest, giving a list of the results."));
+        env.set("fold", func(default_env::fold, Args::Multiple(vec![2, 3]))
+            .with_doc("(fold f [initial] list)\nCombines the elements of a list from left to right with f, starting with initial (or the first element)."));
+        env.set("reduce", func(default_env::fold, Args::Fixed(2))
+            .with_doc("(reduce f list)\nCombines the elements of a list from left to right with f."));
+        env.set("filter", func(default_env::filter, Args::Fixed(2))
+            .with_doc("(filter pred list)\nThe elements of a list that pred is true for."));
+
+        env.set("reverse", func(default_env::reverse, Args::Fixed(1))
+            .with_doc("(reverse list)\nThe list in reverse order."));
+        env.set("append", func(default_env::append, Args::Variant)
+            .with_doc("(append list...)\nJoins lists together."));
+        env.set("concat", func(default_env::append, Args::Variant)
+            .with_doc("(concat list...)\nJoins lists together, the same as append."));
+        env.set("nth", func(default_env::nth, Args::Fixed(2))
+            .with_doc("(nth list index)\nThe element at an index of a list, counting from 0."));
+        env.set("last", func(default_env::last, Args::Fixed(1))
+            .with_doc("(last list)\nThe last element of a list."));
+        env.set("flatten", func(default_env::flatten, Args::Fixed(1))
+            .with_doc("(flatten list)\nThe elements of a list and of every list inside of it, as a single list."));
+        env.set("zip", func(default_env::zip, Args::Atleast(1))
+            .with_doc("(zip list...)\nLists of the elements at each position of the lists, stopping at the end of the shortest."));
 
-        env.set("cons", func(default_env::cons, Args::Fixed(2)));
-        env.set("join", func(default_env::join, Args::Fixed(2)));
+        env.set("take", func(default_env::take, Args::Fixed(2))
+            .with_doc("(take count list)\nThe first count elements of a list."));
+        env.set("drop", func(default_env::drop_fn, Args::Fixed(2))
+            .with_doc("(drop count list)\nThe list without its first count elements."));
+        env.set("take-while", func(default_env::take_while, Args::Fixed(2))
+            .with_doc("(take-while pred list)\nThe elements from the start of the list, up to the first one pred is false for."));
+        env.set("drop-while", func(default_env::drop_while, Args::Fixed(2))
+            .with_doc("(drop-while pred list)\nThe list from the first element pred is false for."));
 
-        env.set("head", func(default_env::head, Args::Fixed(1)));
-        env.set("tail", func(default_env::tail, Args::Fixed(1)));
+        env.set("find", func(default_env::find, Args::Fixed(2))
+            .with_doc("(find pred list)\nThe first element pred is true for, or nil."));
+        env.set("index-of", func(default_env::index_of, Args::Fixed(2))
+            .with_doc("(index-of value list)\nThe index of the first element equal to the value, or nil."));
+        env.set("any?", func(default_env::any, Args::Fixed(2))
+            .with_doc("(any? pred list)\nTrue if pred is true for any element."));
+        env.set("every?", func(default_env::every, Args::Fixed(2))
+            .with_doc("(every? pred list)\nTrue if pred is true for every element."));
+        env.set("count", func(default_env::count, Args::Fixed(2))
+            .with_doc("(count pred list)\nHow many elements pred is true for."));
 
-        env.set("map", func(default_env::map, Args::Fixed(2)));
-        env.set("fold", func(default_env::fold, Args::Multiple(vec![2, 3])));
-        env.set("filter", func(default_env::filter, Args::Fixed(2)));
+        env.set("distinct", func(default_env::distinct, Args::Fixed(1))
+            .with_doc("(distinct list)\nThe list without repeated elements."));
+        env.set("frequencies", func(default_env::frequencies, Args::Fixed(1))
+            .with_doc("(frequencies list)\nA list of (value count) pairs, in the order each value is first seen."));
+        env.set("group-by", func(default_env::group_by, Args::Fixed(2))
+            .with_doc("(group-by f list)\nA list of (key elements) pairs, grouping the elements by the key f gives for them."));
+        env.set("partition", func(default_env::partition, Args::Fixed(2))
+            .with_doc("(partition pred list)\nTwo lists, of the elements pred is true for and of the rest."));
+
+        env.set("sort", func(default_env::sort, Args::Multiple(vec![1, 2]))
+            .with_doc("(sort [less?] list)\nSorts numbers, strings or chars in ascending order, or by the given less than function."));
+        env.set("sort-by", func(default_env::sort_by, Args::Fixed(2))
+            .with_doc("(sort-by key list)\nSorts the elements by the key function gives for each of them."));
+
+        // Byte ops
+        env.set("bytes", func(default_env::bytes, Args::Variant)
+            .with_doc("(bytes byte...)\nBytes made from numbers from 0 to 255."));
+        env.set("bytes-ref", func(default_env::bytes_ref, Args::Fixed(2))
+            .with_doc("(bytes-ref bytes index)\nThe byte at an index, as a number."));
+        env.set("bytes-slice", func(default_env::bytes_slice, Args::Multiple(vec![2, 3]))
+            .with_doc("(bytes-slice bytes start [end])\nThe bytes from start up to end, or the end of the bytes."));
+        env.set("bytes-append", func(default_env::bytes_append, Args::Variant)
+            .with_doc("(bytes-append bytes...)\nJoins bytes together."));
+        env.set("utf8->string", func(default_env::utf8_to_string, Args::Fixed(1))
+            .with_doc("(utf8->string bytes)\nDecodes UTF-8 bytes as a string."));
+        env.set("string->utf8", func(default_env::string_to_utf8, Args::Fixed(1))
+            .with_doc("(string->utf8 string)\nThe UTF-8 bytes of a string."));
+        env.set("bytes->hex", func(default_env::bytes_to_hex, Args::Fixed(1))
+            .with_doc("(bytes->hex bytes)\nThe bytes as a hex string."));
+        env.set("hex->bytes", func(default_env::hex_to_bytes, Args::Fixed(1))
+            .with_doc("(hex->bytes string)\nDecodes a hex string."));
+        env.set("base64-encode", func(default_env::base64_encode, Args::Fixed(1))
+            .with_doc("(base64-encode bytes)\nThe bytes as a base64 string."));
+        env.set("base64-decode", func(default_env::base64_decode, Args::Fixed(1))
+            .with_doc("(base64-decode string)\nDecodes a base64 string."));
+
+        // Regex ops
+        env.set("re-compile", func(default_env::re_compile, Args::Fixed(1))
+            .with_doc("(re-compile pattern)\nCompiles a regex. Functions that take a regex also take a pattern string."));
+        env.set("re-match?", func(default_env::re_is_match, Args::Fixed(2))
+            .with_doc("(re-match? regex string)\nTrue if the regex matches anywhere in the string."));
+        env.set("re-find", func(default_env::re_find, Args::Fixed(2))
+            .with_doc("(re-find regex string)\nThe first match of the regex, or nil."));
+        env.set("re-find-all", func(default_env::re_find_all, Args::Fixed(2))
+            .with_doc("(re-find-all regex string)\nEvery match of the regex."));
+        env.set("re-captures", func(default_env::re_captures, Args::Fixed(2))
+            .with_doc("(re-captures regex string)\nA list of (group value) pairs for the first match, or nil."));
+        env.set("re-replace", func(default_env::re_replace, Args::Fixed(3))
+            .with_doc("(re-replace regex string replacement)\nReplaces every match with a string (which can refer to groups as $name) or what a function gives for it."));
+        env.set("re-split", func(default_env::re_split, Args::Fixed(2))
+            .with_doc("(re-split regex string)\nSplits the string on each match."));
 
         env
     }
 
-    pub fn set<T: ToLisp>(&mut self, name: &str, value: T) {
-        self.map.insert(name.to_string(), value.to_lisp());
+    pub fn set<S: Into<Symbol>, T: ToLisp>(&mut self, name: S, value: T) {
+        self.map.insert(name.into(), value.to_lisp());
+    }
+
+    pub fn get(&self, sym: Symbol) -> Option<&Value> {
+        self.map.get(&sym)
     }
 }
@@ -1,14 +1,16 @@
 
 use std::fmt;
 
+use default_env::MAX_EXACT_INTEGER;
 use eval::FuncError;
 use parse;
+use value::Value;
 
 impl fmt::Debug for FuncError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &FuncError::InvalidArguments { ref expected, got } => {
-                write!(fmt, "expected {:?} arguments, but got {}", expected, got)
+                write!(fmt, "expected {}, but got {}", expected, got)
             },
             &FuncError::InvalidType { ref expected, ref got } => {
                 try!(write!(fmt, "expected "));
@@ -18,6 +20,23 @@ impl fmt::Debug for FuncError {
             &FuncError::UndeclaredSymbol(ref sym) => {
                 write!(fmt, "Undefined symbol {}", sym)
             },
+            &FuncError::InvalidParameter(ref param) => {
+                write!(fmt, "Invalid parameter {:?}, parameters are names, or (name default) after &optional or &key", param)
+            },
+            &FuncError::InvalidPattern(ref pattern) => {
+                write!(fmt, "Invalid pattern {:?}, patterns are names or lists of patterns, which can end in . name", pattern)
+            },
+            &FuncError::PatternMismatch { ref pattern, ref value } => match value {
+                _ if pattern.starts_with("(&keys") => write!(fmt, "{:?} doesn't match the pattern {}, it's not a list of (key value) pairs", value, pattern),
+                &Value::List(ref list) => write!(fmt, "{:?} doesn't match the pattern {}, it has {} items", value, pattern, list.len()),
+                _ => write!(fmt, "{:?} doesn't match the pattern {}, it's a {:?} rather than a list", value, pattern, value.typ()),
+            },
+            &FuncError::UnknownKeyword(ref key) => {
+                write!(fmt, "{:?} is not a keyword this function takes", key)
+            },
+            &FuncError::MissingKeywordValue(ref key) => {
+                write!(fmt, "The keyword {} was given without a value", key)
+            },
             &FuncError::AttemptToCallNonFunction(ref val) => {
                 write!(fmt, "{:?} is not a callable function", val)
             },
@@ -27,12 +46,51 @@ impl fmt::Debug for FuncError {
             &FuncError::GivenEmptyList => {
                 write!(fmt, "Cannot take any elements out of any empty list")
             },
+            &FuncError::EmptyRange { start, end } => {
+                write!(fmt, "The range from {} to {} is empty", start, end)
+            },
+            &FuncError::DivisionByZero => {
+                write!(fmt, "Attempt to divide by zero")
+            },
             &FuncError::IoError(ref error) => {
                 write!(fmt, "An IO error occured: {:?}", error)
             },
             &FuncError::ParsingErr(ref err) => {
                 write!(fmt, "Error while parsing source code: {:?}", err)
             },
+            &FuncError::RegexError(ref err) => {
+                write!(fmt, "Invalid regex: {}", err)
+            },
+            &FuncError::InvalidCharCode(code) => {
+                write!(fmt, "{} is not a valid character code", code)
+            },
+            &FuncError::InvalidByte(byte) => {
+                write!(fmt, "{} is not a byte, bytes are whole numbers from 0 to 255", byte)
+            },
+            &FuncError::InexactInteger(num) => {
+                write!(fmt, "{} can't be used here, only whole numbers from -{max} to {max} can be, since numbers are 32 bit floats", num, max = MAX_EXACT_INTEGER)
+            },
+            &FuncError::IndexOutOfBounds { index, len } => {
+                write!(fmt, "Index {} is out of bounds for a length of {}", index, len)
+            },
+            &FuncError::DecodeError(ref err) => {
+                write!(fmt, "Could not decode: {}", err)
+            },
+            &FuncError::InvalidBytecode(ref err) => {
+                write!(fmt, "Invalid compiled code: {}", err)
+            },
+            &FuncError::BytecodeVersion { expected, found } => {
+                write!(fmt, "Compiled code is format version {}, but only version {} can be loaded (recompile it, or compile it with its source)", found, expected)
+            },
+            &FuncError::StoppedByDebugger => {
+                write!(fmt, "Stopped by the debugger")
+            },
+            &FuncError::AssertionFailed(ref message) => {
+                write!(fmt, "Assertion failed: {}", message)
+            },
+            &FuncError::NotEqual { ref expected, ref actual } => {
+                write!(fmt, "Assertion failed: expected {:?}, but got {:?}", expected, actual)
+            },
             &FuncError::UserError(ref err) => {
                 write!(fmt, "Error from Rust: {}", err)
             }
@@ -1,11 +1,25 @@
 use std::io::{self, Read};
 use std::cmp;
+use std::rc::Rc;
 use std::fmt;
+use std::collections::HashMap;
+
+use regex;
 
 use parse::{self, ParseError};
-use value::{Value, Args, FromLisp, ToLisp};
+use value::{Value, Args, Lambda, Regex, FromLisp, ToLisp};
 use valtype::Type;
 use env::Env;
+use rng::Rng;
+use symbol::Symbol;
+use list::List;
+use vm;
+use pretty;
+use compile::{self, FormCache, Chunk};
+use resolve::{self, Analysis};
+use bytecode::{self, Compiled};
+use profile::{self, Tracer, Profiler, Profile};
+use testing::Test;
 
 pub type FuncResult = Result<Value, FuncError>;
 
@@ -18,37 +32,245 @@ pub enum FuncError {
         expected: Vec<Type>,
         got: Value,
     },
-    UndeclaredSymbol(String),
+    UndeclaredSymbol(Symbol),
+
+    InvalidParameter(Value),
+    InvalidPattern(Value),
+    PatternMismatch {
+        pattern: String,
+        value: Value,
+    },
+    UnknownKeyword(Value),
+    MissingKeywordValue(Symbol),
 
     AttemptToCallNonFunction(Value),
     AttemptToEvalEmptyList,
 
     GivenEmptyList,
+    EmptyRange {
+        start: f32,
+        end: f32,
+    },
+    DivisionByZero,
 
     IoError(io::Error),
 
     ParsingErr(ParseError),
+    RegexError(regex::Error),
+
+    InvalidCharCode(f32),
+    InvalidByte(f32),
+    InexactInteger(f32),
+    IndexOutOfBounds {
+        index: f32,
+        len: usize,
+    },
+    DecodeError(String),
+
+    InvalidBytecode(String),
+    BytecodeVersion {
+        expected: u16,
+        found: u16,
+    },
+
+    StoppedByDebugger,
+
+    AssertionFailed(String),
+    NotEqual {
+        expected: Value,
+        actual: Value,
+    },
 
     UserError(String),
 }
 
+/// How code is evaluated, both backends give the same results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Walks the parsed forms directly
+    TreeWalker,
+    /// Compiles forms to bytecode (see the `compile` module) and runs them on a stack based VM
+    Bytecode,
+}
+
+/// Called by the evaluator as it runs, to watch or step through evaluation (see the `debug` module).
+/// Every method does nothing by default. Hooks are only run by the tree walker,
+/// so code is always evaluated by it while a hook is set, whichever backend is chosen.
+///
+/// The hook is taken out of the interpreter while one of its methods runs,
+/// so anything a hook evaluates itself isn't seen by it.
+pub trait Hook {
+    /// Before a list is evaluated, an error stops it from being evaluated at all
+    fn before_eval(&mut self, _lisp: &mut Lisp, _form: &Value) -> Result<(), FuncError> {
+        Ok(())
+    }
+
+    fn after_eval(&mut self, _lisp: &mut Lisp, _form: &Value, _result: &Value) {}
+
+    /// When a function is called, once its arguments have been evaluated
+    fn on_call(&mut self, _lisp: &mut Lisp, _func: &Value, _args: &[Value]) -> Result<(), FuncError> {
+        Ok(())
+    }
+
+    fn on_return(&mut self, _lisp: &mut Lisp, _func: &Value, _result: &Value) {}
+
+    /// When evaluating a list fails, this is called for each of the lists the error passes through on its way out
+    fn on_error(&mut self, _lisp: &mut Lisp, _form: &Value, _err: &FuncError) {}
+
+    /// When `(break)` is called
+    fn on_break(&mut self, _lisp: &mut Lisp) -> Result<(), FuncError> {
+        Ok(())
+    }
+}
+
+// Once this many regexes have been compiled the cache starts over, so patterns built at runtime can't fill it up
+const REGEX_CACHE_SIZE: usize = 256;
+
 pub struct Lisp {
     pub scopes: Vec<Env>,
+    pub rng: Rng,
+
+    backend: Backend,
+    forms: FormCache,
+    regex_cache: HashMap<String, Regex>,
+    hook: Option<Box<Hook>>,
+    tests: Vec<Test>,
+    tracer: Tracer,
+    profiler: Option<Profiler>,
 }
 
 impl Lisp {
     pub fn new() -> Lisp {
-        Lisp {
-            scopes: vec![Env::std_lib()],
-        }
+        Lisp::with_env(Env::std_lib())
     }
 
     pub fn new_empty_env() -> Lisp {
+        Lisp::with_env(Env::new())
+    }
+
+    fn with_env(env: Env) -> Lisp {
         Lisp {
-            scopes: vec![Env::new()],
+            scopes: vec![env],
+            rng: Rng::from_time(),
+            backend: Backend::TreeWalker,
+            forms: FormCache::new(),
+            regex_cache: HashMap::new(),
+            hook: None,
+            tests: Vec::new(),
+            tracer: Tracer::new(),
+            profiler: None,
+        }
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// The chunk the VM runs for a form outside of a lambda, only compiled the first time the form is run
+    pub(crate) fn form_chunk(&mut self, form: &List) -> Rc<Chunk> {
+        if let Some(chunk) = self.forms.get(form) {
+            return chunk;
+        }
+
+        let chunk = Rc::new(compile::compile_body(self, &[Value::List(form.clone())]));
+        self.forms.insert(form, chunk.clone());
+
+        chunk
+    }
+
+    // Whether calls need to be seen by a hook, the tracer or the profiler
+    fn instrumented(&self) -> bool {
+        self.hook.is_some() || self.tracer.is_active() || self.profiler.is_some()
+    }
+
+    // None of them are run by the VM
+    fn uses_vm(&self) -> bool {
+        self.backend == Backend::Bytecode && !self.instrumented()
+    }
+
+    pub fn set_hook(&mut self, hook: Box<Hook>) {
+        self.hook = Some(hook);
+    }
+
+    pub fn take_hook(&mut self) -> Option<Box<Hook>> {
+        self.hook.take()
+    }
+
+    pub fn has_hook(&self) -> bool {
+        self.hook.is_some()
+    }
+
+    /// Runs `f` with the hook if there is one, the hook is put back afterwards unless `f` set a different one
+    pub fn call_hook<R, F>(&mut self, f: F) -> Option<R>
+        where F: FnOnce(&mut Hook, &mut Lisp) -> R
+    {
+        let mut hook = match self.hook.take() {
+            Some(hook) => hook,
+            None => return None,
+        };
+
+        let result = f(&mut *hook, self);
+        if self.hook.is_none() {
+            self.hook = Some(hook);
+        }
+
+        Some(result)
+    }
+
+    /// Registers a test, replacing any test with the same name (see the `testing` module)
+    pub fn add_test(&mut self, test: Test) {
+        match self.tests.iter().position(|existing| existing.name == test.name) {
+            Some(index) => self.tests[index] = test,
+            None => self.tests.push(test),
         }
     }
 
+    /// The tests that have been registered, in the order they were first defined
+    pub fn tests(&self) -> &[Test] {
+        &self.tests
+    }
+
+    /// Logs calls to the function with this name, and what they return (see the `profile` module)
+    pub fn trace(&mut self, name: Symbol) {
+        self.tracer.trace(name);
+    }
+
+    pub fn untrace(&mut self, name: Symbol) -> bool {
+        self.tracer.untrace(name)
+    }
+
+    pub fn untrace_all(&mut self) {
+        self.tracer.untrace_all();
+    }
+
+    pub fn traced(&self) -> Vec<Symbol> {
+        self.tracer.traced()
+    }
+
+    /// Where traces are written, stderr by default
+    pub fn set_trace_output(&mut self, output: Box<io::Write>) {
+        self.tracer.set_output(output);
+    }
+
+    /// Starts timing calls, throwing away whatever was recorded before
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Stops timing calls, giving what was recorded
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        self.profiler.take().map(|profiler| profiler.profile())
+    }
+
+    /// What's been recorded so far, if profiling
+    pub fn profile(&self) -> Option<Profile> {
+        self.profiler.as_ref().map(Profiler::profile)
+    }
+
     pub fn eval<T: FromLisp>(&mut self, code: &str) -> Result<T, FuncError> {
         let result = try!(self.eval_raw(code));
 
@@ -74,102 +296,376 @@ impl Lisp {
         self.eval_token_vec(tokens)
     }
 
-    pub fn eval_token_vec(&mut self, mut tokens: Vec<Value>) -> FuncResult {
-        let ret_token = match tokens.pop() {
-            Some(token) => token,
-            None => unreachable!(),
+    /// Resolves every symbol in a script without running it, see the `resolve` module.
+    /// The analysis' warnings list every reference to a name that nothing defines.
+    pub fn analyze(&self, code: &str) -> Result<Analysis, FuncError> {
+        match parse::parse_str(code) {
+            Ok(tokens) => Ok(resolve::analyze(self, &tokens)),
+            Err(err) => Err(FuncError::ParsingErr(err)),
+        }
+    }
+
+    /// Compiles a script to the binary format described in the `bytecode` module, which `eval_compiled` can run
+    /// without parsing it again.
+    /// Calls to builtins like `if` are resolved against this interpreter's environment while compiling.
+    pub fn compile(&self, code: &str) -> Result<Vec<u8>, FuncError> {
+        self.compile_script(code, false)
+    }
+
+    /// Like `compile`, but the source is stored alongside the bytecode,
+    /// so the file can still be run by a version of rlisp that can't read its bytecode
+    pub fn compile_with_source(&self, code: &str) -> Result<Vec<u8>, FuncError> {
+        self.compile_script(code, true)
+    }
+
+    fn compile_script(&self, code: &str, embed_source: bool) -> Result<Vec<u8>, FuncError> {
+        let tokens = match parse::parse_str(code) {
+            Ok(tok) => tok,
+            Err(err) => return Err(FuncError::ParsingErr(err)),
+        };
+
+        let chunk = compile::compile_body(self, &tokens);
+        bytecode::write(&chunk, if embed_source { Some(code) } else { None })
+    }
+
+    /// Runs code produced by `compile`, always on the bytecode VM regardless of the current backend.
+    /// Files written by a different format version are parsed from their embedded source if they have one,
+    /// otherwise they fail with `FuncError::BytecodeVersion`.
+    pub fn eval_compiled(&mut self, bytes: &[u8]) -> FuncResult {
+        match try!(bytecode::read(bytes)) {
+            Compiled::Chunk(chunk) => vm::run(self, Rc::new(chunk)),
+            Compiled::Source(source) => self.eval_raw(&source),
+        }
+    }
+
+    pub fn eval_compiled_reader<R: Read>(&mut self, mut code: R) -> FuncResult {
+        let mut bytes = Vec::new();
+        match code.read_to_end(&mut bytes) {
+            Ok(_) => (),
+            Err(err) => return Err(FuncError::IoError(err)),
+        }
+
+        self.eval_compiled(&bytes)
+    }
+
+    pub fn eval_token_vec(&mut self, tokens: Vec<Value>) -> FuncResult {
+        self.eval_body(&tokens)
+    }
+
+    /// Evaluates each token in order, returning the value of the last one (or nil if there are none)
+    pub fn eval_body(&mut self, tokens: &[Value]) -> FuncResult {
+        if self.uses_vm() {
+            return vm::eval_body(self, tokens);
+        }
+
+        let (ret_token, tokens) = match tokens.split_last() {
+            Some(split) => split,
+            None => return Ok(Value::Nil),
         };
 
         for token in tokens {
-            try!(self.eval_token(token));
+            try!(self.eval_token(token.clone()));
         }
 
-        self.eval_token(ret_token)
+        self.eval_token(ret_token.clone())
     }
 
     pub fn eval_token(&mut self, token: Value) -> FuncResult {
         match token {
-            Value::Symbol(sym) => {
-                for env in self.scopes.iter().rev() {
-                    if let Some(val) = env.map.get(&sym) {
-                        return Ok(val.clone());
-                    }
+            Value::Symbol(sym) => self.lookup(sym),
+            Value::List(_) if self.hook.is_some() => self.eval_hooked(token),
+            Value::List(_) if self.uses_vm() => vm::eval_form(self, &token),
+            Value::List(list) => self.eval_call(list),
+            Value::Quote(val) => Ok(*val),
+            _ => Ok(token),
+        }
+    }
+
+    fn eval_hooked(&mut self, form: Value) -> FuncResult {
+        if let Some(Err(err)) = self.call_hook(|hook, lisp| hook.before_eval(lisp, &form)) {
+            return Err(err);
+        }
+
+        let result = match form {
+            Value::List(ref list) => self.eval_call(list.clone()),
+            _ => unreachable!(),
+        };
+
+        match result {
+            Ok(ref value) => self.call_hook(|hook, lisp| hook.after_eval(lisp, &form, value)),
+            Err(ref err) => self.call_hook(|hook, lisp| hook.on_error(lisp, &form, err)),
+        };
+
+        result
+    }
+
+    // `head` is what the function was called as, usually its name
+    fn instrument_call(&mut self, head: &Value, func: &Value, args: &[Value]) -> Result<Instrumented, FuncError> {
+        if let Some(Err(err)) = self.call_hook(|hook, lisp| hook.on_call(lisp, func, args)) {
+            return Err(err);
+        }
+
+        let mut instrumented = Instrumented {
+            traced: false,
+            profiled: false,
+        };
+
+        if self.tracer.is_active() {
+            let traced = self.tracer.traced_name(head, func, |name| self.lookup(name).ok());
+            if let Some(name) = traced {
+                self.tracer.enter(name, args);
+                instrumented.traced = true;
+            }
+        }
+
+        if let Some(ref mut profiler) = self.profiler {
+            let scopes = &self.scopes;
+            let name = profiler.name(head, func, || find_name(scopes, func));
+
+            profiler.enter(name);
+            instrumented.profiled = true;
+        }
+
+        Ok(instrumented)
+    }
+
+    fn instrument_return(&mut self, func: &Value, instrumented: Instrumented, result: &FuncResult) {
+        if instrumented.profiled {
+            if let Some(ref mut profiler) = self.profiler {
+                profiler.exit();
+            }
+        }
+
+        if instrumented.traced {
+            self.tracer.exit(result);
+        }
+
+        if let &Ok(ref value) = result {
+            self.call_hook(|hook, lisp| hook.on_return(lisp, func, value));
+        }
+    }
+
+    fn eval_call(&mut self, list: List) -> FuncResult {
+        let (val, tokens) = match (list.head(), list.tail()) {
+            (Some(head), Some(tail)) => (head.clone(), tail),
+            _ => return Err(FuncError::AttemptToEvalEmptyList),
+        };
+
+        let func = try!(self.eval_token(val.clone()));
+
+        self.sub_scope(); // each list has its own scope
+        let result = self.eval_args_and_call(&val, &func, tokens);
+        self.exit_scope();
+
+        result
+    }
+
+    fn eval_args_and_call(&mut self, head: &Value, func: &Value, tokens: List) -> FuncResult {
+        match func {
+            // A lambda's arguments aren't evaluated if there's the wrong number of them
+            &Value::Lambda(ref lambda) if !lambda.params.arity().accepts(tokens.len()) => {
+                return Err(FuncError::InvalidArguments {
+                    expected: lambda.params.arity(),
+                    got: tokens.len(),
+                });
+            },
+            &Value::HardFunc(_) | &Value::Lambda(_) => (),
+            _ => return Err(FuncError::AttemptToCallNonFunction(head.clone())),
+        }
+
+        // All arguments are evaluated before any are bound, otherwise binding
+        // a parameter could change the value of a later argument
+        let mut args = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            args.push(try!(self.eval_token(token)));
+        }
+
+        self.call_in_scope(head, func, args)
+    }
+
+    /// Calls a function with arguments that have already been evaluated, so they aren't evaluated again
+    pub fn call(&mut self, func: &Value, args: Vec<Value>) -> FuncResult {
+        self.sub_scope();
+        let result = self.call_in_scope(func, func, args);
+        self.exit_scope();
+
+        result
+    }
+
+    // Calls a function in the scope that's just been made for it, which the caller leaves whether or not it fails.
+    // `head` is how the function was written in the call.
+    fn call_in_scope(&mut self, head: &Value, func: &Value, args: Vec<Value>) -> FuncResult {
+        let arity = match func {
+            &Value::HardFunc(ref hard_func) => hard_func.args.clone(),
+            &Value::Lambda(ref lambda) => lambda.params.arity(),
+            _ => return Err(FuncError::AttemptToCallNonFunction(head.clone())),
+        };
+
+        if !arity.accepts(args.len()) {
+            return Err(FuncError::InvalidArguments {
+                expected: arity,
+                got: args.len(),
+            });
+        }
+
+        match func {
+            &Value::HardFunc(ref hard_func) => {
+                if self.instrumented() {
+                    let instrumented = try!(self.instrument_call(head, func, &args));
+
+                    let result = (hard_func.func)(args, self);
+                    self.instrument_return(func, instrumented, &result);
+
+                    return result;
                 }
 
-                Err(FuncError::UndeclaredSymbol(sym))
+                (hard_func.func)(args, self)
             },
-            Value::List(mut tokens) => {
-                if tokens.len() == 0 {
-                  return Err(FuncError::AttemptToEvalEmptyList);
+            &Value::Lambda(ref lambda) => {
+                // The hook is called once the parameters are bound, so it can look at them
+                if self.instrumented() {
+                    try!(self.bind_params(lambda, args.clone()));
+                    let instrumented = try!(self.instrument_call(head, func, &args));
+
+                    let result = self.eval_body(&lambda.body);
+                    self.instrument_return(func, instrumented, &result);
+
+                    return result;
                 }
 
-                let val = tokens.remove(0);
-                let func = try!(self.eval_token(val.clone()));
-                self.sub_scope(); // each list has its own scope
-
-                match func {
-                    Value::HardFunc(hard_func) => {
-                        let mut args = Vec::new();
-
-                        for token in tokens {
-                            args.push(try!(self.eval_token(token)));
-                        }
-
-                        match hard_func.args {
-                            Args::Variant => (),
-                            Args::Fixed(count) => {
-                                if args.len() != count {
-                                    invalid_args!(self, Args::Fixed(count), args);
-                                }
-                            },
-                            Args::Multiple(possible_counts) => {
-                                let mut arg_match = false;
-
-                                // I wish I could label match statements and break out of them...
-                                for count in &possible_counts {
-                                    if args.len() == *count {
-                                        arg_match = true;
-                                        break;
-                                    }
-                                }
-
-                                if !arg_match {
-                                    invalid_args!(self, Args::Multiple(possible_counts), args);
-                                }
-                            },
-                            Args::Atleast(count) => {
-                                if args.len() < count {
-                                    invalid_args!(self, Args::Atleast(count), args);
-                                }
-                            }
-                        }
-
-                        let result = (hard_func.func)(args, self);
-                        self.exit_scope();
-
-                        result
-                    },
-                    Value::Lambda { args, body } => {
-                        if tokens.len() != args.len() {
-                            invalid_args!(self, Args::Fixed(args.len()), tokens);
-                        }
-
-                        for sym in args.iter() {
-                            let value = try!(self.eval_token(tokens.remove(0)));
-                            self.cur_scope().set(sym, value);
-                        }
-
-                        let result = self.eval_token_vec(body);
-                        self.exit_scope();
-
-                        result
-                    },
-                    _ => return Err(FuncError::AttemptToCallNonFunction(val)),
+                let values = try!(self.bind_params(lambda, args));
+                match self.uses_vm() {
+                    true => vm::call_lambda(self, lambda, values),
+                    false => self.eval_body(&lambda.body),
                 }
             },
-            Value::Quote(val) => Ok(*val),
-            _ => Ok(token),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Binds a lambda's parameters to the arguments it's been given in the current scope,
+    /// evaluating the defaults of any that weren't given once the parameters before them are bound.
+    /// Gives the value of each parameter, in the order of `lambda.args`.
+    pub fn bind_params(&mut self, lambda: &Lambda, args: Vec<Value>) -> Result<Vec<Value>, FuncError> {
+        let params = &lambda.params;
+
+        if params.is_simple() {
+            for (sym, value) in lambda.args.iter().zip(args.iter()) {
+                self.cur_scope().set(*sym, value.clone());
+            }
+
+            return Ok(args);
+        }
+
+        let mut bound = Vec::with_capacity(lambda.args.len());
+        let mut args = args.into_iter();
+
+        for pattern in &params.required {
+            let value = args.next().unwrap_or(Value::Nil);
+
+            for (sym, value) in try!(pattern.destructure(value)) {
+                self.cur_scope().set(sym, value.clone());
+                bound.push(value);
+            }
+        }
+
+        for &(sym, ref default) in &params.optional {
+            let value = match args.next() {
+                Some(value) => value,
+                None => try!(self.eval_token(default.clone())),
+            };
+
+            self.cur_scope().set(sym, value.clone());
+            bound.push(value);
+        }
+
+        let rest: Vec<Value> = args.collect();
+
+        if let Some(sym) = params.rest {
+            let value = Value::List(rest.clone().into());
+            self.cur_scope().set(sym, value.clone());
+            bound.push(value);
         }
+
+        if params.keys.is_empty() {
+            return Ok(bound);
+        }
+
+        // With &rest too, the keys are read from the same arguments
+        let mut given = vec![None; params.keys.len()];
+        let mut rest = rest.into_iter();
+
+        while let Some(key) = rest.next() {
+            let (index, name) = match key {
+                Value::Symbol(name) => match params.keys.iter().position(|&(sym, _)| sym == name) {
+                    Some(index) => (index, name),
+                    None => return Err(FuncError::UnknownKeyword(key)),
+                },
+                _ => return Err(FuncError::UnknownKeyword(key)),
+            };
+
+            match rest.next() {
+                Some(value) => given[index] = Some(value),
+                None => return Err(FuncError::MissingKeywordValue(name)),
+            }
+        }
+
+        for (&(sym, ref default), value) in params.keys.iter().zip(given) {
+            let value = match value {
+                Some(value) => value,
+                None => try!(self.eval_token(default.clone())),
+            };
+
+            self.cur_scope().set(sym, value.clone());
+            bound.push(value);
+        }
+
+        Ok(bound)
+    }
+
+    pub fn lookup(&self, sym: Symbol) -> FuncResult {
+        for env in self.scopes.iter().rev() {
+            if let Some(val) = env.get(sym) {
+                return Ok(val.clone());
+            }
+        }
+
+        Err(FuncError::UndeclaredSymbol(sym))
+    }
+
+    /// Compiles a regex, reusing the previously compiled one if this pattern has been seen before
+    pub fn regex(&mut self, pattern: &str) -> Result<Regex, FuncError> {
+        if let Some(re) = self.regex_cache.get(pattern) {
+            return Ok(re.clone());
+        }
+
+        let re = try!(Regex::new(pattern));
+        if self.regex_cache.len() >= REGEX_CACHE_SIZE {
+            self.regex_cache.clear();
+        }
+
+        self.regex_cache.insert(pattern.to_string(), re.clone());
+
+        Ok(re)
+    }
+
+    /// Reseeds the RNG used by `random` and friends, two interpreters with the same seed
+    /// will produce the same sequence of random values
+    pub fn seed_random(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn rng_state(&self) -> u64 {
+        self.rng.state
+    }
+
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.rng.state = state;
+    }
+
+    /// Symbols are interned into a table shared by every interpreter on the current thread
+    pub fn intern(&self, name: &str) -> Symbol {
+        Symbol::intern(name)
     }
 
     pub fn set_global<T: ToLisp>(&mut self, name: &str, value: T) {
@@ -195,19 +691,45 @@ impl Lisp {
     }
 }
 
+// What was started for a call, and needs to be finished when it returns
+#[derive(Clone, Copy)]
+struct Instrumented {
+    traced: bool,
+    profiled: bool,
+}
+
+// A name the function is bound to, for when it's called through a value
+fn find_name(scopes: &[Env], func: &Value) -> Option<Symbol> {
+    let id = profile::identity(func);
+
+    scopes.iter()
+        .rev()
+        .flat_map(|scope| scope.map.iter())
+        .find(|&(_, value)| profile::identity(value) == id)
+        .map(|(&name, _)| name)
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        // {:#?} breaks long lists over multiple lines
+        if fmt.alternate() {
+            return write!(fmt, "{}", pretty::pretty(self, pretty::DEFAULT_WIDTH));
+        }
+
         match self {
             &Value::List(ref values) => parse::write_list(fmt, values, "(", " ", ")"),
             &Value::String(ref string) => write!(fmt, "{:?}", string),
-            &Value::Symbol(ref string) => write!(fmt, "{}", string),
+            &Value::Bytes(ref bytes) => parse::write_list(fmt, bytes, "#u8(", " ", ")"),
+            &Value::Symbol(sym) => write!(fmt, "{}", sym),
             &Value::Number(num) => write!(fmt, "{}", num),
             &Value::HardFunc(ref func) => write!(fmt, "HardFunc({:?})", func.args),
-            &Value::Lambda { ref args, ref body }=> write!(fmt, "λ {:?} => {:?}", args, body),
+            &Value::Lambda(ref lambda) => write!(fmt, "{:?}", lambda),
             &Value::Nil => write!(fmt, "nil"),
             &Value::Bool(val) => write!(fmt, "{}", val),
+            &Value::Char(ch) => parse::write_char(fmt, ch),
             &Value::Quote(ref tok) => write!(fmt, "'{:?}", tok),
             &Value::Type(ref typ) => write!(fmt, "{:?}", typ),
+            &Value::Regex(ref re) => write!(fmt, "Regex({:?})", re.as_str()),
             &Value::Foreign(ref val) => write!(fmt, "{:?}", val),
         }
     }
@@ -0,0 +1,166 @@
+//! Formats source code with the layout rules of the `pretty` module, keeping its comments.
+//! Forms are laid out from scratch, but comments stay attached to the form they're next to,
+//! and a single blank line between top level forms is kept.
+
+use parse::{self, Cst, CstElement, CstNode, CstKind, TokenKind, Diagnostic};
+use pretty::{self, Doc, Entry, text, concat};
+use value::Value;
+
+/// Formats a whole source file, lines are kept within `width` columns where possible.
+/// Code with syntax errors isn't formatted, its diagnostics are returned instead.
+pub fn format_source(code: &str, width: usize) -> Result<String, Vec<Diagnostic>> {
+    let (cst, diagnostics) = parse::parse_cst(code);
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    Ok(format_cst(&cst, width))
+}
+
+pub fn format_cst(cst: &Cst, width: usize) -> String {
+    let mut out = String::new();
+    let mut newlines = 0;
+
+    for element in &cst.elements {
+        match *element {
+            CstElement::Token(ref token) if token.kind == TokenKind::Whitespace => {
+                newlines += token.text.matches('\n').count();
+            },
+            CstElement::Token(ref token) if token.kind == TokenKind::Comment => {
+                if !out.is_empty() {
+                    out.push_str(separator(newlines, " "));
+                }
+
+                out.push_str(token.text.trim_right());
+                newlines = 0;
+            },
+            _ => {
+                if !out.is_empty() {
+                    out.push_str(separator(newlines, "\n"));
+                }
+
+                out.push_str(&pretty::render(&element_doc(element), width));
+                newlines = 0;
+            },
+        }
+    }
+
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    out
+}
+
+// Up to one blank line is kept, `same_line` is used when there wasn't a line break at all
+fn separator(newlines: usize, same_line: &'static str) -> &'static str {
+    match newlines {
+        0 => same_line,
+        1 => "\n",
+        _ => "\n\n",
+    }
+}
+
+fn element_doc(element: &CstElement) -> Doc {
+    match *element {
+        CstElement::Node(ref node) => node_doc(node),
+        CstElement::Token(ref token) => text(token.text.clone()),
+    }
+}
+
+fn node_doc(node: &CstNode) -> Doc {
+    match node.kind {
+        CstKind::List => pretty::list("(", ")", entries(node)),
+        CstKind::QuotedList => pretty::list("{", "}", entries(node)),
+        CstKind::Bytes => prefixed("#u8", node),
+        CstKind::Quote => prefixed("'", node),
+        // Only reachable if the caller ignored the diagnostics, so the source is left as it was
+        CstKind::Error(_) => text(node.to_string()),
+    }
+}
+
+// A `'` or `#u8` followed by its form, with any comments in between kept on their own lines
+fn prefixed(prefix: &str, node: &CstNode) -> Doc {
+    let mut docs = vec![text(prefix)];
+
+    for child in node.children.iter().skip(1) {
+        match *child {
+            CstElement::Token(ref token) if token.kind == TokenKind::Whitespace => (),
+            CstElement::Token(ref token) if token.kind == TokenKind::Comment => {
+                if docs.len() == 1 {
+                    docs.push(text(" "));
+                }
+
+                docs.push(text(token.text.trim_right()));
+                docs.push(Doc::HardLine);
+            },
+            _ => docs.push(element_doc(child)),
+        }
+    }
+
+    concat(docs)
+}
+
+// Attaches each comment in a list to the item next to it
+fn entries(node: &CstNode) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut leading = Vec::new();
+    let mut on_new_line = false;
+
+    for child in &node.children {
+        let token = match *child {
+            CstElement::Token(ref token) => token,
+            CstElement::Node(_) => {
+                entries.push(entry(child, leading));
+                leading = Vec::new();
+                on_new_line = false;
+                continue;
+            },
+        };
+
+        match token.kind {
+            TokenKind::Whitespace => on_new_line |= token.text.contains('\n'),
+            TokenKind::Comment => {
+                let comment = token.text.trim_right().to_string();
+
+                match entries.last_mut() {
+                    Some(ref mut last) if !on_new_line && leading.is_empty() && last.trailing.is_none() => {
+                        last.trailing = Some(comment);
+                    },
+                    _ => leading.push(comment),
+                }
+
+                on_new_line = false;
+            },
+            TokenKind::Atom | TokenKind::Skipped => {
+                entries.push(entry(child, leading));
+                leading = Vec::new();
+                on_new_line = false;
+            },
+            _ => (), // Delimiters
+        }
+    }
+
+    if !leading.is_empty() {
+        entries.push(Entry {
+            leading: leading,
+            doc: None,
+            trailing: None,
+            symbol: None,
+        });
+    }
+
+    entries
+}
+
+fn entry(element: &CstElement, leading: Vec<String>) -> Entry {
+    let mut entry = Entry::new(element_doc(element));
+    entry.leading = leading;
+
+    if let Ok(Value::Symbol(sym)) = element.to_value() {
+        entry.symbol = Some(sym.as_str().to_string());
+    }
+
+    entry
+}
@@ -0,0 +1,326 @@
+//! Just enough JSON for the tools built on top of the interpreter, the linter's output and the language server's messages.
+
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys are kept in the order they were written in
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            &Json::Object(ref fields) => fields.iter().find(|&&(ref name, _)| name == key).map(|&(_, ref value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follows a path of keys through nested objects
+    pub fn at(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().fold(Some(self), |json, key| json.and_then(|json| json.get(key)))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            &Json::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            &Json::Number(num) if num >= 0.0 && num.fract() == 0.0 => Some(num as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            &Json::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(value: &'a str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(value: Vec<Json>) -> Json {
+        Json::Array(value)
+    }
+}
+
+/// Writes compact JSON, all on one line
+impl fmt::Display for Json {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Json::Null => write!(fmt, "null"),
+            &Json::Bool(value) => write!(fmt, "{}", value),
+            &Json::Number(num) if num.is_finite() => write!(fmt, "{}", num),
+            &Json::Number(_) => write!(fmt, "null"),
+            &Json::String(ref string) => write_string(fmt, string),
+            &Json::Array(ref items) => {
+                try!(fmt.write_char('['));
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        try!(fmt.write_char(','));
+                    }
+                    try!(write!(fmt, "{}", item));
+                }
+                fmt.write_char(']')
+            },
+            &Json::Object(ref fields) => {
+                try!(fmt.write_char('{'));
+                for (index, &(ref key, ref value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        try!(fmt.write_char(','));
+                    }
+                    try!(write_string(fmt, key));
+                    try!(write!(fmt, ":{}", value));
+                }
+                fmt.write_char('}')
+            },
+        }
+    }
+}
+
+fn write_string<W: Write>(out: &mut W, string: &str) -> fmt::Result {
+    try!(out.write_char('"'));
+
+    for ch in string.chars() {
+        try!(match ch {
+            '"' => out.write_str("\\\""),
+            '\\' => out.write_str("\\\\"),
+            '\n' => out.write_str("\\n"),
+            '\r' => out.write_str("\\r"),
+            '\t' => out.write_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32),
+            ch => out.write_char(ch),
+        });
+    }
+
+    out.write_char('"')
+}
+
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    };
+
+    let value = try!(parser.value());
+
+    parser.skip_whitespace();
+    match parser.pos == parser.chars.len() {
+        true => Ok(value),
+        false => Err(format!("unexpected text after the value at {}", parser.pos)),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => Err(format!("expected '{}' at {}", expected, self.pos)),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            try!(self.expect(expected));
+        }
+
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(ch) if ch == '-' || ch.is_digit(10) => self.number(),
+            _ => Err(format!("expected a value at {}", self.pos)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            match ch.is_digit(10) || "-+.eE".contains(ch) {
+                true => self.pos += 1,
+                false => break,
+            }
+        }
+
+        let text: String = self.chars[start..self.pos].iter().cloned().collect();
+        text.parse().map(Json::Number).map_err(|_| format!("invalid number {}", text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        try!(self.expect('"'));
+        let mut string = String::new();
+
+        loop {
+            let ch = match self.peek() {
+                Some(ch) => ch,
+                None => return Err("unterminated string".to_string()),
+            };
+            self.pos += 1;
+
+            match ch {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = try!(self.peek().ok_or("unterminated string".to_string()));
+                    self.pos += 1;
+
+                    string.push(match escape {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => try!(self.unicode_escape()),
+                        other => other,
+                    });
+                },
+                ch => string.push(ch),
+            }
+        }
+    }
+
+    // The code after a \u, which may be the first half of a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = try!(self.hex4());
+
+        if high >= 0xD800 && high < 0xDC00 && self.chars[self.pos..].starts_with(&['\\', 'u']) {
+            self.pos += 2;
+            let low = try!(self.hex4());
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+
+            return Ok(::std::char::from_u32(code).unwrap_or('\u{FFFD}'));
+        }
+
+        Ok(::std::char::from_u32(high).unwrap_or('\u{FFFD}'))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        if self.pos + 4 > self.chars.len() {
+            return Err("unterminated unicode escape".to_string());
+        }
+
+        let hex: String = self.chars[self.pos..self.pos + 4].iter().cloned().collect();
+        self.pos += 4;
+
+        u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid unicode escape {}", hex))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        try!(self.expect('['));
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(try!(self.value()));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                },
+                _ => return Err(format!("expected ',' or ']' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        try!(self.expect('{'));
+        let mut fields = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = try!(self.string());
+
+            self.skip_whitespace();
+            try!(self.expect(':'));
+            fields.push((key, try!(self.value())));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                },
+                _ => return Err(format!("expected ',' or '}}' at {}", self.pos)),
+            }
+        }
+    }
+}
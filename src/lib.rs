@@ -14,15 +14,37 @@ macro_rules! invalid_args {
 }
 
 pub mod parse;
+pub mod reader;
+pub mod pretty;
+pub mod format;
 pub mod eval;
 pub mod value;
 pub mod valtype;
 pub mod env;
+pub mod list;
+pub mod rng;
+pub mod symbol;
+pub mod compile;
+pub mod resolve;
+pub mod bytecode;
+pub mod lint;
+pub mod json;
+pub mod lsp;
+pub mod debug;
+pub mod profile;
+pub mod testing;
+pub mod docs;
+pub mod pattern;
 
 mod error_msg;
 mod default_env;
+mod encoding;
+mod vm;
 
-pub use eval::{Lisp, FuncResult, FuncError};
+pub use eval::{Lisp, Backend, Hook, FuncResult, FuncError};
 pub use env::Env;
 pub use value::{Value, ToLisp, FromLisp, ForeignType};
+pub use list::List;
+pub use symbol::Symbol;
 pub use parse::{ParseResult, ParseError};
+pub use reader::Reader;
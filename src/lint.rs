@@ -0,0 +1,525 @@
+//! Static checks for common mistakes in rlisp code.
+//!
+//! Since `def`, `if`, `\` and `let` are regular functions, forgetting to quote one of their arguments still runs,
+//! just not the way it was meant to. The linter looks for those along with calls to builtins (or functions defined in the
+//! code) with the wrong number of arguments, code after a call to `exit`, `let` bindings that are never used
+//! and names that hide one of the interpreter's builtins.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use parse::{self, Node, NodeKind, Span, FilePos};
+use resolve::{core_form, CoreForm};
+use value::{func, Value, Args, Params};
+use pattern::Pattern;
+use symbol::Symbol;
+use eval::Lisp;
+use json::Json;
+use default_env;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    SyntaxError,
+    /// A call with a number of arguments the function doesn't accept
+    Arity,
+    /// `(def x ...)` or `(def (f x) ...)`, which evaluate the name or signature
+    UnquotedDef,
+    /// A body or branch that's evaluated straight away, rather than when it's meant to be
+    UnquotedCode,
+    /// Code after a call to `exit`
+    Unreachable,
+    UnusedBinding,
+    ShadowedBuiltin,
+}
+
+impl LintKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LintKind::SyntaxError => "syntax-error",
+            LintKind::Arity => "arity",
+            LintKind::UnquotedDef => "unquoted-def",
+            LintKind::UnquotedCode => "unquoted-code",
+            LintKind::Unreachable => "unreachable",
+            LintKind::UnusedBinding => "unused-binding",
+            LintKind::ShadowedBuiltin => "shadowed-builtin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}: {}", self.kind.name(), self.message)
+    }
+}
+
+impl Lint {
+    /// The lint as a JSON object, `code` is the source it was found in, which its position is worked out from
+    pub fn to_json(&self, file: &str, code: &str) -> Json {
+        let start = FilePos::from_offset(code, self.span.start);
+        let end = FilePos::from_offset(code, self.span.end);
+
+        Json::object(vec![
+            ("file", file.into()),
+            ("line", start.line.into()),
+            ("column", start.col.into()),
+            ("end_line", end.line.into()),
+            ("end_column", end.col.into()),
+            ("kind", self.kind.name().into()),
+            ("message", self.message.clone().into()),
+        ])
+    }
+}
+
+/// Lints source code, syntax errors are reported as lints as well.
+/// Builtins are whatever `lisp` has defined, so functions added by the host are checked too.
+pub fn lint_source(lisp: &Lisp, code: &str) -> Vec<Lint> {
+    let (nodes, diagnostics) = parse::parse_recovering(code);
+
+    let mut lints: Vec<Lint> = diagnostics.into_iter()
+        .map(|diagnostic| Lint {
+            kind: LintKind::SyntaxError,
+            message: format!("{:?}", diagnostic.error),
+            span: diagnostic.span,
+        })
+        .collect();
+
+    lints.extend(lint(lisp, &nodes));
+    lints.sort_by_key(|lint| lint.span.start);
+
+    lints
+}
+
+/// Lints parsed top level forms, ordered by where each lint starts
+pub fn lint(lisp: &Lisp, forms: &[Node]) -> Vec<Lint> {
+    let mut linter = Linter {
+        lisp: lisp,
+        arities: HashMap::new(),
+        bound: Vec::new(),
+        lints: Vec::new(),
+    };
+
+    for form in forms {
+        linter.collect_names(form);
+    }
+
+    linter.check_sequence(forms, Context::TopLevel);
+
+    let mut lints = linter.lints;
+    lints.sort_by_key(|lint| lint.span.start);
+
+    lints
+}
+
+#[derive(Clone, Copy)]
+enum Context {
+    TopLevel,
+    /// The arguments of `seq`, which can be braced or not
+    Seq,
+    /// The body of a function or let, which should be braced, along with what to say when it isn't
+    Body(&'static str),
+}
+
+struct Linter<'a> {
+    lisp: &'a Lisp,
+
+    // Functions defined in the code, None if they're given more than one arity or a value that isn't a lambda
+    arities: HashMap<Symbol, Option<Args>>,
+    // The lambda parameters and let bindings of each scope the linter is in, which could be anything when called
+    bound: Vec<Vec<Symbol>>,
+
+    lints: Vec<Lint>,
+}
+
+impl<'a> Linter<'a> {
+    fn lint(&mut self, kind: LintKind, span: Span, message: String) {
+        self.lints.push(Lint {
+            kind: kind,
+            message: message,
+            span: span,
+        });
+    }
+
+    fn core_form(&self, head: &Node) -> Option<CoreForm> {
+        match head.kind {
+            NodeKind::Atom(ref value) => core_form(self.lisp, value),
+            _ => None,
+        }
+    }
+
+    fn define(&mut self, name: Symbol, args: Option<Args>) {
+        let known = match self.arities.get(&name) {
+            Some(previous) => *previous == args,
+            None => true,
+        };
+
+        self.arities.insert(name, if known { args } else { None });
+    }
+
+    fn collect_names(&mut self, node: &Node) {
+        let items = match node.kind {
+            NodeKind::List(ref items) | NodeKind::QuotedList(ref items) => items,
+            NodeKind::Quote(ref inner) => return self.collect_names(inner),
+            _ => return,
+        };
+
+        match items.first().and_then(|head| self.core_form(head)) {
+            Some(CoreForm::Def) if items.len() > 1 => {
+                match items[1].kind {
+                    NodeKind::QuotedList(ref sig) | NodeKind::List(ref sig) => {
+                        if let Some(name) = sig.first().and_then(symbol) {
+                            self.define(name, params(&sig[1..]).map(|params| params.arity()));
+                        } else if let Some(pattern) = sig.first() {
+                            for name in pattern_names(pattern) {
+                                self.define(name, None);
+                            }
+                        }
+                    },
+                    NodeKind::Quote(ref name) => if let Some(name) = symbol(name) {
+                        let args = items.get(2).and_then(|value| self.lambda_arity(value));
+                        self.define(name, args);
+                    },
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+
+        for item in items {
+            self.collect_names(item);
+        }
+    }
+
+    // The arity of a (\ {params} ...) form
+    fn lambda_arity(&self, node: &Node) -> Option<Args> {
+        let items = match node.kind {
+            NodeKind::List(ref items) if items.len() > 1 => items,
+            _ => return None,
+        };
+
+        match (self.core_form(&items[0]), &items[1].kind) {
+            (Some(CoreForm::Lambda), &NodeKind::QuotedList(ref nodes)) => params(nodes).map(|params| params.arity()),
+            _ => None,
+        }
+    }
+
+    fn builtin(&self, sym: Symbol) -> bool {
+        match self.lisp.scopes.first().and_then(|globals| globals.get(sym)) {
+            Some(&Value::HardFunc(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn check_shadowing(&mut self, node: &Node, what: &str) {
+        if let Some(name) = symbol(node) {
+            if self.builtin(name) {
+                self.lint(LintKind::ShadowedBuiltin, node.span, format!("{} {} hides the builtin of the same name", what, name));
+            }
+        }
+    }
+
+    fn is_bound(&self, name: Symbol) -> bool {
+        self.bound.iter().any(|scope| scope.contains(&name))
+    }
+
+    fn check_arity(&mut self, name: Symbol, count: usize, span: Span) {
+        if self.is_bound(name) {
+            return;
+        }
+
+        let args = match self.arities.get(&name) {
+            Some(&Some(ref args)) => args.clone(),
+            Some(&None) => return,
+            None => match self.lisp.lookup(name) {
+                Ok(Value::HardFunc(func)) => func.args,
+                Ok(Value::Lambda(lambda)) => lambda.params.arity(),
+                _ => return,
+            },
+        };
+
+        if !args.accepts(count) {
+            self.lint(LintKind::Arity, span, format!("{} takes {}, but is given {}", name, args, count));
+        }
+    }
+
+    fn is_exit(&self, items: &[Node]) -> bool {
+        let name = match items.first().and_then(symbol) {
+            Some(name) if !self.is_bound(name) && !self.arities.contains_key(&name) => name,
+            _ => return false,
+        };
+
+        match self.lisp.lookup(name) {
+            Ok(Value::HardFunc(found)) => found == func(default_env::exit, Args::Multiple(vec![0, 1])),
+            _ => false,
+        }
+    }
+
+    // A form that's evaluated where it is
+    fn check(&mut self, node: &Node) {
+        if let NodeKind::List(ref items) = node.kind {
+            self.check_call(items, node.span);
+        }
+    }
+
+    // A braced form that's evaluated later on, such as the branch of an if
+    fn check_delayed(&mut self, node: &Node) {
+        match node.kind {
+            NodeKind::QuotedList(ref items) => self.check_call(items, node.span),
+            _ => self.check(node),
+        }
+    }
+
+    fn check_sequence(&mut self, forms: &[Node], context: Context) {
+        let mut exit_at = None;
+
+        for (index, form) in forms.iter().enumerate() {
+            let items = match (&form.kind, context) {
+                (&NodeKind::List(ref items), Context::Body(message)) => {
+                    self.lint(LintKind::UnquotedCode, form.span, message.to_string());
+                    items
+                },
+                (&NodeKind::List(ref items), _) => items,
+                (&NodeKind::QuotedList(ref items), Context::Seq) |
+                (&NodeKind::QuotedList(ref items), Context::Body(_)) => items,
+                _ => continue,
+            };
+
+            self.check_call(items, form.span);
+
+            if exit_at.is_none() && self.is_exit(items) {
+                exit_at = Some(index);
+            }
+        }
+
+        match exit_at {
+            Some(index) if index + 1 < forms.len() => {
+                let span = forms[index + 1].span.to(forms[forms.len() - 1].span);
+                self.lint(LintKind::Unreachable, span, "this is never evaluated, since exit is called before it".to_string());
+            },
+            _ => (),
+        }
+    }
+
+    fn check_call(&mut self, items: &[Node], span: Span) {
+        let head = match items.first() {
+            Some(head) => head,
+            None => return,
+        };
+
+        match symbol(head) {
+            Some(name) => self.check_arity(name, items.len() - 1, span),
+            None => self.check(head),
+        }
+
+        let args = &items[1..];
+        match self.core_form(head) {
+            Some(CoreForm::Def) => self.check_def(args),
+            Some(CoreForm::Lambda) => self.check_lambda(args),
+            Some(CoreForm::If) => self.check_if(args),
+            Some(CoreForm::Let) => self.check_let(args),
+            Some(CoreForm::Seq) => self.check_sequence(args, Context::Seq),
+            None => for arg in args {
+                self.check(arg);
+            },
+        }
+    }
+
+    fn check_def(&mut self, args: &[Node]) {
+        let name = match args.first() {
+            Some(name) => name,
+            None => return,
+        };
+
+        let body = Context::Body("this is evaluated once when def is called, rather than each time the function is, brace it to make it part of the function");
+
+        match name.kind {
+            NodeKind::Atom(Value::Symbol(sym)) => {
+                self.lint(LintKind::UnquotedDef, name.span, format!("{} is evaluated before def is given it, quote it as '{}", sym, sym));
+            },
+            NodeKind::Quote(ref inner) => self.check_shadowing(inner, "defining"),
+            // Destructuring, e.g. (def {(a b)} value)
+            NodeKind::QuotedList(ref sig) if sig.first().map_or(false, is_list) => (),
+            NodeKind::QuotedList(ref sig) => {
+                self.check_signature(sig);
+                return self.check_function_body(&sig[1..], &args[1..], body);
+            },
+            NodeKind::List(ref sig) => {
+                self.lint(LintKind::UnquotedDef, name.span, "the signature is evaluated before def is given it, write it in braces".to_string());
+                self.check_signature(sig);
+                return self.check_function_body(&sig[1..], &args[1..], body);
+            },
+            _ => (),
+        }
+
+        for arg in &args[1..] {
+            self.check(arg);
+        }
+    }
+
+    fn check_signature(&mut self, sig: &[Node]) {
+        for (index, name) in sig.iter().enumerate() {
+            self.check_shadowing(param_name(name), if index == 0 { "defining" } else { "the parameter" });
+        }
+    }
+
+    // The parameters are only bound inside of the body
+    fn check_function_body(&mut self, params_nodes: &[Node], body: &[Node], context: Context) {
+        self.bound.push(params(params_nodes).map_or(Vec::new(), |params| params.names()));
+        self.check_sequence(body, context);
+        self.bound.pop();
+    }
+
+    fn check_lambda(&mut self, args: &[Node]) {
+        let params = match args.first() {
+            Some(params) => params,
+            None => return,
+        };
+
+        let body = Context::Body("this is evaluated once when the lambda is made, rather than each time it's called, brace it to make it part of the lambda");
+
+        match params.kind {
+            NodeKind::QuotedList(ref params) => {
+                for param in params {
+                    self.check_shadowing(param_name(param), "the parameter");
+                }
+
+                return self.check_function_body(params, &args[1..], body);
+            },
+            NodeKind::List(_) => {
+                self.lint(LintKind::UnquotedCode, params.span, "the parameters are evaluated before \\ is given them, write them in braces".to_string());
+            },
+            _ => (),
+        }
+
+        self.check_sequence(&args[1..], body);
+    }
+
+    fn check_if(&mut self, args: &[Node]) {
+        if args.len() != 3 {
+            for arg in args {
+                self.check(arg);
+            }
+
+            return;
+        }
+
+        self.check(&args[0]);
+
+        for branch in &args[1..] {
+            if let NodeKind::List(_) = branch.kind {
+                self.lint(LintKind::UnquotedCode, branch.span, "both branches are evaluated before if picks one, brace this one to only evaluate it when it's picked".to_string());
+            }
+
+            self.check_delayed(branch);
+        }
+    }
+
+    fn check_let(&mut self, args: &[Node]) {
+        let bindings = match args.first() {
+            Some(&Node { kind: NodeKind::QuotedList(ref bindings), .. }) => bindings,
+            Some(other) => {
+                if let NodeKind::List(_) = other.kind {
+                    self.lint(LintKind::UnquotedCode, other.span, "the bindings are evaluated before let is given them, write them in braces".to_string());
+                }
+
+                for arg in args {
+                    self.check(arg);
+                }
+
+                return;
+            },
+            None => return,
+        };
+
+        let body = &args[1..];
+
+        // Each binding is in scope for the values after it, as well as the body
+        self.bound.push(Vec::new());
+
+        for (index, node) in bindings.iter().enumerate() {
+            let (names, value) = match binding(node) {
+                Some(binding) => binding,
+                None => continue,
+            };
+
+            if let NodeKind::List(ref pair) = node.kind {
+                self.check_shadowing(&pair[0], "the binding");
+            }
+
+            self.check(value);
+
+            for name in names {
+                let later_values = bindings[index + 1..].iter().filter_map(binding).map(|(_, value)| value);
+                let used = later_values.chain(body).any(|node| mentions(node, name));
+
+                if !used && !name.as_str().starts_with('_') {
+                    self.lint(LintKind::UnusedBinding, node.span, format!("{} is bound but never used, prefix it with _ if that's intended", name));
+                }
+
+                self.bound.last_mut().unwrap().push(name);
+            }
+        }
+
+        self.check_sequence(body, Context::Body("this is evaluated before let binds anything, brace it to evaluate it inside of the let"));
+        self.bound.pop();
+    }
+}
+
+fn symbol(node: &Node) -> Option<Symbol> {
+    match node.kind {
+        NodeKind::Atom(Value::Symbol(sym)) => Some(sym),
+        _ => None,
+    }
+}
+
+// The parameters of a lambda, if they're valid
+fn params(nodes: &[Node]) -> Option<Params> {
+    let values: Result<Vec<Value>, _> = nodes.iter().map(Node::to_value).collect();
+    values.ok().and_then(|values| Params::parse(values).ok())
+}
+
+// The name a parameter binds, which is the first item of an (name default) pair
+fn param_name(node: &Node) -> &Node {
+    match node.kind {
+        NodeKind::List(ref pair) if !pair.is_empty() => &pair[0],
+        _ => node,
+    }
+}
+
+// The names bound by a pattern, none if it isn't valid
+fn pattern_names(node: &Node) -> Vec<Symbol> {
+    node.to_value().ok()
+        .and_then(|value| Pattern::parse(&value).ok())
+        .map_or(Vec::new(), |pattern| pattern.names())
+}
+
+fn is_list(node: &Node) -> bool {
+    match node.kind {
+        NodeKind::List(_) => true,
+        _ => false,
+    }
+}
+
+// A (pattern value) pair of a let, with the names it binds
+fn binding(node: &Node) -> Option<(Vec<Symbol>, &Node)> {
+    match node.kind {
+        NodeKind::List(ref pair) if pair.len() == 2 => Some((pattern_names(&pair[0]), &pair[1])),
+        _ => None,
+    }
+}
+
+fn mentions(node: &Node, name: Symbol) -> bool {
+    match node.kind {
+        NodeKind::Atom(Value::Symbol(sym)) => sym == name,
+        NodeKind::Atom(_) => false,
+        NodeKind::List(ref items) | NodeKind::QuotedList(ref items) => items.iter().any(|item| mentions(item, name)),
+        NodeKind::Error { ref children, .. } => children.iter().any(|child| mentions(child, name)),
+        NodeKind::Quote(ref inner) => mentions(inner, name),
+    }
+}
@@ -0,0 +1,214 @@
+use std::rc::{Rc, Weak};
+use std::iter::FromIterator;
+use std::fmt;
+
+use value::Value;
+
+/// A persistent, singly linked list.
+/// Lists share their tails, so cloning, `cons`, `head` and `tail` are all O(1).
+#[derive(Clone)]
+pub struct List {
+    node: Option<Rc<Node>>,
+}
+
+struct Node {
+    value: Value,
+    next: List,
+    len: usize,
+}
+
+impl List {
+    pub fn new() -> List {
+        List {
+            node: None,
+        }
+    }
+
+    /// Returns a new list with `value` in front of this one, which is shared rather than copied
+    pub fn cons(&self, value: Value) -> List {
+        List {
+            node: Some(Rc::new(Node {
+                value: value,
+                next: self.clone(),
+                len: self.len() + 1,
+            })),
+        }
+    }
+
+    pub fn head(&self) -> Option<&Value> {
+        self.node.as_ref().map(|node| &node.value)
+    }
+
+    pub fn tail(&self) -> Option<List> {
+        self.node.as_ref().map(|node| node.next.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.node.as_ref().map_or(0, |node| node.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node.is_none()
+    }
+
+    /// The address of the list's first cell, which identifies the list for as long as it's alive.
+    /// Clones of a list share their cells, so they have the same address.
+    pub fn addr(&self) -> usize {
+        self.node.as_ref().map_or(0, |node| &**node as *const Node as usize)
+    }
+
+    pub fn iter(&self) -> Iter {
+        Iter {
+            list: self,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.iter().cloned().collect()
+    }
+
+    pub fn downgrade(&self) -> WeakList {
+        WeakList {
+            node: self.node.as_ref().map(Rc::downgrade),
+        }
+    }
+}
+
+/// A list that isn't kept alive by this reference to it.
+/// Its first cell isn't freed until the reference is dropped though, so no other list can have its address until then.
+#[derive(Clone)]
+pub struct WeakList {
+    node: Option<Weak<Node>>,
+}
+
+impl WeakList {
+    /// The list, if something else is still keeping it alive
+    pub fn upgrade(&self) -> Option<List> {
+        match self.node {
+            Some(ref node) => node.upgrade().map(|node| List { node: Some(node) }),
+            None => Some(List::new()),
+        }
+    }
+}
+
+impl Drop for List {
+    // The default drop would recurse once per node, which overflows the stack on long lists
+    fn drop(&mut self) {
+        let mut node = self.node.take();
+
+        while let Some(rc) = node {
+            match Rc::try_unwrap(rc) {
+                Ok(mut owned) => node = owned.next.node.take(),
+                Err(_) => break, // The rest of the list is still shared with someone else
+            }
+        }
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, rhs: &List) -> bool {
+        self.len() == rhs.len() && self.iter().zip(rhs.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.iter()).finish()
+    }
+}
+
+pub struct Iter<'a> {
+    list: &'a List,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        match self.list.node {
+            Some(ref node) => {
+                self.list = &node.next;
+                Some(&node.value)
+            },
+            None => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len(), Some(self.list.len()))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> { }
+
+impl<'a> IntoIterator for &'a List {
+    type Item = &'a Value;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+pub struct IntoIter {
+    list: List,
+}
+
+impl Iterator for IntoIter {
+    type Item = Value;
+
+    // Values are moved out of nodes that aren't shared, and cloned out of ones that are
+    fn next(&mut self) -> Option<Value> {
+        let rc = match self.list.node.take() {
+            Some(rc) => rc,
+            None => return None,
+        };
+
+        match Rc::try_unwrap(rc) {
+            Ok(mut node) => {
+                self.list = List { node: node.next.node.take() };
+                Some(node.value)
+            },
+            Err(rc) => {
+                self.list = rc.next.clone();
+                Some(rc.value.clone())
+            },
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len(), Some(self.list.len()))
+    }
+}
+
+impl ExactSizeIterator for IntoIter { }
+
+impl IntoIterator for List {
+    type Item = Value;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter {
+            list: self,
+        }
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item=Value>>(iter: I) -> List {
+        let values: Vec<Value> = iter.into_iter().collect();
+        List::from(values)
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(values: Vec<Value>) -> List {
+        let mut list = List::new();
+
+        for value in values.into_iter().rev() {
+            list = list.cons(value);
+        }
+
+        list
+    }
+}
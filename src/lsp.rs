@@ -0,0 +1,778 @@
+//! A language server for rlisp, speaking the Language Server Protocol over any reader and writer
+//! (`rlisp-lsp` runs it over stdio).
+//!
+//! Diagnostics come from the parser and the linter, and hover shows a function's arity and docstring.
+//! Definitions, completion and document symbols come from the `def`s in the open documents,
+//! along with every `.lisp` file in the workspace so names defined in other files can be found.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use docs::FuncDoc;
+use eval::Lisp;
+use json::{self, Json};
+use lint::{self, LintKind};
+use parse::{self, Node, NodeKind, Span};
+use resolve::{core_form, CoreForm};
+use symbol::Symbol;
+use value::{Value, Params};
+
+// JSON-RPC error codes
+const PARSE_ERROR: isize = -32700;
+const INVALID_REQUEST: isize = -32600;
+const METHOD_NOT_FOUND: isize = -32601;
+
+// LSP enums
+const SEVERITY_ERROR: usize = 1;
+const SEVERITY_WARNING: usize = 2;
+const TAG_UNNECESSARY: usize = 1;
+const COMPLETION_FUNCTION: usize = 3;
+const COMPLETION_VARIABLE: usize = 6;
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+
+/// Messages with a longer body are refused, rather than trusting the client with how much to allocate
+pub const MAX_MESSAGE_LENGTH: usize = 64 << 20;
+
+/// A name given to `def` somewhere in a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: Symbol,
+    /// Where the name is written
+    pub span: Span,
+    /// The whole def
+    pub form: Span,
+    /// The parameters, if it defines a function
+    pub params: Option<Params>,
+    /// The function's docstring, if it has one
+    pub doc: Option<String>,
+}
+
+impl Definition {
+    /// How the definition is used, e.g. `(square x)` or `pi`
+    pub fn signature(&self) -> String {
+        match self.params {
+            Some(ref params) => call_signature(self.name, params),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+/// Every def in the nodes, including those nested inside of other forms
+pub fn definitions(lisp: &Lisp, nodes: &[Node]) -> Vec<Definition> {
+    let mut defs = Vec::new();
+    for node in nodes {
+        collect_definitions(lisp, node, &mut defs);
+    }
+
+    defs
+}
+
+fn collect_definitions(lisp: &Lisp, node: &Node, defs: &mut Vec<Definition>) {
+    let items = match node.kind {
+        NodeKind::List(ref items) | NodeKind::QuotedList(ref items) => items,
+        NodeKind::Quote(ref inner) => return collect_definitions(lisp, inner, defs),
+        NodeKind::Error { ref children, .. } => children,
+        NodeKind::Atom(_) => return,
+    };
+
+    if items.len() > 1 && is_core_form(lisp, &items[0], CoreForm::Def) {
+        match items[1].kind {
+            NodeKind::QuotedList(ref sig) | NodeKind::List(ref sig) => {
+                if let Some(name) = sig.first().and_then(symbol) {
+                    defs.push(Definition {
+                        name: name,
+                        span: sig[0].span,
+                        form: node.span,
+                        params: Some(params(&sig[1..])),
+                        doc: docstring(&items[2..]),
+                    });
+                } else if let Some(pattern) = sig.first() {
+                    let mut names = Vec::new();
+                    pattern_symbols(pattern, &mut names);
+
+                    for (name, span) in names {
+                        defs.push(Definition {
+                            name: name,
+                            span: span,
+                            form: node.span,
+                            params: None,
+                            doc: None,
+                        });
+                    }
+                }
+            },
+            NodeKind::Quote(ref inner) => if let Some(name) = symbol(inner) {
+                defs.push(Definition {
+                    name: name,
+                    span: inner.span,
+                    form: node.span,
+                    params: items.get(2).and_then(|value| lambda_params(lisp, value)),
+                    doc: items.get(2).and_then(|value| lambda_doc(lisp, value)),
+                });
+            },
+            _ => (),
+        }
+    }
+
+    for item in items {
+        collect_definitions(lisp, item, defs);
+    }
+}
+
+fn is_core_form(lisp: &Lisp, head: &Node, form: CoreForm) -> bool {
+    match head.kind {
+        NodeKind::Atom(ref value) => core_form(lisp, value) == Some(form),
+        _ => false,
+    }
+}
+
+// The parameters of a (\ {params} ...) form
+fn lambda_params(lisp: &Lisp, node: &Node) -> Option<Params> {
+    match node.kind {
+        NodeKind::List(ref items) if items.len() > 1 && is_core_form(lisp, &items[0], CoreForm::Lambda) => {
+            match items[1].kind {
+                NodeKind::QuotedList(ref nodes) => Some(params(nodes)),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+// The names in a destructuring pattern, along with where they're written
+fn pattern_symbols(node: &Node, names: &mut Vec<(Symbol, Span)>) {
+    match node.kind {
+        NodeKind::Atom(Value::Symbol(sym)) if sym.as_str() != "." && sym.as_str() != "&rest" => names.push((sym, node.span)),
+        NodeKind::List(ref items) => for item in items {
+            pattern_symbols(item, names);
+        },
+        _ => (),
+    }
+}
+
+// Parameters as they're written, just the names if they aren't valid
+fn params(nodes: &[Node]) -> Params {
+    let values: Result<Vec<Value>, _> = nodes.iter().map(Node::to_value).collect();
+
+    match values.ok().and_then(|values| Params::parse(values).ok()) {
+        Some(params) => params,
+        None => Params::new(nodes.iter().filter_map(symbol).collect()),
+    }
+}
+
+// The docstring of a (\ {params} "doc" ...) form
+fn lambda_doc(lisp: &Lisp, node: &Node) -> Option<String> {
+    match node.kind {
+        NodeKind::List(ref items) if items.len() > 1 && is_core_form(lisp, &items[0], CoreForm::Lambda) => docstring(&items[2..]),
+        _ => None,
+    }
+}
+
+// A string starting a function's body, as long as there's more to the body
+fn docstring(body: &[Node]) -> Option<String> {
+    match body.first().map(|node| &node.kind) {
+        Some(&NodeKind::Atom(Value::String(ref doc))) if body.len() > 1 => Some(doc.clone()),
+        _ => None,
+    }
+}
+
+fn symbol(node: &Node) -> Option<Symbol> {
+    match node.kind {
+        NodeKind::Atom(Value::Symbol(sym)) => Some(sym),
+        _ => None,
+    }
+}
+
+// The symbol written at the given offset, including when the offset is just past its end
+fn symbol_at(nodes: &[Node], offset: usize) -> Option<(Symbol, Span)> {
+    nodes.iter()
+        .filter(|node| node.span.start <= offset && offset <= node.span.end)
+        .filter_map(|node| node_symbol_at(node, offset))
+        .next()
+}
+
+fn node_symbol_at(node: &Node, offset: usize) -> Option<(Symbol, Span)> {
+    match node.kind {
+        NodeKind::Atom(Value::Symbol(sym)) => Some((sym, node.span)),
+        NodeKind::Atom(_) => None,
+        NodeKind::List(ref items) | NodeKind::QuotedList(ref items) => symbol_at(items, offset),
+        NodeKind::Error { ref children, .. } => symbol_at(children, offset),
+        NodeKind::Quote(ref inner) => node_symbol_at(inner, offset),
+    }
+}
+
+/// Reads one message, returning None once the input has ended
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if try!(input.read_line(&mut header)) == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+
+            continue; // Blank lines between messages
+        }
+
+        let mut parts = header.splitn(2, ':');
+        let (name, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or("").trim());
+
+        if name.to_lowercase() == "content-length" {
+            length = value.parse().ok();
+        }
+    }
+
+    let length = length.unwrap();
+    if length > MAX_MESSAGE_LENGTH {
+        let message = format!("a message of {} bytes is over the limit of {}", length, MAX_MESSAGE_LENGTH);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+
+    let mut body = vec![0; length];
+    try!(input.read_exact(&mut body));
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+
+    try!(write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body));
+    output.flush()
+}
+
+fn response(id: Json, result: Result<Json, (isize, String)>) -> Json {
+    let mut fields = vec![("jsonrpc", Json::from("2.0")), ("id", id)];
+
+    match result {
+        Ok(result) => fields.push(("result", result)),
+        Err((code, message)) => fields.push(("error", Json::object(vec![
+            ("code", Json::Number(code as f64)),
+            ("message", message.into()),
+        ]))),
+    }
+
+    Json::object(fields)
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+/// The byte offset of an LSP position, whose character is counted in UTF-16 code units
+fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(newline) => start += newline + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (index, ch) in text[start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return start + index;
+        }
+
+        units += ch.len_utf16();
+    }
+
+    text.len()
+}
+
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    Json::object(vec![
+        ("line", before.matches('\n').count().into()),
+        ("character", before[line_start..].encode_utf16().count().into()),
+    ])
+}
+
+fn range(text: &str, span: Span) -> Json {
+    Json::object(vec![
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+
+    let encoded = uri["file://".len()..].as_bytes();
+    let mut bytes = Vec::new();
+    let mut index = 0;
+
+    while index < encoded.len() {
+        let escaped = match encoded[index] {
+            b'%' if index + 2 < encoded.len() => {
+                ::std::str::from_utf8(&encoded[index + 1..index + 3]).ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            },
+            _ => None,
+        };
+
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 3;
+            },
+            None => {
+                bytes.push(encoded[index]);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for &byte in path.to_string_lossy().as_bytes() {
+        match byte {
+            _ if (byte as char).is_ascii_alphanumeric() || b"/-_.~".contains(&byte) => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+// Everything but these can be part of a symbol
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || "(){}[]'\";".contains(ch)
+}
+
+struct Document {
+    text: String,
+    nodes: Vec<Node>,
+    /// Whether the client has it open, rather than it only being read from the workspace
+    open: bool,
+}
+
+impl Document {
+    fn new(text: String, open: bool) -> Document {
+        let (nodes, _) = parse::parse_recovering(&text);
+
+        Document {
+            text: text,
+            nodes: nodes,
+            open: open,
+        }
+    }
+}
+
+pub struct Server {
+    lisp: Lisp,
+    documents: HashMap<String, Document>, // By URI
+    shutdown: bool,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::with_lisp(Lisp::new())
+    }
+
+    /// A server whose builtins are the functions defined in `lisp`
+    pub fn with_lisp(lisp: Lisp) -> Server {
+        Server {
+            lisp: lisp,
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Serves until the client sends `exit` or the input ends, returning the exit code the process should use
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut output: W) -> io::Result<i32> {
+        while let Some(body) = try!(read_message(&mut input)) {
+            let message = match json::parse(&body) {
+                Ok(message) => message,
+                Err(err) => {
+                    try!(write_message(&mut output, &response(Json::Null, Err((PARSE_ERROR, err)))));
+                    continue;
+                },
+            };
+
+            if message.get("method").and_then(Json::as_str) == Some("exit") {
+                break;
+            }
+
+            for reply in self.handle(&message) {
+                try!(write_message(&mut output, &reply));
+            }
+        }
+
+        Ok(if self.shutdown { 0 } else { 1 })
+    }
+
+    /// Handles a single request or notification, returning the messages to send back
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notify(method, params),
+        };
+
+        let result = match method {
+            _ if self.shutdown => Err((INVALID_REQUEST, "the server has been shut down".to_string())),
+            "initialize" => Ok(self.initialize(params)),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            },
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(params)),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {}", method))),
+        };
+
+        vec![response(id, result)]
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = match params.at(&["textDocument", "uri"]).and_then(Json::as_str) {
+            Some(uri) => uri.to_string(),
+            None => return Vec::new(),
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.at(&["textDocument", "text"]).and_then(Json::as_str).unwrap_or("");
+                self.documents.insert(uri.clone(), Document::new(text.to_string(), true));
+            },
+            "textDocument/didChange" => {
+                // Documents are synced in full, so the last change is the whole text
+                let text = params.get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+
+                if let Some(text) = text {
+                    self.documents.insert(uri.clone(), Document::new(text.to_string(), true));
+                }
+            },
+            "textDocument/didClose" => {
+                // Files in the workspace are still searched for definitions once they're closed
+                let saved = uri_to_path(&uri).and_then(|path| read_file(&path).ok());
+
+                match saved {
+                    Some(text) => self.documents.insert(uri.clone(), Document::new(text, false)),
+                    None => self.documents.remove(&uri),
+                };
+
+                return vec![self.publish(&uri, Vec::new())];
+            },
+            _ => return Vec::new(),
+        }
+
+        let diagnostics = self.diagnostics(&uri);
+        vec![self.publish(&uri, diagnostics)]
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        let root = params.get("rootUri").and_then(Json::as_str)
+            .or_else(|| {
+                params.get("workspaceFolders")
+                    .and_then(Json::as_array)
+                    .and_then(|folders| folders.first())
+                    .and_then(|folder| folder.get("uri"))
+                    .and_then(Json::as_str)
+            })
+            .and_then(uri_to_path)
+            .or_else(|| params.get("rootPath").and_then(Json::as_str).map(PathBuf::from));
+
+        if let Some(root) = root {
+            self.index_workspace(&root);
+        }
+
+        Json::object(vec![
+            ("capabilities", Json::object(vec![
+                ("textDocumentSync", 1.into()), // Full
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("completionProvider", Json::object(vec![])),
+                ("documentSymbolProvider", true.into()),
+            ])),
+            ("serverInfo", Json::object(vec![
+                ("name", "rlisp-lsp".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ])),
+        ])
+    }
+
+    // Reads every .lisp file under the root, skipping hidden directories and build output
+    fn index_workspace(&mut self, dir: &Path) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    self.index_workspace(&path);
+                }
+            } else if name.ends_with(".lisp") {
+                let uri = path_to_uri(&path);
+                if self.documents.get(&uri).map_or(false, |doc| doc.open) {
+                    continue;
+                }
+
+                if let Ok(text) = read_file(&path) {
+                    self.documents.insert(uri, Document::new(text, false));
+                }
+            }
+        }
+    }
+
+    fn diagnostics(&self, uri: &str) -> Vec<Json> {
+        let doc = match self.documents.get(uri) {
+            Some(doc) => doc,
+            None => return Vec::new(),
+        };
+
+        lint::lint_source(&self.lisp, &doc.text).into_iter()
+            .map(|lint| {
+                let severity = match lint.kind {
+                    LintKind::SyntaxError | LintKind::Arity => SEVERITY_ERROR,
+                    _ => SEVERITY_WARNING,
+                };
+
+                let mut fields = vec![
+                    ("range", range(&doc.text, lint.span)),
+                    ("severity", severity.into()),
+                    ("code", lint.kind.name().into()),
+                    ("source", "rlisp".into()),
+                    ("message", lint.message.clone().into()),
+                ];
+
+                if lint.kind == LintKind::UnusedBinding || lint.kind == LintKind::Unreachable {
+                    fields.push(("tags", vec![TAG_UNNECESSARY.into()].into()));
+                }
+
+                Json::object(fields)
+            })
+            .collect()
+    }
+
+    fn publish(&self, uri: &str, diagnostics: Vec<Json>) -> Json {
+        notification("textDocument/publishDiagnostics", Json::object(vec![
+            ("uri", uri.into()),
+            ("diagnostics", diagnostics.into()),
+        ]))
+    }
+
+    // The document and byte offset a request is about
+    fn target<'a>(&'a self, params: &'a Json) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params.at(&["textDocument", "uri"]).and_then(Json::as_str);
+        let line = params.at(&["position", "line"]).and_then(Json::as_usize);
+        let character = params.at(&["position", "character"]).and_then(Json::as_usize);
+
+        match (uri.and_then(|uri| self.documents.get(uri)), line, character) {
+            (Some(doc), Some(line), Some(character)) => Some((uri.unwrap(), doc, offset_at(&doc.text, line, character))),
+            _ => None,
+        }
+    }
+
+    // Definitions of the name, the ones in the given document first
+    fn find_definitions(&self, uri: &str, name: Symbol) -> Vec<(&str, &Document, Definition)> {
+        let mut uris: Vec<&String> = self.documents.keys().collect();
+        uris.sort_by_key(|&other| (other != uri, other.clone()));
+
+        let mut found = Vec::new();
+        for other in uris {
+            let doc = &self.documents[other];
+
+            for def in definitions(&self.lisp, &doc.nodes) {
+                if def.name == name {
+                    found.push((&other[..], doc, def));
+                }
+            }
+        }
+
+        found
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let (uri, doc, offset) = match self.target(params) {
+            Some(target) => target,
+            None => return Json::Null,
+        };
+
+        let (name, span) = match symbol_at(&doc.nodes, offset) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+
+        let contents = match self.find_definitions(uri, name).into_iter().next() {
+            Some((_, _, def)) => {
+                let mut contents = format!("```rlisp\n{}\n```", def.signature());
+                if let Some(ref doc) = def.doc {
+                    contents.push_str(&format!("\n\n{}", doc));
+                }
+                if let Some(ref params) = def.params {
+                    contents.push_str(&format!("\n\ntakes {}", params.arity()));
+                }
+
+                contents
+            },
+            None => match self.lisp.scopes[0].get(name) {
+                Some(value) => match FuncDoc::new(name, value) {
+                    Some(func) => {
+                        let mut contents = format!("```rlisp\n{}\n```", func.usage);
+                        if let Some(ref text) = func.text {
+                            contents.push_str(&format!("\n\n{}", text));
+                        }
+
+                        contents + &format!("\n\n{}takes {}", if func.builtin { "builtin, " } else { "" }, func.args)
+                    },
+                    None => format!("```rlisp\n{}\n```\n\nbuiltin {:?}, `{:?}`", name, value.typ(), value),
+                },
+                None => return Json::Null,
+            },
+        };
+
+        Json::object(vec![
+            ("contents", Json::object(vec![
+                ("kind", "markdown".into()),
+                ("value", contents.into()),
+            ])),
+            ("range", range(&doc.text, span)),
+        ])
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let (uri, doc, offset) = match self.target(params) {
+            Some(target) => target,
+            None => return Json::Null,
+        };
+
+        let name = match symbol_at(&doc.nodes, offset) {
+            Some((name, _)) => name,
+            None => return Json::Null,
+        };
+
+        let locations: Vec<Json> = self.find_definitions(uri, name).into_iter()
+            .map(|(uri, doc, def)| Json::object(vec![
+                ("uri", uri.into()),
+                ("range", range(&doc.text, def.span)),
+            ]))
+            .collect();
+
+        match locations.is_empty() {
+            true => Json::Null,
+            false => locations.into(),
+        }
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let (_, doc, offset) = match self.target(params) {
+            Some(target) => target,
+            None => return Json::Null,
+        };
+
+        let prefix_start = doc.text[..offset].char_indices()
+            .rev()
+            .take_while(|&(_, ch)| !is_delimiter(ch))
+            .last()
+            .map_or(offset, |(index, _)| index);
+        let prefix = &doc.text[prefix_start..offset];
+
+        // Sorted by name, with definitions in the code replacing builtins of the same name
+        let mut items = BTreeMap::new();
+
+        for (&name, value) in self.lisp.scopes[0].map.iter() {
+            let (kind, detail) = match value {
+                &Value::HardFunc(ref func) => (COMPLETION_FUNCTION, format!("builtin, takes {}", func.args)),
+                &Value::Lambda(ref lambda) => (COMPLETION_FUNCTION, call_signature(name, &lambda.params)),
+                other => (COMPLETION_VARIABLE, format!("builtin {:?}", other.typ())),
+            };
+
+            items.insert(name.as_str(), (kind, detail));
+        }
+
+        for doc in self.documents.values() {
+            for def in definitions(&self.lisp, &doc.nodes) {
+                let kind = if def.params.is_some() { COMPLETION_FUNCTION } else { COMPLETION_VARIABLE };
+                items.insert(def.name.as_str(), (kind, def.signature()));
+            }
+        }
+
+        let items: Vec<Json> = items.into_iter()
+            .filter(|&(name, _)| name.starts_with(prefix))
+            .map(|(name, (kind, detail))| Json::object(vec![
+                ("label", name.into()),
+                ("kind", kind.into()),
+                ("detail", detail.into()),
+            ]))
+            .collect();
+
+        Json::object(vec![
+            ("isIncomplete", false.into()),
+            ("items", items.into()),
+        ])
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let doc = match params.at(&["textDocument", "uri"]).and_then(Json::as_str).and_then(|uri| self.documents.get(uri)) {
+            Some(doc) => doc,
+            None => return Json::Null,
+        };
+
+        let symbols: Vec<Json> = definitions(&self.lisp, &doc.nodes).into_iter()
+            .map(|def| Json::object(vec![
+                ("name", def.name.as_str().into()),
+                ("detail", def.signature().into()),
+                ("kind", if def.params.is_some() { SYMBOL_FUNCTION } else { SYMBOL_VARIABLE }.into()),
+                ("range", range(&doc.text, def.form)),
+                ("selectionRange", range(&doc.text, def.span)),
+            ]))
+            .collect();
+
+        symbols.into()
+    }
+}
+
+fn call_signature(name: Symbol, params: &Params) -> String {
+    let params = params.to_string();
+
+    match params.is_empty() {
+        true => format!("({})", name),
+        false => format!("({} {})", name, params),
+    }
+}
+
+fn read_file(path: &Path) -> io::Result<String> {
+    let mut text = String::new();
+    try!(try!(fs::File::open(path)).read_to_string(&mut text));
+
+    Ok(text)
+}
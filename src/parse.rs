@@ -1,42 +1,39 @@
 use regex::Regex;
 
-use std::collections::VecDeque;
 use std::fmt;
+use std::char;
 
-use eval::FuncError;
 use value::Value;
+use symbol::Symbol;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
     UnclosedList,
     InvalidListDelimitter,
     UnreadableSourceCode,
     InvalidHexLiteral,
+    InvalidCharLiteral(String),
+    InvalidByteLiteral,
+    UnterminatedString,
+    InvalidEscape,
+    TooManySymbols, // See `symbol::MAX_SYMBOLS`
 }
 
-#[derive(Debug, Clone)]
+/// A line and column in source code, both starting from 1. Columns count chars, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FilePos {
-    line: usize,
-    col: usize,
+    pub line: usize,
+    pub col: usize,
 }
 
 impl FilePos {
-    fn from_offset(text: &str, mut offset: usize) -> FilePos {
-        let mut line_no = 1;
-
-        for (cur_offset, ch) in text.bytes().enumerate() {
-            if cur_offset >= offset {
-                break;
-            }
-
-            if ch == '\n' as u8 {
-                line_no += 1;
-            }
-        }
+    pub fn from_offset(text: &str, offset: usize) -> FilePos {
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
 
         FilePos {
-            line: line_no,
-            col: 0,
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
         }
     }
 }
@@ -49,141 +46,678 @@ const LIST_CLOSE: &'static str = ")";
 const QUOTE_OPEN: &'static str = "{";
 const QUOTE_CLOSE: &'static str = "}";
 
-pub fn write_list<T>(fmt: &mut fmt::Formatter, list: &Vec<T>, start: &str, sep: &str, end: &str) -> fmt::Result where T: fmt::Debug {
-    if list.len() == 0 {
-        return write!(fmt, "{}{}", start, end);
-    }
+const BYTES_PREFIX: &'static str = "#u8";
 
-    write!(fmt, "{}{:?}", start, list[0]);
+pub fn write_list<I>(fmt: &mut fmt::Formatter, list: I, start: &str, sep: &str, end: &str) -> fmt::Result
+    where I: IntoIterator, I::Item: fmt::Debug
+{
+    let mut items = list.into_iter();
 
-    for token in &list[1..] {
-        write!(fmt, "{}{:?}", sep, token);
+    try!(write!(fmt, "{}", start));
+
+    if let Some(first) = items.next() {
+        try!(write!(fmt, "{:?}", first));
+
+        for token in items {
+            try!(write!(fmt, "{}{:?}", sep, token));
+        }
     }
 
     write!(fmt, "{}", end)
 }
 
-fn remove_comments(code: &str) -> String {
-    let re = Regex::new(r#";.*\n"#).unwrap();
-    re.replace_all(code, "").to_string()
+const CHAR_NAMES: &'static [(&'static str, char)] = &[
+    ("space", ' '),
+    ("newline", '\n'),
+    ("tab", '\t'),
+    ("return", '\r'),
+    ("nul", '\0'),
+];
+
+/// Writes a char using the same syntax it's read with, e.g. #\a or #\space
+pub fn write_char(fmt: &mut fmt::Formatter, ch: char) -> fmt::Result {
+    for &(name, named_ch) in CHAR_NAMES {
+        if ch == named_ch {
+            return write!(fmt, "#\\{}", name);
+        }
+    }
+
+    if ch.is_control() {
+        write!(fmt, "#\\u{{{:X}}}", ch as u32)
+    } else {
+        write!(fmt, "#\\{}", ch)
+    }
 }
 
-fn tokenize(code: &str) -> VecDeque<String> {
-    let string_re = r#""[^"]*""#;
-    let sym_re = r"[-!:?#\w\.]+";
+fn token_regex() -> Regex {
+    let comment_re = r";[^\n]*";
+    let string_re = r#""(?:[^"\\]|\\(?s:.))*""#; // A backslash escapes whatever follows it, including a quote
+    let unterminated_re = r#""(?:[^"\\]|\\(?s:.))*\\?"#; // Only matches when there's no closing quote, so it runs to the end
+    let char_re = r"#\\(?:u\{[[:xdigit:]]+\}|[[:alpha:]]+|.)";
+    let sym_re = r"[-!:?#\w\.<>=*/+&]+";
     let num_re = r"\d+\.?\d*e?\d*";
     let list_re = r"[(){}\[\]]";
     let op_re = r"\+|-|\*|/|\^|&|\||=|\\|<|>";
     let quote_re = r"'";
 
-    let regex = format!("{}|{}|{}|{}|{}|{}", string_re, num_re, sym_re, list_re, op_re, quote_re);
+    let regex = format!("{}|{}|{}|{}|{}|{}|{}|{}|{}",
+                        comment_re, string_re, unterminated_re, char_re, num_re, sym_re, list_re, op_re, quote_re);
 
-    let re = match Regex::new(&regex) {
-        Ok(re) => re,
-        Err(_) => return VecDeque::new(),
+    Regex::new(&regex).unwrap()
+}
+
+thread_local! {
+    // Compiled once per thread, since the incremental reader parses every form separately
+    static TOKEN_RE: Regex = token_regex();
+}
+
+fn tokenize(code: &str) -> Vec<CstToken> {
+    let mut tokens = Vec::new();
+    let mut last = 0;
+
+    // Parens are matched by their own pattern, so there's no need to pad them with spaces
+    // (doing so would also mangle any parens inside of string literals, such as regex groups).
+    // Comments are matched like any other token, so that a ; inside of a string literal doesn't start one
+    TOKEN_RE.with(|re| {
+        for found in re.find_iter(code) {
+            push_gap(&mut tokens, code, last, found.start());
+
+            let text = found.as_str();
+            let kind = match text {
+                LIST_OPEN | QUOTE_OPEN => TokenKind::Open,
+                LIST_CLOSE | QUOTE_CLOSE => TokenKind::Close,
+                BYTES_PREFIX => TokenKind::BytesPrefix,
+                "'" => TokenKind::Quote,
+                _ if text.starts_with(";") => TokenKind::Comment,
+                _ => TokenKind::Atom,
+            };
+
+            tokens.push(CstToken::new(kind, text, Span::new(found.start(), found.end())));
+            last = found.end();
+        }
+    });
+
+    push_gap(&mut tokens, code, last, code.len());
+    tokens
+}
+
+// Splits the text between two tokens into whitespace, and characters that aren't part of any token
+fn push_gap(tokens: &mut Vec<CstToken>, code: &str, start: usize, end: usize) {
+    let mut run_start = start;
+
+    for (offset, ch) in code[start..end].char_indices() {
+        let pos = start + offset;
+
+        if pos > run_start && ch.is_whitespace() != code[run_start..].starts_with(char::is_whitespace) {
+            tokens.push(gap_token(code, run_start, pos));
+            run_start = pos;
+        }
+    }
+
+    if run_start < end {
+        tokens.push(gap_token(code, run_start, end));
+    }
+}
+
+fn gap_token(code: &str, start: usize, end: usize) -> CstToken {
+    let text = &code[start..end];
+    let kind = match text.starts_with(char::is_whitespace) {
+        true => TokenKind::Whitespace,
+        false => TokenKind::Skipped,
     };
 
-    let spaced_code = remove_comments(code)
-        .replace("(", " ( ")
-        .replace(")", " ) ");
+    CstToken::new(kind, text, Span::new(start, end))
+}
 
-    let mut token_strs = VecDeque::new();
+/// A range of bytes in the source code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
 
-    for cap in re.captures_iter(&spaced_code) {
-        let (offset, _) = cap.get(0)
-            .map(|text| (text.start(), text.end()))
-            .unwrap_or((0, 0));
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start: start,
+            end: end,
+        }
+    }
+
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
 
-        let match_str = cap.get(0)
-            .map(|text| text.as_str())
-            .unwrap_or("");
+/// A node in the syntax tree produced by `parse_recovering`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    /// Numbers, strings, symbols, chars and byte literals
+    Atom(Value),
+    /// ( ... )
+    List(Vec<Node>),
+    /// { ... }
+    QuotedList(Vec<Node>),
+    /// 'x
+    Quote(Box<Node>),
+    /// Source that couldn't be parsed, along with whatever could be parsed inside of it (e.g. the items of an unclosed list)
+    Error {
+        error: ParseError,
+        children: Vec<Node>,
+    },
+}
 
-        token_strs.push_back(match_str.to_string());
+impl Node {
+    /// Converts the node into the value it evaluates to, failing with the first error inside of it
+    pub fn to_value(&self) -> ParseResult {
+        match self.kind {
+            NodeKind::Atom(ref value) => Ok(value.clone()),
+            NodeKind::List(ref items) => {
+                let items: Vec<Value> = try!(items.iter().map(Node::to_value).collect());
+                Ok(Value::List(items.into()))
+            },
+            NodeKind::QuotedList(ref items) => {
+                let items: Vec<Value> = try!(items.iter().map(Node::to_value).collect());
+                Ok(Value::Quote(Box::new(Value::List(items.into()))))
+            },
+            NodeKind::Quote(ref inner) => Ok(Value::Quote(Box::new(try!(inner.to_value())))),
+            NodeKind::Error { ref error, .. } => Err(error.clone()),
+        }
     }
+}
 
-    return token_strs;
+/// A syntax error, and where it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    pub span: Span,
 }
 
 pub fn parse_str(code: &str) -> Result<Vec<Value>, ParseError> {
-    let mut values = Vec::new();
+    let (nodes, diagnostics) = parse_nodes(code);
 
-    let mut tokens = tokenize(code);
+    // The first error the parser ran into, which for an unclosed list is only once it's reached the end
+    if let Some(diagnostic) = diagnostics.into_iter().next() {
+        return Err(diagnostic.error);
+    }
+
+    nodes.iter().map(Node::to_value).collect()
+}
+
+/// Parses as much of the code as possible rather than stopping at the first error.
+/// Anything that can't be parsed becomes an error node in the tree, and every error is also reported
+/// as a diagnostic (ordered by where they start), so tools can point out all of them at once.
+pub fn parse_recovering(code: &str) -> (Vec<Node>, Vec<Diagnostic>) {
+    let (nodes, mut diagnostics) = parse_nodes(code);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    (nodes, diagnostics)
+}
+
+fn parse_nodes(code: &str) -> (Vec<Node>, Vec<Diagnostic>) {
+    let (cst, diagnostics) = parse_cst_unsorted(code);
+    (cst.nodes(), diagnostics)
+}
+
+/// The kinds of token in a concrete syntax tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    /// Characters that aren't part of any token, which are otherwise ignored
+    Skipped,
+    /// ( or {
+    Open,
+    /// ) or }
+    Close,
+    /// The ' in 'x
+    Quote,
+    /// The #u8 in #u8(1 2 3)
+    BytesPrefix,
+    /// Numbers, strings, symbols and chars
+    Atom,
+}
+
+impl TokenKind {
+    /// Whether the token has no meaning to the parser
+    pub fn is_trivia(&self) -> bool {
+        match *self {
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::Skipped => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstToken {
+    pub kind: TokenKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl CstToken {
+    fn new(kind: TokenKind, text: &str, span: Span) -> CstToken {
+        CstToken {
+            kind: kind,
+            text: text.to_string(),
+            span: span,
+        }
+    }
+}
 
-    while tokens.len() > 0 {
-        values.push(try!(parse(&mut tokens)));
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstKind {
+    /// ( ... )
+    List,
+    /// { ... }
+    QuotedList,
+    /// #u8( ... ), its children are the prefix, then a list
+    Bytes,
+    /// 'x
+    Quote,
+    /// Source that couldn't be parsed, its children are everything that was read while trying to
+    Error(ParseError),
+}
+
+/// A node in a concrete syntax tree.
+/// Its children include every token inside of it, delimiters, whitespace and comments included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: CstKind,
+    pub children: Vec<CstElement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstElement {
+    Node(CstNode),
+    Token(CstToken),
+}
+
+/// A lossless syntax tree of a whole source file, printing it gives back exactly the code it was parsed from.
+/// Trivia between top level forms is kept as top level elements,
+/// trivia inside of a form belongs to the innermost node it's in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub elements: Vec<CstElement>,
+}
+
+impl Cst {
+    /// The top level forms, skipping trivia
+    pub fn forms(&self) -> Vec<&CstElement> {
+        self.elements.iter().filter(|element| element.is_form()).collect()
     }
 
-    Ok(values)
+    /// Lowers every top level form into the value it's read as, failing at the first error
+    pub fn to_values(&self) -> Result<Vec<Value>, ParseError> {
+        self.forms().into_iter().map(CstElement::to_value).collect()
+    }
+
+    /// Lowers every top level form into the syntax tree used by `parse_recovering`
+    pub fn nodes(&self) -> Vec<Node> {
+        self.elements.iter().filter_map(CstElement::to_node).collect()
+    }
 }
 
-/// Extracts a single token out of a list of strings, which may contain multiple tokens
-fn parse(list: &mut VecDeque<String>) -> ParseResult {
-    let head = match list.pop_front() {
-        Some(string) => string,
-        None => return Err(ParseError::UnreadableSourceCode),
+impl CstElement {
+    pub fn span(&self) -> Span {
+        match *self {
+            CstElement::Node(ref node) => node.span,
+            CstElement::Token(ref token) => token.span,
+        }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        match *self {
+            CstElement::Token(ref token) => token.kind.is_trivia(),
+            CstElement::Node(_) => false,
+        }
+    }
+
+    /// Whether the element is a form of its own, rather than trivia or punctuation such as a delimiter
+    pub fn is_form(&self) -> bool {
+        match *self {
+            CstElement::Token(ref token) => token.kind == TokenKind::Atom,
+            CstElement::Node(_) => true,
+        }
+    }
+
+    pub fn to_value(&self) -> ParseResult {
+        match *self {
+            CstElement::Node(ref node) => node.to_value(),
+            CstElement::Token(ref token) if token.kind == TokenKind::Atom => parse_atom(&token.text),
+            CstElement::Token(_) => Err(ParseError::UnreadableSourceCode),
+        }
+    }
+
+    fn to_node(&self) -> Option<Node> {
+        match *self {
+            CstElement::Node(ref node) => Some(node.to_node()),
+            CstElement::Token(ref token) if token.kind == TokenKind::Atom => {
+                // Atoms that don't parse are always wrapped in an error node, which already stands for them
+                parse_atom(&token.text).ok().map(|value| Node {
+                    kind: NodeKind::Atom(value),
+                    span: token.span,
+                })
+            },
+            CstElement::Token(_) => None,
+        }
+    }
+}
+
+impl CstNode {
+    fn new(kind: CstKind, children: Vec<CstElement>) -> CstNode {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => Span::new(0, 0),
+        };
+
+        CstNode {
+            kind: kind,
+            children: children,
+            span: span,
+        }
+    }
+
+    /// The children that are forms of their own, skipping trivia and delimiters
+    pub fn items(&self) -> Vec<&CstElement> {
+        self.children.iter().filter(|child| child.is_form()).collect()
+    }
+
+    pub fn to_value(&self) -> ParseResult {
+        let items: Vec<Value> = try!(self.items().into_iter().map(CstElement::to_value).collect());
+
+        match self.kind {
+            CstKind::List => Ok(Value::List(items.into())),
+            CstKind::QuotedList => Ok(Value::Quote(Box::new(Value::List(items.into())))),
+            CstKind::Quote => Ok(Value::Quote(Box::new(items.into_iter().next().unwrap_or(Value::Nil)))),
+            CstKind::Bytes => {
+                let list = try!(items.into_iter().next().unwrap_or(Value::Nil).as_list()
+                    .map_err(|_| ParseError::InvalidByteLiteral));
+
+                list.iter().map(byte_lit).collect::<Option<Vec<u8>>>()
+                    .map(Value::Bytes)
+                    .ok_or(ParseError::InvalidByteLiteral)
+            },
+            CstKind::Error(ref error) => Err(error.clone()),
+        }
+    }
+
+    fn to_node(&self) -> Node {
+        let mut children: Vec<Node> = self.children.iter().filter_map(CstElement::to_node).collect();
+
+        let kind = match self.kind {
+            CstKind::List => NodeKind::List(children),
+            CstKind::QuotedList => NodeKind::QuotedList(children),
+            CstKind::Quote => NodeKind::Quote(Box::new(children.remove(0))),
+            CstKind::Bytes => match self.to_value() {
+                Ok(bytes) => NodeKind::Atom(bytes),
+                Err(_) => children.remove(0).kind, // The list inside is an error, which has already been reported
+            },
+            CstKind::Error(ref error) => NodeKind::Error {
+                error: error.clone(),
+                children: children,
+            },
+        };
+
+        Node {
+            kind: kind,
+            span: self.span,
+        }
+    }
+}
+
+fn byte_lit(value: &Value) -> Option<u8> {
+    match *value {
+        Value::Number(n) if n >= 0.0 && n <= 255.0 && n.fract() == 0.0 => Some(n as u8),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for element in &self.elements {
+            try!(write!(fmt, "{}", element));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CstNode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        for child in &self.children {
+            try!(write!(fmt, "{}", child));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CstElement {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CstElement::Node(ref node) => write!(fmt, "{}", node),
+            CstElement::Token(ref token) => write!(fmt, "{}", token.text),
+        }
+    }
+}
+
+/// Parses code into a lossless concrete syntax tree, recovering from errors the same way `parse_recovering` does
+pub fn parse_cst(code: &str) -> (Cst, Vec<Diagnostic>) {
+    let (cst, mut diagnostics) = parse_cst_unsorted(code);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    (cst, diagnostics)
+}
+
+// Diagnostics are in the order they were found in
+fn parse_cst_unsorted(code: &str) -> (Cst, Vec<Diagnostic>) {
+    let mut tokens = tokenize(code);
+    tokens.reverse();
+
+    let mut parser = Parser {
+        tokens: tokens,
+        diagnostics: Vec::new(),
     };
 
-    match &head[..] {
-        LIST_OPEN => {
-            let tokens = try!(parse_list(list, LIST_CLOSE));
-            Ok(Value::List(tokens))
-        },
-        QUOTE_OPEN => {
-            let tokens = try!(parse_list(list, QUOTE_CLOSE));
-            Ok(Value::Quote(box Value::List(tokens)))
-        },
-        LIST_CLOSE | QUOTE_CLOSE => Err(ParseError::InvalidListDelimitter),
-        r"'" => {
-            let token = try!(parse(list));
-            Ok(Value::Quote(box token))
-        },
-        atom => parse_atom(atom.to_string()),
-    }
-}
-
-fn parse_atom(atom: String) -> ParseResult {
-    if atom.starts_with("#") {
+    let mut elements = Vec::new();
+    loop {
+        parser.trivia(&mut elements);
+
+        if parser.tokens.is_empty() {
+            break;
+        }
+
+        elements.push(parser.element());
+    }
+
+    (Cst { elements: elements }, parser.diagnostics)
+}
+
+struct Parser {
+    tokens: Vec<CstToken>, // In reverse, so the next one can be popped off the end
+
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Parser {
+    fn bump(&mut self) -> CstToken {
+        self.tokens.pop().unwrap()
+    }
+
+    fn peek(&self) -> Option<&CstToken> {
+        self.tokens.last()
+    }
+
+    // The next token that isn't trivia, without consuming anything
+    fn peek_form(&self) -> Option<&CstToken> {
+        self.tokens.iter().rev().find(|token| !token.kind.is_trivia())
+    }
+
+    fn trivia(&mut self, into: &mut Vec<CstElement>) {
+        while self.peek().map_or(false, |token| token.kind.is_trivia()) {
+            into.push(CstElement::Token(self.bump()));
+        }
+    }
+
+    fn error(&mut self, error: ParseError, children: Vec<CstElement>) -> CstElement {
+        let node = CstNode::new(CstKind::Error(error.clone()), children);
+
+        self.diagnostics.push(Diagnostic {
+            error: error,
+            span: node.span,
+        });
+
+        CstElement::Node(node)
+    }
+
+    /// Parses a single element, the next token must not be trivia
+    fn element(&mut self) -> CstElement {
+        let token = self.bump();
+
+        match token.kind {
+            TokenKind::Open => self.list(token),
+            TokenKind::BytesPrefix => {
+                if self.peek_form().map(|open| &open.text[..]) != Some(LIST_OPEN) {
+                    return self.error(ParseError::InvalidByteLiteral, vec![CstElement::Token(token)]);
+                }
+
+                let mut children = vec![CstElement::Token(token)];
+                self.trivia(&mut children);
+
+                let open = self.bump();
+                let list = self.list(open);
+
+                let valid = match list {
+                    CstElement::Node(CstNode { kind: CstKind::List, .. }) => {
+                        list.to_value().ok()
+                            .and_then(|value| value.as_list().ok())
+                            .map_or(false, |items| items.iter().all(|item| byte_lit(item).is_some()))
+                    },
+                    _ => true, // The list is an error itself, which has already been reported
+                };
+
+                children.push(list);
+
+                match valid {
+                    true => CstElement::Node(CstNode::new(CstKind::Bytes, children)),
+                    false => self.error(ParseError::InvalidByteLiteral, children),
+                }
+            },
+            TokenKind::Close => self.error(ParseError::InvalidListDelimitter, vec![CstElement::Token(token)]),
+            TokenKind::Quote => {
+                if self.peek_form().is_none() {
+                    return self.error(ParseError::UnreadableSourceCode, vec![CstElement::Token(token)]);
+                }
+
+                let mut children = vec![CstElement::Token(token)];
+                self.trivia(&mut children);
+                children.push(self.element());
+
+                CstElement::Node(CstNode::new(CstKind::Quote, children))
+            },
+            // Strings and literals starting with # can be malformed, and symbols can't be interned once there are too many
+            _ => match parse_atom(&token.text) {
+                Ok(_) => CstElement::Token(token),
+                Err(err) => self.error(err, vec![CstElement::Token(token)]),
+            },
+        }
+    }
+
+    fn list(&mut self, open: CstToken) -> CstElement {
+        let (close, kind) = match &open.text[..] {
+            LIST_OPEN => (LIST_CLOSE, CstKind::List),
+            _ => (QUOTE_CLOSE, CstKind::QuotedList),
+        };
+
+        let mut children = vec![CstElement::Token(open)];
+
+        loop {
+            self.trivia(&mut children);
+
+            let closes = match self.peek() {
+                None => return self.error(ParseError::UnclosedList, children),
+                Some(token) => token.kind == TokenKind::Close && token.text == close,
+            };
+
+            if closes {
+                children.push(CstElement::Token(self.bump()));
+                return CstElement::Node(CstNode::new(kind, children));
+            }
+
+            // Any other closing delimiter is reported as a stray one, and the list carries on
+            children.push(self.element());
+        }
+    }
+}
+
+fn parse_atom(atom: &str) -> ParseResult {
+    if atom.starts_with("\"") {
+        string_lit(atom).map(Value::String)
+    } else if atom.starts_with("#\\") {
+        char_lit(&atom[2..]).map(Value::Char).ok_or(ParseError::InvalidCharLiteral(atom.to_string()))
+    } else if atom.starts_with("#") {
         let value = usize::from_str_radix(&atom[1..], 16).map_err(|_| ParseError::InvalidHexLiteral)?;
         Ok(Value::Number(value as f32))
     } else if let Ok(n) = atom.parse() {
         Ok(Value::Number(n))
-    } else if let Some(lit) = string_lit(&atom[..]) {
-        Ok(Value::String(lit))
     } else {
-        Ok(Value::Symbol(atom.to_string()))
+        Symbol::try_intern(atom).map(Value::Symbol).ok_or(ParseError::TooManySymbols)
     }
 }
 
-fn parse_list(list: &mut VecDeque<String>, delimit: &str) -> Result<Vec<Value>, ParseError> {
-    let mut tokens = Vec::new();
-
-    while let Some(item_str) = list.pop_front() {
-        if &item_str[..] == delimit {
-            return Ok(tokens);
-        }
+fn char_lit(lit: &str) -> Option<char> {
+    let mut chars = lit.chars();
 
-        // If not the end of the list, push the item back and continue to process the list
-        list.push_front(item_str);
-        tokens.push(try!(parse(list)));
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(ch);
     }
 
-    Err(ParseError::UnclosedList)
-}
-
-fn string_lit(slice: &str) -> Option<String> {
-    let len = slice.len();
-    let end = len - 1;
+    if lit.starts_with("u{") && lit.ends_with("}") {
+        let code = match u32::from_str_radix(&lit[2 .. lit.len() - 1], 16) {
+            Ok(code) => code,
+            Err(_) => return None,
+        };
 
-    if &slice[0..1] == "\"" && &slice[end .. len] == "\"" {
-        let lit = &slice[1..end];
+        return char::from_u32(code);
+    }
 
-        let lit_str = lit.to_string()
-            .replace(r"\n", "\n")
-            .replace(r"\t", "\t")
-            .replace(r"\r", "\r");
+    CHAR_NAMES.iter()
+        .find(|&&(name, _)| name == lit)
+        .map(|&(_, ch)| ch)
+}
 
-        return Some(lit_str);
+fn string_lit(slice: &str) -> Result<String, ParseError> {
+    let mut string = String::with_capacity(slice.len());
+    let mut chars = slice.chars().skip(1);
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            // Escaped quotes are skipped over below, so this is the closing one
+            '"' => return Ok(string),
+            '\\' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some('\\') => string.push('\\'),
+                Some('"') => string.push('"'),
+                // Anything else is left as it is, so regexes like "\d+" don't need their backslashes doubled
+                Some(other) => {
+                    string.push('\\');
+                    string.push(other);
+                },
+                None => break,
+            },
+            _ => string.push(ch),
+        }
     }
 
-    return None;
+    Err(ParseError::UnterminatedString)
 }
@@ -0,0 +1,236 @@
+//! Destructuring, binding the parts of a value to names.
+//!
+//! A pattern is either a name, which binds the whole value, or a list of patterns, which matches a list
+//! with an item for each of them. A list pattern can end in `. name` (or `&rest name`) to bind whatever
+//! items are left over, so `(a (b c) . more)` matches `(1 (2 3) 4 5)` with `more` bound to `(4 5)`.
+//!
+//! There's no separate vector or map type, lists are used for both, with maps written as alists of
+//! `(key value)` pairs. A pattern starting with `&keys` looks values up by key, so `(&keys name (n "count"))`
+//! binds `name` to the value with the key `name` and `n` to the value with the key `"count"`, or nil if
+//! there isn't one.
+//!
+//! Patterns work in `let`, as lambda parameters and in `def`:
+//! ```lisp
+//! (let {((x y) point)} {+ x y})
+//! (def {length-of (x y)} {sqrt (+ (* x x) (* y y))})
+//! (def {(first . others)} {1 2 3})
+//! (let {((&keys x y) {(y 2) (x 1)})} {list x y})
+//! ```
+
+use std::fmt;
+
+use eval::FuncError;
+use symbol::Symbol;
+use value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Name(Symbol),
+    List {
+        items: Vec<Pattern>,
+        /// Bound to a list of the items after the ones matched by `items`
+        rest: Option<Symbol>,
+    },
+    /// Each name is bound to the value paired with its key in an alist
+    Keys(Vec<(Symbol, Value)>),
+}
+
+const KEYS_MARKER: &'static str = "&keys";
+
+fn is_rest_marker(sym: Symbol) -> bool {
+    sym.as_str() == "." || sym.as_str() == "&rest"
+}
+
+impl Pattern {
+    pub fn parse(value: &Value) -> Result<Pattern, FuncError> {
+        let pattern = try!(Pattern::parse_inner(value));
+
+        // A name can only be bound once, except for _ which is for parts you don't care about
+        let names = pattern.names();
+        for (index, name) in names.iter().enumerate() {
+            if name.as_str() != "_" && names[..index].contains(name) {
+                return Err(FuncError::InvalidPattern(value.clone()));
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    fn parse_inner(value: &Value) -> Result<Pattern, FuncError> {
+        match value {
+            &Value::Symbol(sym) if !sym.as_str().starts_with('&') && sym.as_str() != "." => Ok(Pattern::Name(sym)),
+            &Value::List(ref list) if list.head() == Some(&Value::Symbol(Symbol::intern(KEYS_MARKER))) => {
+                let mut keys = Vec::new();
+
+                // Each key is a name, or (name key) for keys that aren't the same as the name
+                for item in list.iter().skip(1) {
+                    keys.push(match item {
+                        &Value::Symbol(name) if !name.as_str().starts_with('&') && name.as_str() != "." => (name, item.clone()),
+                        &Value::List(ref pair) if pair.len() == 2 => match pair.head() {
+                            Some(&Value::Symbol(name)) if !name.as_str().starts_with('&') && name.as_str() != "." => {
+                                (name, pair.iter().nth(1).unwrap().clone())
+                            },
+                            _ => return Err(FuncError::InvalidPattern(value.clone())),
+                        },
+                        _ => return Err(FuncError::InvalidPattern(value.clone())),
+                    });
+                }
+
+                Ok(Pattern::Keys(keys))
+            },
+            &Value::List(ref list) => {
+                let values = list.to_vec();
+                let mut items = Vec::new();
+                let mut rest = None;
+
+                for (index, item) in values.iter().enumerate() {
+                    match item {
+                        &Value::Symbol(sym) if is_rest_marker(sym) => {
+                            // The marker has to be followed by exactly one name
+                            let after = &values[index + 1..];
+                            rest = match after.first() {
+                                Some(&Value::Symbol(name)) if after.len() == 1 && !is_rest_marker(name) => Some(name),
+                                _ => return Err(FuncError::InvalidPattern(value.clone())),
+                            };
+
+                            break;
+                        },
+                        _ => items.push(try!(Pattern::parse_inner(item))),
+                    }
+                }
+
+                Ok(Pattern::List {
+                    items: items,
+                    rest: rest,
+                })
+            },
+            _ => Err(FuncError::InvalidPattern(value.clone())),
+        }
+    }
+
+    pub fn is_name(&self) -> bool {
+        match self {
+            &Pattern::Name(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Every name the pattern binds, in the order they're bound
+    pub fn names(&self) -> Vec<Symbol> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+
+        names
+    }
+
+    fn collect_names(&self, names: &mut Vec<Symbol>) {
+        match self {
+            &Pattern::Name(name) => names.push(name),
+            &Pattern::List { ref items, rest } => {
+                for item in items {
+                    item.collect_names(names);
+                }
+
+                names.extend(rest);
+            },
+            &Pattern::Keys(ref keys) => names.extend(keys.iter().map(|&(name, _)| name)),
+        }
+    }
+
+    /// Matches the value against the pattern, giving what each name is bound to in the same order as `names`
+    pub fn destructure(&self, value: Value) -> Result<Vec<(Symbol, Value)>, FuncError> {
+        let mut bindings = Vec::new();
+        try!(self.destructure_into(value, &mut bindings));
+
+        Ok(bindings)
+    }
+
+    fn destructure_into(&self, value: Value, bindings: &mut Vec<(Symbol, Value)>) -> Result<(), FuncError> {
+        let (items, rest) = match self {
+            &Pattern::Name(name) => {
+                bindings.push((name, value));
+                return Ok(());
+            },
+            &Pattern::List { ref items, rest } => (items, rest),
+            &Pattern::Keys(ref keys) => return self.look_up(keys, value, bindings),
+        };
+
+        let matches = match value {
+            Value::List(ref list) if rest.is_some() => list.len() >= items.len(),
+            Value::List(ref list) => list.len() == items.len(),
+            _ => false,
+        };
+
+        if !matches {
+            return Err(FuncError::PatternMismatch {
+                pattern: self.to_string(),
+                value: value,
+            });
+        }
+
+        let mut values = try!(value.as_list()).into_iter();
+        for item in items {
+            try!(item.destructure_into(values.next().unwrap(), bindings));
+        }
+
+        if let Some(rest) = rest {
+            bindings.push((rest, Value::List(values.collect())));
+        }
+
+        Ok(())
+    }
+
+    fn look_up(&self, keys: &[(Symbol, Value)], value: Value, bindings: &mut Vec<(Symbol, Value)>) -> Result<(), FuncError> {
+        let pairs = match value {
+            Value::List(ref list) => list.iter()
+                .map(|pair| match pair {
+                    &Value::List(ref pair) if pair.len() == 2 => Some((pair.head().unwrap(), pair.iter().nth(1).unwrap())),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+
+        let pairs = match pairs {
+            Some(pairs) => pairs,
+            None => return Err(FuncError::PatternMismatch {
+                pattern: self.to_string(),
+                value: value.clone(),
+            }),
+        };
+
+        // The first pair with the key wins
+        for &(name, ref key) in keys {
+            let found = pairs.iter().find(|&&(pair_key, _)| pair_key == key).map(|&(_, value)| value.clone());
+            bindings.push((name, found.unwrap_or(Value::Nil)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the pattern the way it's written in code, e.g. `(a (b c) . more)`
+impl fmt::Display for Pattern {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Pattern::Name(name) => write!(fmt, "{}", name),
+            &Pattern::List { ref items, rest } => {
+                let mut words: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                if let Some(rest) = rest {
+                    words.push(format!(". {}", rest));
+                }
+
+                write!(fmt, "({})", words.join(" "))
+            },
+            &Pattern::Keys(ref keys) => {
+                let mut words = vec![KEYS_MARKER.to_string()];
+                words.extend(keys.iter().map(|&(name, ref key)| match key {
+                    &Value::Symbol(sym) if sym == name => name.to_string(),
+                    key => format!("({} {:?})", name, key),
+                }));
+
+                write!(fmt, "({})", words.join(" "))
+            },
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! A width aware pretty-printer, in the style of Wadler's "A prettier printer".
+//!
+//! Layouts are described as a `Doc`, where every `Group` is printed on a single line if it fits in the
+//! remaining width, and otherwise has each of its `Line`s broken onto a new line.
+//! `format` uses the same layout rules to format source code, keeping its comments.
+
+use std::fmt::Write;
+
+use value::Value;
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    /// A space if the enclosing group fits on one line, otherwise a newline
+    Line,
+    /// Always a newline, which also forces every enclosing group to break
+    HardLine,
+    Concat(Vec<Doc>),
+    /// Indents lines broken inside of it by this many more columns
+    Nest(usize, Box<Doc>),
+    /// Indents lines broken inside of it to the column it starts at
+    Align(Box<Doc>),
+    Group(Box<Doc>),
+}
+
+pub fn text<S: Into<String>>(text: S) -> Doc {
+    Doc::Text(text.into())
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+pub fn nest(indent: usize, doc: Doc) -> Doc {
+    Doc::Nest(indent, Box::new(doc))
+}
+
+pub fn align(doc: Doc) -> Doc {
+    Doc::Align(Box::new(doc))
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+/// One item of a list, along with the comments around it
+pub struct Entry {
+    /// Comments on the lines before the item
+    pub leading: Vec<String>,
+    /// None for comments that come after the last item of a list
+    pub doc: Option<Doc>,
+    /// A comment on the same line, after the item
+    pub trailing: Option<String>,
+    /// The name of the item if it's a symbol, which decides how the list it heads is laid out
+    pub symbol: Option<String>,
+}
+
+impl Entry {
+    pub fn new(doc: Doc) -> Entry {
+        Entry {
+            leading: Vec::new(),
+            doc: Some(doc),
+            trailing: None,
+            symbol: None,
+        }
+    }
+
+    fn to_doc(&self) -> Doc {
+        let mut docs = Vec::new();
+
+        for comment in &self.leading {
+            docs.push(text(comment.clone()));
+            docs.push(Doc::HardLine);
+        }
+
+        if let Some(ref doc) = self.doc {
+            docs.push(doc.clone());
+        } else {
+            docs.pop(); // A comment before the closing delimiter, which is followed by a line break anyway
+        }
+
+        if let Some(ref comment) = self.trailing {
+            docs.push(text(" "));
+            docs.push(text(comment.clone()));
+        }
+
+        concat(docs)
+    }
+
+    fn is_plain_atom(&self) -> bool {
+        match self.doc {
+            Some(Doc::Text(_)) => self.leading.is_empty() && self.trailing.is_none(),
+            _ => false,
+        }
+    }
+
+    // Nothing can follow a comment on the same line
+    fn ends_in_comment(&self) -> bool {
+        self.trailing.is_some() || self.doc.is_none()
+    }
+}
+
+/// How many arguments of a form stay on the same line as its name, with the rest indented under it.
+/// Anything else that starts with a symbol is laid out like a call, with its arguments aligned.
+fn distinguished_args(name: &str) -> Option<usize> {
+    match name {
+        "def" | "\\" | "let" | "if" => Some(1),
+        "seq" => Some(0),
+        _ => None,
+    }
+}
+
+// Joins entries with `sep`, other than after a comment, which has to be followed by a line break
+fn join(entries: &[Entry], sep: Doc) -> Doc {
+    let mut docs = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            docs.push(match entries[index - 1].ends_in_comment() {
+                true => Doc::HardLine,
+                false => sep.clone(),
+            });
+        }
+
+        docs.push(entry.to_doc());
+    }
+
+    concat(docs)
+}
+
+// Packs as many entries onto each line as will fit, each line break is only taken if the next entry wouldn't fit
+fn fill(entries: &[Entry]) -> Doc {
+    let mut docs = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        match index {
+            0 => docs.push(entry.to_doc()),
+            _ => docs.push(group(concat(vec![Doc::Line, entry.to_doc()]))),
+        }
+    }
+
+    concat(docs)
+}
+
+/// Lays out a list of the given entries, between the given delimiters
+pub fn list(open: &str, close: &str, entries: Vec<Entry>) -> Doc {
+    let close_sep = match entries.last() {
+        Some(entry) if entry.ends_in_comment() => Doc::HardLine,
+        _ => Doc::Nil,
+    };
+
+    let head_name = entries.first().and_then(|entry| entry.symbol.clone());
+
+    let body = match head_name {
+        Some(ref name) if entries.len() > 1 => {
+            match distinguished_args(name) {
+                Some(count) => {
+                    let count = ::std::cmp::min(count + 1, entries.len());
+                    let (first_line, rest) = entries.split_at(count);
+
+                    let mut docs = vec![join(first_line, text(" "))];
+                    if !rest.is_empty() {
+                        let sep = match first_line.last().unwrap().ends_in_comment() {
+                            true => Doc::HardLine,
+                            false => Doc::Line,
+                        };
+
+                        docs.push(nest(2, concat(vec![sep, join(rest, Doc::Line)])));
+                    }
+
+                    concat(docs)
+                },
+                None => {
+                    let sep = match entries[0].ends_in_comment() {
+                        true => nest(2, Doc::HardLine),
+                        false => text(" "),
+                    };
+
+                    concat(vec![entries[0].to_doc(), sep, align(join(&entries[1..], Doc::Line))])
+                },
+            }
+        },
+        _ if entries.iter().all(Entry::is_plain_atom) => align(fill(&entries)),
+        _ => align(join(&entries, Doc::Line)),
+    };
+
+    group(concat(vec![text(open), body, close_sep, text(close)]))
+}
+
+/// Lays out a value the same way it would be written in source
+pub fn value_doc(value: &Value) -> Doc {
+    match value {
+        &Value::List(ref items) => list("(", ")", items.iter().map(value_entry).collect()),
+        &Value::Quote(ref inner) => match **inner {
+            Value::List(ref items) => list("{", "}", items.iter().map(value_entry).collect()),
+            ref inner => concat(vec![text("'"), value_doc(inner)]),
+        },
+        other => text(format!("{:?}", other)),
+    }
+}
+
+fn value_entry(value: &Value) -> Entry {
+    let mut entry = Entry::new(value_doc(value));
+
+    if let &Value::Symbol(sym) = value {
+        entry.symbol = Some(sym.as_str().to_string());
+    }
+
+    entry
+}
+
+/// Pretty prints a value, breaking it over as many lines as needed to fit within `width` columns
+pub fn pretty(value: &Value, width: usize) -> String {
+    render(&value_doc(value), width)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut col = 0;
+
+    // Docs still to be printed, the next one last
+    let mut stack: Vec<(usize, Mode, &Doc)> = vec![(0, Mode::Break, doc)];
+
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match *doc {
+            Doc::Nil => (),
+            Doc::Text(ref string) => {
+                out.push_str(string);
+
+                col = match string.rfind('\n') {
+                    Some(newline) => string[newline + 1..].chars().count(),
+                    None => col + string.chars().count(),
+                };
+            },
+            Doc::Line if mode == Mode::Flat => {
+                out.push(' ');
+                col += 1;
+            },
+            Doc::Line | Doc::HardLine => {
+                // Indentation is trimmed off of lines that nothing else was printed on
+                while out.ends_with(' ') {
+                    out.pop();
+                }
+
+                out.push('\n');
+                write!(out, "{:1$}", "", indent).unwrap();
+                col = indent;
+            },
+            Doc::Concat(ref docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((indent, mode, doc));
+                }
+            },
+            Doc::Nest(extra, ref doc) => stack.push((indent + extra, mode, doc)),
+            Doc::Align(ref doc) => stack.push((col, mode, doc)),
+            Doc::Group(ref doc) => {
+                let mode = match mode == Mode::Flat || fits(width as isize - col as isize, doc, &stack) {
+                    true => Mode::Flat,
+                    false => Mode::Break,
+                };
+
+                stack.push((indent, mode, doc));
+            },
+        }
+    }
+
+    out
+}
+
+// Whether the group, printed flat, fits in the remaining width along with everything after it up to the next line break
+fn fits(mut remaining: isize, group: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut pending: Vec<(Mode, &Doc)> = vec![(Mode::Flat, group)];
+    let mut rest = rest.iter().rev();
+
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+
+        let (mode, doc) = match pending.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+
+        match *doc {
+            Doc::Nil => (),
+            Doc::Text(ref string) if string.contains('\n') => return false,
+            Doc::Text(ref string) => remaining -= string.chars().count() as isize,
+            Doc::Line if mode == Mode::Flat => remaining -= 1,
+            Doc::Line => return true,
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Concat(ref docs) => pending.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            Doc::Nest(_, ref doc) | Doc::Align(ref doc) | Doc::Group(ref doc) => pending.push((mode, doc)),
+        }
+    }
+}
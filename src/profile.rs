@@ -0,0 +1,298 @@
+//! Tracing calls to functions, and profiling where the time goes.
+//!
+//! `(trace 'name)` logs each call to a function and what it returns, indented by how many traced calls it's inside of.
+//! `Lisp::start_profiling` times every call until `Lisp::stop_profiling`, which gives a `Profile` of
+//! how many times each function was called, and the time spent in it and in what it called.
+//! Like hooks, both only work with the tree walker, so code is evaluated by it while either is on.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use eval::FuncError;
+use symbol::Symbol;
+use value::Value;
+
+/// Identifies a function by where it lives, so the same function can be recognized under any name
+pub fn identity(func: &Value) -> usize {
+    match func {
+        &Value::Lambda(ref lambda) => &**lambda as *const _ as usize,
+        &Value::HardFunc(ref func) => *func.func as usize,
+        _ => 0,
+    }
+}
+
+pub struct Tracer {
+    traced: HashSet<Symbol>,
+    depth: usize,
+    output: Box<Write>,
+}
+
+impl Tracer {
+    pub fn new() -> Tracer {
+        Tracer {
+            traced: HashSet::new(),
+            depth: 0,
+            output: Box::new(io::stderr()),
+        }
+    }
+
+    pub fn set_output(&mut self, output: Box<Write>) {
+        self.output = output;
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.traced.is_empty()
+    }
+
+    pub fn trace(&mut self, name: Symbol) {
+        self.traced.insert(name);
+    }
+
+    pub fn untrace(&mut self, name: Symbol) -> bool {
+        self.traced.remove(&name)
+    }
+
+    pub fn untrace_all(&mut self) {
+        self.traced.clear();
+    }
+
+    /// The traced names, sorted
+    pub fn traced(&self) -> Vec<Symbol> {
+        let mut traced: Vec<Symbol> = self.traced.iter().cloned().collect();
+        traced.sort_by_key(|name| name.as_str());
+
+        traced
+    }
+
+    /// Which of the traced names, if any, is the function being called.
+    /// `lookup` finds what a name is bound to where the function is called.
+    pub fn traced_name<F>(&self, head: &Value, func: &Value, lookup: F) -> Option<Symbol>
+        where F: Fn(Symbol) -> Option<Value>
+    {
+        if let &Value::Symbol(name) = head {
+            if self.traced.contains(&name) {
+                return Some(name);
+            }
+        }
+
+        // Called through a value rather than by name, e.g. given to map
+        let id = identity(func);
+        self.traced.iter()
+            .cloned()
+            .find(|&name| lookup(name).map_or(false, |value| identity(&value) == id))
+    }
+
+    pub fn enter(&mut self, name: Symbol, args: &[Value]) {
+        let mut call = format!("({}", name);
+        for arg in args {
+            let _ = write!(call, " {:?}", arg);
+        }
+        call.push(')');
+
+        self.line(&call);
+        self.depth += 1;
+    }
+
+    pub fn exit(&mut self, result: &Result<Value, FuncError>) {
+        self.depth = self.depth.saturating_sub(1);
+
+        match result {
+            &Ok(ref value) => self.line(&format!("=> {:?}", value)),
+            &Err(ref err) => self.line(&format!("!! {:?}", err)),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        let indent = "| ".repeat(self.depth);
+        let _ = writeln!(self.output, "{}{}", indent, text);
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    total: Duration,
+    self_time: Duration,
+    active: usize, // How many calls to it are running, so recursive calls aren't counted twice in the total
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    children: Duration, // Time spent in calls made from this one
+}
+
+pub struct Profiler {
+    stack: Vec<Frame>,
+    functions: HashMap<String, Stats>,
+    stacks: HashMap<String, Duration>, // Self time by the stack of calls leading to it, joined by ;
+    names: HashMap<usize, Option<Symbol>>, // Names of functions called through values, by identity
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            stack: Vec::new(),
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// The name to give a function in the profile, `find` looks for a name it's bound to if it wasn't called by name
+    pub fn name<F>(&mut self, head: &Value, func: &Value, find: F) -> String
+        where F: FnOnce() -> Option<Symbol>
+    {
+        if let &Value::Symbol(name) = head {
+            return name.to_string();
+        }
+
+        let name = *self.names.entry(identity(func)).or_insert_with(find);
+        match (name, func) {
+            (Some(name), _) => name.to_string(),
+            (None, &Value::HardFunc(_)) => "<builtin>".to_string(),
+            (None, _) => "<lambda>".to_string(),
+        }
+    }
+
+    pub fn enter(&mut self, name: String) {
+        {
+            let stats = self.functions.entry(name.clone()).or_insert_with(Stats::default);
+            stats.calls += 1;
+            stats.active += 1;
+        }
+
+        self.stack.push(Frame {
+            name: name,
+            start: Instant::now(),
+            children: Duration::new(0, 0),
+        });
+    }
+
+    pub fn exit(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let elapsed = frame.start.elapsed();
+        let self_time = elapsed.checked_sub(frame.children).unwrap_or(Duration::new(0, 0));
+
+        let mut path: Vec<&str> = self.stack.iter().map(|frame| frame.name.as_str()).collect();
+        path.push(&frame.name);
+        *self.stacks.entry(path.join(";")).or_insert(Duration::new(0, 0)) += self_time;
+
+        if let Some(stats) = self.functions.get_mut(&frame.name) {
+            stats.self_time += self_time;
+            stats.active -= 1;
+
+            if stats.active == 0 {
+                stats.total += elapsed;
+            }
+        }
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+    }
+
+    /// What's been recorded so far, calls that haven't returned yet aren't included
+    pub fn profile(&self) -> Profile {
+        let functions = self.functions.iter()
+            .filter(|&(_, stats)| stats.calls > stats.active as u64)
+            .map(|(name, stats)| FunctionProfile {
+                name: name.clone(),
+                calls: stats.calls - stats.active as u64,
+                total: stats.total,
+                self_time: stats.self_time,
+            })
+            .collect();
+
+        let mut stacks: Vec<(String, Duration)> = self.stacks.iter()
+            .map(|(stack, &time)| (stack.clone(), time))
+            .collect();
+        stacks.sort();
+
+        let mut profile = Profile {
+            functions: functions,
+            stacks: stacks,
+        };
+        profile.sort_by(SortBy::SelfTime);
+
+        profile
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Calls,
+    Total,
+    SelfTime,
+    Name,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    /// Time from being called to returning, including whatever it called
+    pub total: Duration,
+    /// Time spent in the function itself, leaving out the functions it called
+    pub self_time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// Sorted by self time, most first, unless sorted otherwise
+    pub functions: Vec<FunctionProfile>,
+    /// Self time for each stack of calls, as function names joined by ;
+    pub stacks: Vec<(String, Duration)>,
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs() as f64 * 1000.0 + time.subsec_nanos() as f64 / 1_000_000.0
+}
+
+impl Profile {
+    /// Sorts the functions, the largest first (or alphabetically by name)
+    pub fn sort_by(&mut self, order: SortBy) {
+        self.functions.sort_by(|a, b| {
+            let ordering = match order {
+                SortBy::Calls => b.calls.cmp(&a.calls),
+                SortBy::Total => b.total.cmp(&a.total),
+                SortBy::SelfTime => b.self_time.cmp(&a.self_time),
+                SortBy::Name => a.name.cmp(&b.name),
+            };
+
+            ordering.then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    /// A table of the functions, in their current order
+    pub fn table(&self) -> String {
+        let mut table = format!("{:>10} {:>12} {:>12}  {}\n", "calls", "total ms", "self ms", "function");
+
+        for function in &self.functions {
+            let _ = writeln!(table, "{:>10} {:>12.3} {:>12.3}  {}",
+                             function.calls, millis(function.total), millis(function.self_time), function.name);
+        }
+
+        table
+    }
+
+    /// The stacks in the folded format flame graph tools take, a line of `outer;inner;innermost microseconds` for each
+    pub fn folded(&self) -> String {
+        let mut folded = String::new();
+
+        for &(ref stack, time) in &self.stacks {
+            let micros = time.as_secs() * 1_000_000 + time.subsec_nanos() as u64 / 1000;
+            if micros > 0 {
+                let _ = writeln!(folded, "{} {}", stack, micros);
+            }
+        }
+
+        folded
+    }
+}
@@ -0,0 +1,297 @@
+//! Incremental reading of source code, for input that arrives a piece at a time (a REPL, a socket, a large file).
+//!
+//! The `Reader` only works out where each top level form ends, the form itself is then parsed by `parse::parse_str`,
+//! so code read this way always means the same thing as code parsed all at once.
+
+use std::io::BufRead;
+
+use parse::{self, ParseError};
+use eval::FuncError;
+use value::Value;
+
+/// What the reader found when asked for the next form
+#[derive(Debug, Clone, PartialEq)]
+pub enum Next {
+    Form(Value),
+    /// Everything buffered so far is either whitespace, comments, or the start of a form that hasn't been closed yet
+    NeedMoreInput,
+}
+
+/// Buffers source code as it's fed in, and hands out each top level form as soon as it's complete.
+///
+/// ```ignore
+/// let mut reader = Reader::new();
+/// reader.feed("(+ 1");
+/// reader.next_form(); // Ok(Next::NeedMoreInput)
+///
+/// reader.feed(" 2)");
+/// reader.next_form(); // Ok(Next::Form((+ 1 2)))
+/// ```
+pub struct Reader {
+    buffer: String,
+    pos: usize, // Everything before this has already been read
+    finished: bool,
+}
+
+impl Reader {
+    pub fn new() -> Reader {
+        Reader {
+            buffer: String::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    pub fn feed(&mut self, input: &str) {
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+
+        self.buffer.push_str(input);
+    }
+
+    /// Marks the end of the input.
+    /// A symbol or number at the very end of the input is only complete once it's known nothing else follows it,
+    /// and anything left unclosed becomes an error rather than a request for more input.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Throws away anything buffered, such as the rest of a line that had an error on it
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.pos = 0;
+    }
+
+    /// Whether nothing but whitespace and comments are waiting to be read
+    pub fn is_empty(&self) -> bool {
+        let rest = &self.buffer[self.pos..];
+        skip_trivia(rest, 0) == rest.len()
+    }
+
+    /// Reads the next complete form.
+    /// Malformed forms are skipped over once their error has been returned, so reading can carry on afterwards.
+    pub fn next_form(&mut self) -> Result<Next, ParseError> {
+        let (start, end) = {
+            let rest = &self.buffer[self.pos..];
+
+            match scan(rest, self.finished) {
+                Scan::Nothing(end) => {
+                    self.pos += end;
+                    return Ok(Next::NeedMoreInput);
+                },
+                Scan::Incomplete(_) if !self.finished => return Ok(Next::NeedMoreInput),
+                Scan::Incomplete(err) => {
+                    self.pos = self.buffer.len();
+                    return Err(err);
+                },
+                Scan::Form(start, end) => (self.pos + start, self.pos + end),
+            }
+        };
+
+        self.pos = end;
+
+        let mut values = try!(parse::parse_str(&self.buffer[start..end]));
+        match values.len() {
+            0 => Err(ParseError::UnreadableSourceCode),
+            _ => Ok(Next::Form(values.remove(0))),
+        }
+    }
+
+    /// Reads forms lazily out of `input`, a line at a time
+    pub fn forms<R: BufRead>(input: R) -> Forms<R> {
+        Forms {
+            input: input,
+            reader: Reader::new(),
+        }
+    }
+}
+
+/// An iterator over the forms in a `BufRead`, created by `Reader::forms`
+pub struct Forms<R> {
+    input: R,
+    reader: Reader,
+}
+
+impl<R: BufRead> Iterator for Forms<R> {
+    type Item = Result<Value, FuncError>;
+
+    fn next(&mut self) -> Option<Result<Value, FuncError>> {
+        loop {
+            match self.reader.next_form() {
+                Ok(Next::Form(form)) => return Some(Ok(form)),
+                Ok(Next::NeedMoreInput) if self.reader.finished => return None,
+                Ok(Next::NeedMoreInput) => (),
+                Err(err) => return Some(Err(FuncError::ParsingErr(err))),
+            }
+
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) => self.reader.finish(),
+                Ok(_) => self.reader.feed(&line),
+                Err(err) => return Some(Err(FuncError::IoError(err))),
+            }
+        }
+    }
+}
+
+enum Scan {
+    /// Only whitespace and comments, up to the given offset
+    Nothing(usize),
+    /// The start and end of the next form
+    Form(usize, usize),
+    /// The next form hasn't been closed, this is the error to give if no more input is coming
+    Incomplete(ParseError),
+}
+
+fn scan(text: &str, finished: bool) -> Scan {
+    let start = skip_trivia(text, 0);
+    if start == text.len() {
+        // An unterminated comment could still be continued by the next piece of input, so it isn't consumed yet
+        return Scan::Nothing(match finished {
+            true => start,
+            false => text[..start].rfind('\n').map_or(0, |pos| pos + 1),
+        });
+    }
+
+    let bytes = text.as_bytes();
+    let mut pos = start;
+    while bytes[pos] == b'\'' {
+        pos = skip_trivia(text, pos + 1);
+
+        if pos == text.len() {
+            return Scan::Incomplete(ParseError::UnreadableSourceCode);
+        }
+    }
+
+    let end = match bytes[pos] {
+        b'(' | b'{' => scan_list(text, pos),
+        b'"' => scan_string(text, pos),
+        _ if text[pos..].starts_with("#u8(") => scan_list(text, pos + 3),
+        _ => scan_atom(text, pos, finished),
+    };
+
+    match end {
+        Ok(end) => Scan::Form(start, end),
+        Err(err) => Scan::Incomplete(err),
+    }
+}
+
+// Positions are always kept on character boundaries, and whitespace is whatever char::is_whitespace says it is,
+// the same as the parser, rather than looking at single bytes of a multibyte character
+fn char_at(text: &str, pos: usize) -> char {
+    text[pos..].chars().next().unwrap()
+}
+
+fn skip_trivia(text: &str, mut pos: usize) -> usize {
+    while pos < text.len() {
+        match char_at(text, pos) {
+            ';' => {
+                pos = text[pos..].find('\n').map_or(text.len(), |newline| pos + newline);
+            },
+            ch if ch.is_whitespace() => pos += ch.len_utf8(),
+            _ => break,
+        }
+    }
+
+    pos
+}
+
+fn is_delimiter(ch: char) -> bool {
+    match ch {
+        '(' | ')' | '{' | '}' | '[' | ']' | '"' | ';' | '\'' => true,
+        _ => ch.is_whitespace(),
+    }
+}
+
+// Strings end at the next quote that isn't escaped
+fn scan_string(text: &str, pos: usize) -> Result<usize, ParseError> {
+    let mut chars = text[pos + 1..].char_indices();
+
+    while let Some((offset, ch)) = chars.next() {
+        match ch {
+            '\\' => {
+                chars.next();
+            },
+            '"' => return Ok(pos + 1 + offset + 1),
+            _ => (),
+        }
+    }
+
+    Err(ParseError::UnterminatedString)
+}
+
+// Skips a character literal, which may be a delimiter itself, e.g. #\( or #\;
+// Returns None if the literal runs off the end of the text
+fn scan_char(text: &str, pos: usize) -> Option<usize> {
+    let lit = &text[pos + 2..];
+
+    if lit.starts_with("u{") {
+        return lit.find('}').map(|close| pos + 2 + close + 1);
+    }
+
+    lit.chars().next().map(|ch| pos + 2 + ch.len_utf8())
+}
+
+fn is_char_start(text: &str, pos: usize) -> bool {
+    text[pos..].starts_with("#\\")
+}
+
+fn scan_list(text: &str, mut pos: usize) -> Result<usize, ParseError> {
+    let mut depth = 0;
+
+    while pos < text.len() {
+        match text.as_bytes()[pos] {
+            b'(' | b'{' => {
+                depth += 1;
+                pos += 1;
+            },
+            b')' | b'}' => {
+                depth -= 1;
+                pos += 1;
+
+                if depth == 0 {
+                    return Ok(pos);
+                }
+            },
+            b'"' => pos = try!(scan_string(text, pos)),
+            b';' => {
+                pos = skip_trivia(text, pos);
+            },
+            _ if is_char_start(text, pos) => {
+                pos = try!(scan_char(text, pos).ok_or(ParseError::UnclosedList));
+            },
+            _ => pos += char_at(text, pos).len_utf8(),
+        }
+    }
+
+    Err(ParseError::UnclosedList)
+}
+
+fn scan_atom(text: &str, start: usize, finished: bool) -> Result<usize, ParseError> {
+    let mut pos = start;
+
+    while pos < text.len() {
+        if is_char_start(text, pos) {
+            pos = match scan_char(text, pos) {
+                Some(end) => end,
+                None => return Err(ParseError::InvalidCharLiteral(text[start..].to_string())),
+            };
+        } else if is_delimiter(char_at(text, pos)) {
+            break;
+        } else {
+            pos += char_at(text, pos).len_utf8();
+        }
+    }
+
+    if pos == start {
+        // A delimiter that can't start a form, such as a stray ), which is read on its own so parsing can report it
+        return Ok(start + char_at(text, start).len_utf8());
+    }
+
+    if pos == text.len() && !finished {
+        // More of the symbol or number could still be on its way
+        return Err(ParseError::UnreadableSourceCode);
+    }
+
+    Ok(pos)
+}
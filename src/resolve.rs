@@ -0,0 +1,504 @@
+//! Static resolution of the symbols in parsed code.
+//!
+//! Scoping in rlisp is dynamic, so a name that isn't bound by an enclosing `let` or by the parameters
+//! of the lambda it's in can only be resolved at runtime, since it might be bound by whatever called the lambda.
+//! Names that are bound locally can be given a lexical address though,
+//! which the compiler turns into a slot in the VM's frame instead of a lookup through every scope.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use value::{func, Value, Func, Args, Params};
+use valtype::Type;
+use pattern::Pattern;
+use symbol::Symbol;
+use eval::Lisp;
+use default_env;
+
+/// Where the value of a symbol comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// Bound by an enclosing `let` or lambda, `depth` frames out from the innermost one
+    Local {
+        depth: usize,
+        slot: usize,
+    },
+    /// Bound by a frame that can't be addressed statically, since its body can `def` into it
+    Dynamic,
+    /// Defined at the top level, or part of the interpreter's environment
+    Global,
+}
+
+/// Builtins whose arguments are code, which the analysis and the compiler look inside of.
+/// They're recognised by their head resolving to the builtin itself, not by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoreForm {
+    If,
+    Seq,
+    Let,
+    Lambda,
+    Def,
+}
+
+pub fn core_form(lisp: &Lisp, head: &Value) -> Option<CoreForm> {
+    let sym = match head {
+        &Value::Symbol(sym) => sym,
+        _ => return None,
+    };
+
+    let found = match lisp.lookup(sym) {
+        Ok(Value::HardFunc(found)) => found,
+        _ => return None,
+    };
+
+    let forms: [(Func, CoreForm); 5] = [
+        (func(default_env::if_fn, Args::Fixed(3)), CoreForm::If),
+        (func(default_env::seq, Args::Atleast(1)), CoreForm::Seq),
+        (func(default_env::let_fn, Args::Atleast(2)), CoreForm::Let),
+        (func(default_env::lambda, Args::Atleast(2)), CoreForm::Lambda),
+        (func(default_env::define, Args::Atleast(2)), CoreForm::Def),
+    ];
+
+    forms.iter()
+        .find(|&&(ref core, _)| *core == found)
+        .map(|&(_, form)| form)
+}
+
+/// Whether anything in the value could add a binding to the scope it's evaluated in,
+/// which makes the names in that scope impossible to address statically.
+/// That's `def` or `eval` under any name, or a call to something that might turn out to be one of them,
+/// which is anything in `locals` (their values aren't known until runtime) or anything that isn't a function yet.
+pub fn mentions_redefinition(lisp: &Lisp, locals: &[Symbol], value: &Value) -> bool {
+    let list = match value {
+        &Value::Symbol(sym) => return is_redefinition(lisp, sym),
+        &Value::Quote(ref inner) => return mentions_redefinition(lisp, locals, inner),
+        &Value::List(ref list) => list.to_vec(),
+        _ => return false,
+    };
+
+    let (head, args) = match list.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+
+    // Binding lists and parameter lists are names rather than calls
+    match core_form(lisp, head) {
+        Some(CoreForm::Def) => return true,
+        Some(CoreForm::Let) if args.len() >= 2 => {
+            if let Some(defs) = let_defs(unquote(&args[0])) {
+                return let_redefines(lisp, locals, &defs, &args[1..]);
+            }
+        },
+        Some(CoreForm::Lambda) if args.len() >= 2 => {
+            let params = match unquote(&args[0]) {
+                &Value::List(ref params) => Params::parse(params.to_vec()),
+                _ => return true,
+            };
+
+            return match params {
+                Ok(params) => {
+                    let mut locals = locals.to_vec();
+                    locals.extend(params.names());
+                    args[1..].iter().any(|arg| mentions_redefinition(lisp, &locals, arg))
+                },
+                Err(_) => true,
+            };
+        },
+        Some(_) => return args.iter().any(|arg| mentions_redefinition(lisp, locals, arg)),
+        None => (),
+    }
+
+    let unknown_head = match head {
+        &Value::Symbol(sym) => locals.contains(&sym) || !is_function(lisp, sym),
+        _ => false,
+    };
+
+    unknown_head || list.iter().any(|item| mentions_redefinition(lisp, locals, item))
+}
+
+/// Whether a let's bindings or body could add a binding to the let's own scope
+pub fn let_redefines(lisp: &Lisp, locals: &[Symbol], defs: &[(Symbol, Value)], body: &[Value]) -> bool {
+    let mut locals = locals.to_vec();
+    locals.extend(defs.iter().map(|&(name, _)| name));
+
+    defs.iter().any(|&(_, ref value)| mentions_redefinition(lisp, &locals, value))
+        || body.iter().any(|form| mentions_redefinition(lisp, &locals, form))
+}
+
+fn is_redefinition(lisp: &Lisp, sym: Symbol) -> bool {
+    if sym.as_str() == "def" || sym.as_str() == "eval" {
+        return true;
+    }
+
+    match lisp.lookup(sym) {
+        Ok(Value::HardFunc(found)) => {
+            found == func(default_env::define, Args::Atleast(2)) || found == func(default_env::eval, Args::Fixed(1))
+        },
+        _ => false,
+    }
+}
+
+fn is_function(lisp: &Lisp, sym: Symbol) -> bool {
+    match lisp.lookup(sym) {
+        Ok(Value::HardFunc(_)) | Ok(Value::Lambda(_)) => true,
+        _ => false,
+    }
+}
+
+struct Frame {
+    names: Vec<Symbol>,
+    slotted: bool,
+}
+
+/// The statically known frames around a piece of code, innermost last.
+/// Each lambda body starts with a fresh set of frames, since the lambda can be called from anywhere.
+pub struct Scopes {
+    frames: Vec<Frame>,
+}
+
+impl Scopes {
+    pub fn new() -> Scopes {
+        Scopes {
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, names: Vec<Symbol>, slotted: bool) {
+        self.frames.push(Frame {
+            names: names,
+            slotted: slotted,
+        });
+    }
+
+    pub fn pop(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Adds a name to the innermost frame, returning its slot
+    pub fn bind(&mut self, sym: Symbol) -> usize {
+        let frame = self.frames.last_mut().expect("binding a name outside of any frame");
+        frame.names.push(sym);
+
+        frame.names.len() - 1
+    }
+
+    /// Whether names in the innermost frame can be addressed statically
+    pub fn is_slotted(&self) -> bool {
+        self.frames.last().map_or(false, |frame| frame.slotted)
+    }
+
+    /// The address of the innermost binding of `sym`, or None if no enclosing frame binds it
+    pub fn lookup(&self, sym: Symbol) -> Option<Address> {
+        for (depth, frame) in self.frames.iter().rev().enumerate() {
+            if let Some(slot) = frame.names.iter().rposition(|name| *name == sym) {
+                if !frame.slotted {
+                    return Some(Address::Dynamic);
+                }
+
+                return Some(Address::Local { depth: depth, slot: slot });
+            }
+        }
+
+        None
+    }
+
+    /// Frames are laid out one after another in a single array of slots,
+    /// this is the index of a local address in that array
+    pub fn flat_slot(&self, depth: usize, slot: usize) -> usize {
+        let outer = self.frames.len() - 1 - depth;
+        self.frames[..outer].iter().map(|frame| frame.names.len()).sum::<usize>() + slot
+    }
+
+    /// Every name bound by the frames currently in scope
+    pub fn names(&self) -> Vec<Symbol> {
+        self.frames.iter().flat_map(|frame| frame.names.iter().cloned()).collect()
+    }
+
+    /// The number of slots used by every frame currently in scope
+    pub fn slots(&self) -> usize {
+        self.frames.iter().map(|frame| frame.names.len()).sum()
+    }
+}
+
+/// A symbol that was evaluated somewhere in the analysed code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub symbol: Symbol,
+    pub address: Address,
+    /// The index of the top level form the reference is in
+    pub form: usize,
+}
+
+/// A reference to a name that nothing defines, either in the code or in the interpreter's environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub symbol: Symbol,
+    pub form: usize,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "warning: undefined symbol {} in top level form {}", self.symbol, self.form + 1)
+    }
+}
+
+pub struct Analysis {
+    pub references: Vec<Reference>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Resolves every symbol in the given top level forms.
+/// Definitions anywhere in the code count, so functions can refer to ones that are defined after them.
+pub fn analyze(lisp: &Lisp, forms: &[Value]) -> Analysis {
+    let mut analyzer = Analyzer {
+        lisp: lisp,
+        scopes: Scopes::new(),
+        defined: HashSet::new(),
+        bound: HashSet::new(),
+        form: 0,
+        analysis: Analysis {
+            references: Vec::new(),
+            warnings: Vec::new(),
+        },
+    };
+
+    for form in forms {
+        analyzer.collect_names(form);
+    }
+
+    for (index, form) in forms.iter().enumerate() {
+        analyzer.form = index;
+        analyzer.walk(form);
+    }
+
+    analyzer.analysis
+}
+
+struct Analyzer<'a> {
+    lisp: &'a Lisp,
+    scopes: Scopes,
+
+    defined: HashSet<Symbol>, // Anything given to def
+    bound: HashSet<Symbol>, // Lambda parameters and let bindings, which are visible to callees as well
+
+    form: usize,
+    analysis: Analysis,
+}
+
+impl<'a> Analyzer<'a> {
+    fn collect_names(&mut self, value: &Value) {
+        let list = match value {
+            &Value::List(ref list) => list,
+            &Value::Quote(ref inner) => return self.collect_names(inner),
+            _ => return,
+        };
+
+        let items = list.to_vec();
+        match items.first().and_then(|head| core_form(self.lisp, head)) {
+            Some(CoreForm::Def) if items.len() > 1 => {
+                match unquote(&items[1]) {
+                    &Value::Symbol(sym) => {
+                        self.defined.insert(sym);
+                    },
+                    &Value::List(ref sig) => match sig.head() {
+                        Some(&Value::Symbol(name)) => {
+                            self.defined.insert(name);
+                            self.bound.extend(param_names(sig.iter().skip(1).cloned().collect()));
+                        },
+                        Some(pattern) => self.defined.extend(pattern_names(pattern)),
+                        None => (),
+                    },
+                    _ => (),
+                }
+            },
+            Some(CoreForm::Lambda) if items.len() > 1 => {
+                if let &Value::List(ref params) = unquote(&items[1]) {
+                    self.bound.extend(param_names(params.iter().cloned().collect()));
+                }
+            },
+            Some(CoreForm::Let) if items.len() > 1 => {
+                if let &Value::List(ref defs) = unquote(&items[1]) {
+                    for def in defs {
+                        match def {
+                            &Value::List(ref pair) if pair.len() == 2 => self.bound.extend(pattern_names(pair.head().unwrap())),
+                            _ => (),
+                        }
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        for item in &items {
+            self.collect_names(item);
+        }
+    }
+
+    fn reference(&mut self, sym: Symbol) {
+        let address = match self.scopes.lookup(sym) {
+            Some(address) => address,
+            None if self.lisp.lookup(sym).is_ok() || self.defined.contains(&sym) => Address::Global,
+            None => {
+                if !self.bound.contains(&sym) {
+                    self.analysis.warnings.push(Warning {
+                        symbol: sym,
+                        form: self.form,
+                    });
+                }
+
+                Address::Dynamic
+            },
+        };
+
+        self.analysis.references.push(Reference {
+            symbol: sym,
+            address: address,
+            form: self.form,
+        });
+    }
+
+    fn walk(&mut self, value: &Value) {
+        match value {
+            &Value::Symbol(sym) => self.reference(sym),
+            &Value::List(ref list) => {
+                let items = list.to_vec();
+                let form = items.first().and_then(|head| core_form(self.lisp, head));
+
+                match form {
+                    Some(form) if self.walk_core_form(form, &items[1..]) => (),
+                    _ => {
+                        // Quoted arguments to anything else are data, not code
+                        for item in &items {
+                            self.walk(item);
+                        }
+                    },
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn walk_body(&mut self, forms: &[Value]) {
+        for form in forms {
+            self.walk(unquote(form));
+        }
+    }
+
+    // Lambda bodies are analysed on their own, since the frames around the lambda
+    // aren't necessarily around it when it's called
+    fn walk_lambda(&mut self, params: &Value, body: &[Value]) {
+        let params = match unquote(params) {
+            &Value::List(ref params) => match Params::parse(params.iter().cloned().collect()) {
+                Ok(params) => params,
+                Err(_) => return,
+            },
+            _ => return,
+        };
+
+        let outer = ::std::mem::replace(&mut self.scopes, Scopes::new());
+        let names = params.names();
+        let slotted = !body.iter().any(|form| mentions_redefinition(self.lisp, &names, form));
+        self.scopes.push(names, slotted);
+
+        // Defaults are evaluated when the lambda's called, with the parameters before them bound
+        for &(_, ref default) in params.optional.iter().chain(&params.keys) {
+            self.walk(default);
+        }
+
+        self.walk_body(body);
+        self.scopes = outer;
+    }
+
+    // Returns false if the form isn't in a shape that's understood, in which case it's walked like a regular call
+    fn walk_core_form(&mut self, form: CoreForm, args: &[Value]) -> bool {
+        match form {
+            CoreForm::If if args.len() == 3 => {
+                self.walk(&args[0]);
+                self.walk_body(&args[1..]);
+            },
+            CoreForm::Seq => self.walk_body(args),
+            CoreForm::Let if args.len() >= 2 => {
+                let defs = match let_defs(unquote(&args[0])) {
+                    Some(defs) => defs,
+                    None => return false,
+                };
+
+                let slotted = !let_redefines(self.lisp, &self.scopes.names(), &defs, &args[1..]);
+                self.scopes.push(Vec::new(), slotted);
+
+                for (name, value) in defs {
+                    self.walk(&value);
+                    self.scopes.bind(name);
+                }
+
+                self.walk_body(&args[1..]);
+                self.scopes.pop();
+            },
+            CoreForm::Lambda if args.len() >= 2 => self.walk_lambda(&args[0], &args[1..]),
+            CoreForm::Def if args.len() >= 2 => {
+                match unquote(&args[0]) {
+                    &Value::Symbol(_) => self.walk(&args[1]),
+                    &Value::List(ref sig) if sig.head().map_or(false, |head| head.typ() == Type::List) => self.walk(&args[1]),
+                    &Value::List(ref sig) if !sig.is_empty() => {
+                        let params = Value::List(sig.tail().unwrap());
+                        self.walk_lambda(&params, &args[1..]);
+                    },
+                    _ => return false,
+                }
+            },
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+// The names bound by a pattern, none if it isn't valid
+fn pattern_names(pattern: &Value) -> Vec<Symbol> {
+    Pattern::parse(pattern).map(|pattern| pattern.names()).unwrap_or_default()
+}
+
+// The names bound by a lambda's parameters, or just the symbols if they aren't valid parameters
+fn param_names(params: Vec<Value>) -> Vec<Symbol> {
+    match Params::parse(params.clone()) {
+        Ok(params) => params.names(),
+        Err(_) => params.into_iter().filter_map(|value| value.as_sym().ok()).collect(),
+    }
+}
+
+pub fn is_quote(value: &Value) -> bool {
+    match value {
+        &Value::Quote(_) => true,
+        _ => false,
+    }
+}
+
+pub fn unquote(value: &Value) -> &Value {
+    match value {
+        &Value::Quote(ref inner) => inner,
+        other => other,
+    }
+}
+
+/// The bindings of a let, if they're all (name value) pairs
+pub fn let_defs(value: &Value) -> Option<Vec<(Symbol, Value)>> {
+    let list = match value {
+        &Value::List(ref list) => list,
+        _ => return None,
+    };
+
+    let mut defs = Vec::new();
+    for def in list {
+        let pair = match def {
+            &Value::List(ref pair) if pair.len() == 2 => pair.to_vec(),
+            _ => return None,
+        };
+
+        match pair[0] {
+            Value::Symbol(name) => defs.push((name, pair[1].clone())),
+            _ => return None,
+        }
+    }
+
+    Some(defs)
+}
@@ -0,0 +1,47 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small, seedable PRNG (SplitMix64), so that scripts can be made reproducible.
+/// Its entire state is a single `u64`, which can be read and restored by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng {
+            state: seed,
+        }
+    }
+
+    /// Seeds the generator from the system clock
+    pub fn from_time() -> Rng {
+        let seed = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.as_secs() ^ (time.subsec_nanos() as u64) << 32,
+            Err(_) => 0,
+        };
+
+        Rng::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// A float in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        // An f32 only has 24 bits of precision, so use the top 24 bits
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// An integer in [0, bound), `bound` must be non-zero
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
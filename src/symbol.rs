@@ -0,0 +1,128 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hasher, BuildHasherDefault};
+use std::fmt;
+
+/// An interned symbol, which is just an index into the symbol table.
+/// Comparing and hashing symbols never has to look at the symbol's name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// The table is per-thread rather than per-`Lisp`, since symbols are created while parsing and
+// printed by `Debug`, neither of which has access to an interpreter.
+// Names are leaked so that they can be handed out as &'static str, there's only ever one copy of each.
+// Since they're never freed, names that come from parsing (and so possibly from data) are limited
+// to MAX_SYMBOLS of them, taking up MAX_SYMBOL_BYTES at most.
+struct SymbolTable {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, Symbol>,
+    bytes: usize,
+}
+
+/// The most distinct names `try_intern` will intern on a thread
+pub const MAX_SYMBOLS: usize = 1 << 20;
+
+/// The most memory the names interned by `try_intern` can take up, in bytes
+pub const MAX_SYMBOL_BYTES: usize = 16 << 20;
+
+thread_local! {
+    static SYMBOLS: RefCell<SymbolTable> = RefCell::new(SymbolTable {
+        names: Vec::new(),
+        ids: HashMap::new(),
+        bytes: 0,
+    });
+}
+
+impl SymbolTable {
+    fn get(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).cloned()
+    }
+
+    fn insert(&mut self, name: &str) -> Symbol {
+        let sym = Symbol(self.names.len() as u32);
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+
+        self.names.push(name);
+        self.ids.insert(name, sym);
+        self.bytes += name.len();
+
+        sym
+    }
+
+    fn is_full(&self, name: &str) -> bool {
+        self.names.len() >= MAX_SYMBOLS || self.bytes + name.len() > MAX_SYMBOL_BYTES
+    }
+}
+
+impl Symbol {
+    /// Interns a name, for names that are part of the program (like those of builtins) rather than read from somewhere
+    pub fn intern(name: &str) -> Symbol {
+        SYMBOLS.with(|table| {
+            let mut table = table.borrow_mut();
+            table.get(name).unwrap_or_else(|| table.insert(name))
+        })
+    }
+
+    /// Interns a name unless it's new and the table is already full, which is what parsing uses
+    /// so that reading more and more distinct names can't use up all of the memory
+    pub fn try_intern(name: &str) -> Option<Symbol> {
+        SYMBOLS.with(|table| {
+            let mut table = table.borrow_mut();
+
+            match table.get(name) {
+                Some(sym) => Some(sym),
+                None if table.is_full(name) => None,
+                None => Some(table.insert(name)),
+            }
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        SYMBOLS.with(|table| table.borrow().names[self.0 as usize])
+    }
+
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl<'a> From<&'a str> for Symbol {
+    fn from(name: &'a str) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+/// Symbols are already unique integers, so maps keyed by them only need to spread the bits out
+/// (a multiply) rather than running a full hash function over them
+#[derive(Default)]
+pub struct SymbolHasher(u64);
+
+impl Hasher for SymbolHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0 << 8 | byte as u64;
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = (n as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    }
+}
+
+pub type SymbolMap<V> = HashMap<Symbol, V, BuildHasherDefault<SymbolHasher>>;
@@ -0,0 +1,327 @@
+//! Unit tests written in rlisp.
+//!
+//! `(deftest 'name form...)` registers a test, whose quoted forms are evaluated when it's run:
+//! ```lisp
+//! (deftest 'squares {assert-equal 9 (square 3)} {assert-error {square "3"}})
+//! ```
+//! Running a file's tests evaluates the file once to find them, then runs each test in a fresh interpreter
+//! that has evaluated the file again, so tests can't affect each other.
+//! A test passes if its forms are all evaluated without an error.
+
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use eval::{Lisp, FuncError};
+use parse::{self, FilePos, NodeKind};
+use pretty;
+use symbol::Symbol;
+use value::Value;
+
+/// A test registered by `deftest`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Test {
+    pub name: String,
+    pub body: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Passed,
+    /// An assertion failed
+    Failed(FuncError),
+    /// Anything else went wrong
+    Errored(FuncError),
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub file: String,
+    pub name: String,
+    /// The line of the deftest, if it could be found
+    pub line: Option<usize>,
+    pub outcome: Outcome,
+    pub time: Duration,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        match self.outcome {
+            Outcome::Passed => true,
+            _ => false,
+        }
+    }
+
+    /// What went wrong, with a diff of the values if they weren't equal
+    pub fn failure(&self) -> Option<String> {
+        match self.outcome {
+            Outcome::Passed => None,
+            Outcome::Failed(FuncError::NotEqual { ref expected, ref actual }) => {
+                Some(format!("values aren't equal (- expected, + actual)\n{}", diff(expected, actual)))
+            },
+            Outcome::Failed(ref err) | Outcome::Errored(ref err) => Some(format!("{:?}", err)),
+        }
+    }
+}
+
+/// Whether a file holds tests, by its name: test_*.lisp, test-*.lisp, *_test.lisp or *-test.lisp
+pub fn is_test_file(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.ends_with(".lisp") => &name[..name.len() - ".lisp".len()],
+        _ => return false,
+    };
+
+    name.starts_with("test_") || name.starts_with("test-") || name.ends_with("_test") || name.ends_with("-test")
+}
+
+/// Test files in each of the paths, directories are searched (skipping hidden ones) and files are kept as they are
+pub fn discover<P: AsRef<Path>>(paths: &[P]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+
+        match path.is_dir() {
+            true => discover_dir(path, &mut files),
+            false => files.push(path.to_path_buf()),
+        }
+    }
+
+    files
+}
+
+fn discover_dir(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(Result::ok).map(|entry| entry.path()).collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+
+    for path in entries {
+        let hidden = path.file_name().map_or(false, |name| name.to_string_lossy().starts_with('.'));
+
+        if path.is_dir() && !hidden && !path.ends_with("target") {
+            discover_dir(&path, files);
+        } else if is_test_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// Runs the tests in a file's source whose names contain `filter`
+pub fn run_source(file: &str, code: &str, filter: Option<&str>) -> Vec<TestResult> {
+    let start = Instant::now();
+
+    let mut lisp = Lisp::new();
+    if let Err(err) = load(&mut lisp, code) {
+        return vec![TestResult {
+            file: file.to_string(),
+            name: "(load)".to_string(),
+            line: None,
+            outcome: Outcome::Errored(err),
+            time: start.elapsed(),
+        }];
+    }
+
+    let mut results = Vec::new();
+
+    for test in lisp.tests() {
+        if filter.map_or(false, |filter| !test.name.contains(filter)) {
+            continue;
+        }
+
+        let start = Instant::now();
+        let outcome = run_test(code, &test.name);
+
+        results.push(TestResult {
+            file: file.to_string(),
+            name: test.name.clone(),
+            line: test_line(code, &test.name),
+            outcome: outcome,
+            time: start.elapsed(),
+        });
+    }
+
+    results
+}
+
+fn load(lisp: &mut Lisp, code: &str) -> Result<(), FuncError> {
+    let forms = try!(parse::parse_str(code).map_err(FuncError::ParsingErr));
+
+    for form in forms {
+        if let Err(err) = lisp.eval_token(form) {
+            lisp.scopes.truncate(1);
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs a test on its own, in an interpreter that's only loaded its file
+fn run_test(code: &str, name: &str) -> Outcome {
+    let mut lisp = Lisp::new();
+    if let Err(err) = load(&mut lisp, code) {
+        return Outcome::Errored(err);
+    }
+
+    let body = match lisp.tests().iter().find(|test| test.name == name) {
+        Some(test) => test.body.clone(),
+        None => return Outcome::Errored(FuncError::UserError(format!("the test {} wasn't defined again", name))),
+    };
+
+    match lisp.eval_body(&body) {
+        Ok(_) => Outcome::Passed,
+        Err(err) => match err {
+            FuncError::AssertionFailed(_) | FuncError::NotEqual { .. } => Outcome::Failed(err),
+            _ => Outcome::Errored(err),
+        },
+    }
+}
+
+// Finds the line of a top level (deftest 'name ...)
+fn test_line(code: &str, name: &str) -> Option<usize> {
+    let (nodes, _) = parse::parse_recovering(code);
+
+    nodes.iter()
+        .find(|node| match node.kind {
+            NodeKind::List(ref items) if items.len() >= 2 => {
+                items[0].to_value() == Ok(Value::Symbol(Symbol::intern("deftest"))) &&
+                    items[1].to_value().ok().and_then(test_name).map_or(false, |test| test == name)
+            },
+            _ => false,
+        })
+        .map(|node| FilePos::from_offset(code, node.span.start).line)
+}
+
+/// The name of a test, given to deftest as a quoted symbol or a string
+pub fn test_name(value: Value) -> Option<String> {
+    match value {
+        Value::Quote(inner) => test_name(*inner),
+        Value::Symbol(name) => Some(name.to_string()),
+        Value::String(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// A line by line diff of two values, two lists that don't fit on a line are compared an item at a time
+pub fn diff(expected: &Value, actual: &Value) -> String {
+    let (expected, actual) = match (expected, actual) {
+        (&Value::List(ref expected), &Value::List(ref actual)) if format!("{:?}{:?}", expected, actual).len() > 60 => {
+            (expected.iter().map(|item| format!("{:?}", item)).collect::<Vec<_>>().join("\n"),
+             actual.iter().map(|item| format!("{:?}", item)).collect::<Vec<_>>().join("\n"))
+        },
+        _ => (pretty::pretty(expected, 60), pretty::pretty(actual, 60)),
+    };
+    let (old, new): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+
+    // The longest common subsequence of lines, lcs[i][j] being for old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                ::std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            let _ = writeln!(out, "  {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            let _ = writeln!(out, "- {}", old[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(out, "+ {}", new[j]);
+            j += 1;
+        }
+    }
+
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+fn seconds(time: Duration) -> f64 {
+    time.as_secs() as f64 + time.subsec_nanos() as f64 / 1e9
+}
+
+/// The results as a JUnit XML report, with a test suite for each file
+pub fn junit_xml(results: &[TestResult]) -> String {
+    let mut files: Vec<&str> = Vec::new();
+    for result in results {
+        if !files.contains(&&result.file[..]) {
+            files.push(&result.file);
+        }
+    }
+
+    let count = |results: &[&TestResult], errored: bool| results.iter()
+        .filter(|result| match result.outcome {
+            Outcome::Failed(_) => !errored,
+            Outcome::Errored(_) => errored,
+            Outcome::Passed => false,
+        })
+        .count();
+
+    let all: Vec<&TestResult> = results.iter().collect();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<testsuites tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">",
+                     all.len(), count(&all, false), count(&all, true),
+                     seconds(results.iter().fold(Duration::new(0, 0), |total, result| total + result.time)));
+
+    for file in files {
+        let suite: Vec<&TestResult> = results.iter().filter(|result| result.file == file).collect();
+        let time = suite.iter().fold(Duration::new(0, 0), |total, result| total + result.time);
+
+        let _ = writeln!(xml, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">",
+                         escape_xml(file), suite.len(), count(&suite, false), count(&suite, true), seconds(time));
+
+        for result in suite {
+            let _ = write!(xml, "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\"",
+                           escape_xml(&result.name), escape_xml(file), seconds(result.time));
+
+            let tag = match result.outcome {
+                Outcome::Passed => {
+                    xml.push_str("/>\n");
+                    continue;
+                },
+                Outcome::Failed(_) => "failure",
+                Outcome::Errored(_) => "error",
+            };
+
+            let failure = result.failure().unwrap_or_default();
+            let message = failure.lines().next().unwrap_or("");
+
+            let _ = writeln!(xml, ">\n      <{} message=\"{}\">{}</{}>\n    </testcase>",
+                             tag, escape_xml(message), escape_xml(&failure), tag);
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
@@ -5,9 +5,11 @@ use std::any::TypeId;
 pub enum Type {
     Number,
     Bool,
+    Char,
     
     Symbol,
     String,
+    Bytes,
 
     List,
 
@@ -19,5 +21,7 @@ pub enum Type {
     Type,
     Quote(Box<Type>),
 
+    Regex,
+
     Foreign(TypeId),
 }
@@ -1,10 +1,18 @@
 use std::rc::Rc;
 use std::marker::Sized;
 use std::any::{Any, TypeId};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::cell::RefCell;
+
+use regex;
 
 use eval::{Lisp, FuncError, FuncResult};
 use valtype::Type;
+use list::List;
+use symbol::Symbol;
+use compile::Chunk;
+use pattern::Pattern;
 
 pub type RawFunc = fn(Vec<Value>, &mut Lisp) -> FuncResult;
 
@@ -16,16 +24,58 @@ pub enum Args {
     Atleast(usize), // Must contain this many or greater args
 }
 
+impl Args {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            &Args::Variant => true,
+            &Args::Fixed(expected) => count == expected,
+            &Args::Multiple(ref possible_counts) => possible_counts.contains(&count),
+            &Args::Atleast(min) => count >= min,
+        }
+    }
+}
+
+/// Describes the argument counts, e.g. "at least 2 arguments"
+impl fmt::Display for Args {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |count: usize| if count == 1 { "argument" } else { "arguments" };
+
+        match self {
+            &Args::Variant => write!(fmt, "any number of arguments"),
+            &Args::Fixed(count) => write!(fmt, "{} {}", count, plural(count)),
+            &Args::Atleast(count) => write!(fmt, "at least {} {}", count, plural(count)),
+            &Args::Multiple(ref counts) => match counts.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    let rest: Vec<String> = rest.iter().map(|count| count.to_string()).collect();
+                    write!(fmt, "{} or {} arguments", rest.join(", "), last)
+                },
+                Some((&last, _)) => write!(fmt, "{} {}", last, plural(last)),
+                None => write!(fmt, "no arguments"),
+            },
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Func {
     pub func: Rc<RawFunc>,
     pub args: Args,
+    /// The first line shows how it's called, e.g. "(map f list...)", and the rest says what it does
+    pub doc: Option<&'static str>,
 }
 
 pub fn func(func: RawFunc, args: Args) -> Func {
     Func {
         func: Rc::new(func),
         args: args,
+        doc: None,
+    }
+}
+
+impl Func {
+    pub fn with_doc(mut self, doc: &'static str) -> Func {
+        self.doc = Some(doc);
+        self
     }
 }
 
@@ -51,33 +101,271 @@ impl PartialEq for Foreign {
     }
 }
 
+// Compiled regexes are shared, and compare equal if their patterns are the same
+#[derive(Clone, Debug)]
+pub struct Regex(Rc<regex::Regex>);
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, FuncError> {
+        match regex::Regex::new(pattern) {
+            Ok(re) => Ok(Regex(Rc::new(re))),
+            Err(err) => Err(FuncError::RegexError(err)),
+        }
+    }
+}
+
+impl Deref for Regex {
+    type Target = regex::Regex;
+
+    fn deref(&self) -> &regex::Regex {
+        &self.0
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, rhs: &Regex) -> bool {
+        self.0.as_str() == rhs.0.as_str()
+    }
+}
+
+/// The parameters of a lambda, e.g. `{a &optional (b 1) &rest more}` or `{text &key (sep " ")}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params {
+    /// Names, or patterns that destructure the argument
+    pub required: Vec<Pattern>,
+    /// Each with the expression giving its value when it isn't passed, nil if there isn't one
+    pub optional: Vec<(Symbol, Value)>,
+    /// Given a list of the arguments after the required and optional ones
+    pub rest: Option<Symbol>,
+    /// Passed as the quoted name followed by the value, after the other arguments, e.g. `(f "a b" 'sep ",")`
+    pub keys: Vec<(Symbol, Value)>,
+}
+
+impl Params {
+    /// Plain parameters, that are all required
+    pub fn new(required: Vec<Symbol>) -> Params {
+        Params {
+            required: required.into_iter().map(Pattern::Name).collect(),
+            optional: Vec::new(),
+            rest: None,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Reads a parameter list, where &optional, &rest and &key start their kinds of parameters (in that order).
+    /// Required parameters can be patterns like (x y), and optional and key parameters can be written as (name default).
+    pub fn parse(params: Vec<Value>) -> Result<Params, FuncError> {
+        #[derive(PartialEq, PartialOrd)]
+        enum Section { Required, Optional, Rest, AfterRest, Key }
+
+        let mut parsed = Params::new(Vec::new());
+        let mut section = Section::Required;
+
+        for param in params {
+            let marker = match param {
+                Value::Symbol(sym) => match sym.as_str() {
+                    "&optional" => Some(Section::Optional),
+                    "&rest" => Some(Section::Rest),
+                    "&key" => Some(Section::Key),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if let Some(marker) = marker {
+                if marker <= section || section == Section::Rest {
+                    return Err(FuncError::InvalidParameter(param));
+                }
+
+                section = marker;
+                continue;
+            }
+
+            // Required parameters can destructure their arguments
+            let is_pattern = match param {
+                Value::List(_) => section == Section::Required,
+                _ => false,
+            };
+
+            if is_pattern {
+                let pattern = try!(Pattern::parse(&param));
+                if pattern.names().iter().any(|name| name.as_str() != "_" && parsed.names().contains(name)) {
+                    return Err(FuncError::InvalidParameter(param));
+                }
+
+                parsed.required.push(pattern);
+                continue;
+            }
+
+            let (name, default) = match param {
+                Value::Symbol(name) => (name, Value::Nil),
+                Value::List(ref list) if section == Section::Optional || section == Section::Key => {
+                    match (list.len(), list.head()) {
+                        (1, Some(&Value::Symbol(name))) => (name, Value::Nil),
+                        (2, Some(&Value::Symbol(name))) => (name, list.iter().nth(1).unwrap().clone()),
+                        _ => return Err(FuncError::InvalidParameter(param.clone())),
+                    }
+                },
+                _ => return Err(FuncError::InvalidParameter(param)),
+            };
+
+            // Like in patterns, _ can be used for more than one parameter that isn't needed
+            if name.as_str() != "_" && parsed.names().contains(&name) {
+                return Err(FuncError::InvalidParameter(param));
+            }
+
+            match section {
+                Section::Required => parsed.required.push(Pattern::Name(name)),
+                Section::Optional => parsed.optional.push((name, default)),
+                Section::Rest => {
+                    parsed.rest = Some(name);
+                    section = Section::AfterRest;
+                },
+                Section::AfterRest => return Err(FuncError::InvalidParameter(param)),
+                Section::Key => parsed.keys.push((name, default)),
+            }
+        }
+
+        // &rest has to be followed by its name
+        if section == Section::Rest {
+            return Err(FuncError::InvalidParameter(Value::Symbol(Symbol::intern("&rest"))));
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether every parameter is a required name
+    pub fn is_simple(&self) -> bool {
+        self.optional.is_empty() && self.rest.is_none() && self.keys.is_empty() && self.required.iter().all(Pattern::is_name)
+    }
+
+    /// Every name the parameters bind, in order
+    pub fn names(&self) -> Vec<Symbol> {
+        let mut names: Vec<Symbol> = self.required.iter().flat_map(Pattern::names).collect();
+        names.extend(self.optional.iter().map(|&(name, _)| name));
+        names.extend(self.rest);
+        names.extend(self.keys.iter().map(|&(name, _)| name));
+
+        names
+    }
+
+    /// How many arguments can be passed
+    pub fn arity(&self) -> Args {
+        let required = self.required.len();
+
+        if self.rest.is_some() {
+            return Args::Atleast(required);
+        }
+
+        if self.is_simple() {
+            return Args::Fixed(required);
+        }
+
+        // Keys can only be passed once every optional parameter has been
+        let positional = required + self.optional.len();
+        let keyed = (1..self.keys.len() + 1).map(|keys| positional + keys * 2);
+
+        Args::Multiple((required..positional + 1).chain(keyed).collect())
+    }
+}
+
+/// Writes the parameters the way they're written in a lambda, without the braces
+impl fmt::Display for Params {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut words: Vec<String> = self.required.iter().map(|pattern| pattern.to_string()).collect();
+        let with_default = |&(name, ref default): &(Symbol, Value)| match default {
+            &Value::Nil => name.to_string(),
+            default => format!("({} {:?})", name, default),
+        };
+
+        if !self.optional.is_empty() {
+            words.push("&optional".to_string());
+            words.extend(self.optional.iter().map(&with_default));
+        }
+
+        if let Some(rest) = self.rest {
+            words.push(format!("&rest {}", rest));
+        }
+
+        if !self.keys.is_empty() {
+            words.push("&key".to_string());
+            words.extend(self.keys.iter().map(&with_default));
+        }
+
+        write!(fmt, "{}", words.join(" "))
+    }
+}
+
+pub struct Lambda {
+    /// Every name bound by the parameters, in order
+    pub args: Vec<Symbol>,
+    pub params: Params,
+    pub body: Vec<Value>,
+    pub doc: Option<String>,
+
+    // Filled in the first time the lambda is called by the bytecode backend
+    pub compiled: RefCell<Option<Rc<Chunk>>>,
+}
+
+impl Lambda {
+    pub fn new(args: Vec<Symbol>, body: Vec<Value>) -> Lambda {
+        Lambda::with_params(Params::new(args), body)
+    }
+
+    pub fn with_params(params: Params, body: Vec<Value>) -> Lambda {
+        Lambda {
+            args: params.names(),
+            params: params,
+            body: body,
+            doc: None,
+            compiled: RefCell::new(None),
+        }
+    }
+}
+
+impl PartialEq for Lambda {
+    fn eq(&self, rhs: &Lambda) -> bool {
+        self.params == rhs.params && self.body == rhs.body
+    }
+}
+
+impl Debug for Lambda {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.params.is_simple() {
+            true => write!(fmt, "λ {:?} => {:?}", self.args, self.body),
+            false => write!(fmt, "λ [{}] => {:?}", self.params, self.body),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub enum Value {
     Number(f32),
     Bool(bool),
+    Char(char),
 
-    Symbol(String),
+    Symbol(Symbol),
 
     String(String),
+    Bytes(Vec<u8>),
 
     // For some reason, fns that take reference arguments are not clonable on their own
     HardFunc(Func),
-    Lambda {
-        args: Vec<String>,
-        body: Vec<Value>,
-    },
+    Lambda(Rc<Lambda>),
 
-    List(Vec<Value>),
+    List(List),
     Nil,
 
     Quote(Box<Value>),
     Type(Type),
 
+    Regex(Regex),
+
     Foreign(Foreign),
 }
 
 impl<'a> Value {
-    pub fn as_sym(self) -> Result<String, FuncError> {
+    pub fn as_sym(self) -> Result<Symbol, FuncError> {
         match self {
             Value::Symbol(sym) => Ok(sym),
             _ => Err(FuncError::InvalidType {
@@ -87,7 +375,7 @@ impl<'a> Value {
         }
     }
 
-    pub fn as_list(self) -> Result<Vec<Value>, FuncError> {
+    pub fn as_list(self) -> Result<List, FuncError> {
         match self {
             Value::List(list) => Ok(list),
             _ => Err(FuncError::InvalidType {
@@ -118,14 +406,17 @@ impl<'a> Value {
         match self {
             &Value::Number(_) => Type::Number,
             &Value::Bool(_) => Type::Bool,
+            &Value::Char(_) => Type::Char,
             &Value::Symbol(_) => Type::Symbol,
             &Value::String(_) => Type::String,
+            &Value::Bytes(_) => Type::Bytes,
             &Value::HardFunc(_) => Type::HardFunc,
-            &Value::Lambda { .. } => Type::Lambda,
+            &Value::Lambda(_) => Type::Lambda,
             &Value::List(_) => Type::List,
             &Value::Nil => Type::Nil,
             &Value::Quote(ref val) => Type::Quote(box val.typ()),
             &Value::Type(_) => Type::Type,
+            &Value::Regex(_) => Type::Regex,
             &Value::Foreign(ref value) => Type::Foreign(value.get_type_id()),
         }
     }
@@ -179,9 +470,14 @@ macro_rules! lisp_impl {
 
 lisp_impl!(bool: Bool,
           f32: Number,
+          char: Char,
           String: String,
+          Vec<u8>: Bytes,
           Func: HardFunc,
-          Type: Type);
+          Type: Type,
+          Regex: Regex,
+          List: List,
+          Symbol: Symbol);
 
 impl ToLisp for () {
     fn to_lisp(self) -> Value { Value::Nil }
@@ -199,6 +495,34 @@ impl FromLisp for () {
     }
 }
 
+impl<'a> ToLisp for &'a [u8] {
+    fn to_lisp(self) -> Value { Value::Bytes(self.to_vec()) }
+}
+
+// Bytes aside (see the impls for Vec<u8>), Vecs convert to and from lists
+impl<T: FromLisp> FromLisp for Vec<T> {
+    fn from_lisp(val: Value) -> Result<Vec<T>, FuncError> {
+        let list = try!(val.as_list());
+
+        let mut result = Vec::with_capacity(list.len());
+        for item in list {
+            result.push(try!(T::from_lisp(item)));
+        }
+
+        Ok(result)
+    }
+}
+
+impl<T: ToLisp> ToLisp for Vec<T> {
+    fn to_lisp(self) -> Value {
+        Value::List(self.into_iter().map(ToLisp::to_lisp).collect())
+    }
+}
+
+impl FromLisp for Value {
+    fn from_lisp(val: Value) -> Result<Value, FuncError> { Ok(val) }
+}
+
 impl ToLisp for Value {
     fn to_lisp(self) -> Value { self }
 }
@@ -0,0 +1,188 @@
+use std::rc::Rc;
+use std::mem;
+
+use compile::{self, Op, Chunk};
+use eval::{Lisp, FuncError, FuncResult};
+use value::{Value, Lambda, FromLisp};
+
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    locals: Vec<Value>,
+
+    stack_base: usize,
+    scope_base: usize, // The index of the frame's own scope, everything from here up is popped on return
+}
+
+pub fn eval_body(lisp: &mut Lisp, body: &[Value]) -> FuncResult {
+    let (last, body) = match body.split_last() {
+        Some(split) => split,
+        None => return Ok(Value::Nil),
+    };
+
+    for form in body {
+        try!(eval_form(lisp, form));
+    }
+
+    eval_form(lisp, last)
+}
+
+/// Runs a list from its cached chunk, anything else is simple enough to just evaluate
+pub fn eval_form(lisp: &mut Lisp, form: &Value) -> FuncResult {
+    match form {
+        &Value::List(ref list) => {
+            let chunk = lisp.form_chunk(list);
+            run(lisp, chunk)
+        },
+        _ => lisp.eval_token(form.clone()),
+    }
+}
+
+pub fn run(lisp: &mut Lisp, chunk: Rc<Chunk>) -> FuncResult {
+    let locals = vec![Value::Nil; chunk.locals];
+    run_with_locals(lisp, chunk, locals)
+}
+
+/// Runs the body of a lambda whose parameters have been bound in the current scope, given their values
+pub fn call_lambda(lisp: &mut Lisp, lambda: &Lambda, mut locals: Vec<Value>) -> FuncResult {
+    let chunk = compile::lambda_chunk(lisp, lambda);
+    locals.resize(chunk.locals, Value::Nil);
+
+    run_with_locals(lisp, chunk, locals)
+}
+
+fn run_with_locals(lisp: &mut Lisp, chunk: Rc<Chunk>, locals: Vec<Value>) -> FuncResult {
+    let scope_depth = lisp.scopes.len();
+
+    let result = execute(lisp, chunk, locals);
+    if result.is_err() {
+        lisp.scopes.truncate(scope_depth);
+    }
+
+    result
+}
+
+// A tail call can only reuse the current frame if nothing visible would be lost by doing so.
+// Since scoping is dynamic, the callee can see every binding between its caller's scope and its own,
+// so those bindings can only be dropped if the callee's parameters shadow all of them.
+fn can_replace_frame(lisp: &Lisp, frame: &Frame, lambda: &Lambda) -> bool {
+    let call_scope = lisp.scopes.len() - 1;
+
+    lisp.scopes[frame.scope_base .. call_scope].iter()
+        .all(|env| env.map.keys().all(|sym| lambda.args.contains(sym)))
+}
+
+fn execute(lisp: &mut Lisp, chunk: Rc<Chunk>, locals: Vec<Value>) -> FuncResult {
+    let mut stack: Vec<Value> = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+
+    let mut frame = Frame {
+        locals: locals,
+        chunk: chunk,
+        ip: 0,
+        stack_base: 0,
+        scope_base: lisp.scopes.len(),
+    };
+
+    loop {
+        let op = frame.chunk.code[frame.ip];
+        frame.ip += 1;
+
+        match op {
+            Op::Const(index) => stack.push(frame.chunk.constants[index].clone()),
+            Op::LoadLocal(slot) => stack.push(frame.locals[slot].clone()),
+            Op::StoreLocal(slot) => frame.locals[slot] = stack.last().unwrap().clone(),
+            Op::LoadName(sym) => stack.push(try!(lisp.lookup(sym))),
+
+            Op::PushScope => lisp.sub_scope(),
+            Op::PopScope => lisp.exit_scope(),
+            Op::Bind(sym) => {
+                let value = stack.pop().unwrap();
+                lisp.cur_scope().set(sym, value);
+            },
+
+            Op::Pop => {
+                stack.pop();
+            },
+            Op::Jump(target) => frame.ip = target,
+            Op::JumpIfFalse(target) => {
+                let cond = try!(bool::from_lisp(stack.pop().unwrap()));
+
+                if !cond {
+                    frame.ip = target;
+                }
+            },
+
+            Op::Call { args, form } | Op::TailCall { args, form } => {
+                let args = stack.split_off(stack.len() - args);
+                let callee = stack.pop().unwrap();
+
+                match callee {
+                    Value::HardFunc(hard_func) => {
+                        if !hard_func.args.accepts(args.len()) {
+                            invalid_args!(lisp, hard_func.args, args);
+                        }
+
+                        let result = (hard_func.func)(args, lisp);
+                        lisp.exit_scope();
+
+                        stack.push(try!(result));
+                    },
+                    Value::Lambda(lambda) => {
+                        if !lambda.params.arity().accepts(args.len()) {
+                            invalid_args!(lisp, lambda.params.arity(), args);
+                        }
+
+                        let mut locals = try!(lisp.bind_params(&lambda, args));
+                        let chunk = compile::lambda_chunk(lisp, &lambda);
+
+                        locals.resize(chunk.locals, Value::Nil);
+                        let is_tail = match op {
+                            Op::TailCall { .. } => true,
+                            _ => false,
+                        };
+
+                        if is_tail && can_replace_frame(lisp, &frame, &lambda) {
+                            let call_scope = lisp.scopes.pop().unwrap();
+                            lisp.scopes.truncate(frame.scope_base);
+                            lisp.scopes.push(call_scope);
+
+                            stack.truncate(frame.stack_base);
+
+                            frame.chunk = chunk;
+                            frame.ip = 0;
+                            frame.locals = locals;
+                        } else {
+                            let callee_frame = Frame {
+                                chunk: chunk,
+                                ip: 0,
+                                locals: locals,
+                                stack_base: stack.len(),
+                                scope_base: lisp.scopes.len() - 1,
+                            };
+
+                            frames.push(mem::replace(&mut frame, callee_frame));
+                        }
+                    },
+                    _ => return Err(FuncError::AttemptToCallNonFunction(frame.chunk.constants[form].clone())),
+                }
+            },
+            Op::Return => {
+                let result = stack.pop().unwrap_or(Value::Nil);
+
+                stack.truncate(frame.stack_base);
+                lisp.scopes.truncate(frame.scope_base);
+
+                match frames.pop() {
+                    Some(caller) => {
+                        frame = caller;
+                        stack.push(result);
+                    },
+                    None => return Ok(result),
+                }
+            },
+
+            Op::EvalEmptyList => return Err(FuncError::AttemptToEvalEmptyList),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Backend, FuncResult};
+
+const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
+
+// Each program is run in a fresh interpreter
+const PROGRAMS: &'static [&'static str] = &[
+    "(+ 1 (* 2 3))",
+    "(def {fib n} {if (< n 2) n {+ (fib (- n 1)) (fib (- n 2))}}) (map fib (range 0 12))",
+    "(def {fact n} {if (< n 2) 1 {* n (fact (- n 1))}}) (fact 10)",
+    "(let {(a 1) (b (+ a 1))} {list a b})",
+    "(let {(x 1)} {seq (def 'x 5) x})",
+    "(def 'x 1) (def {get-x} {id x}) (let {(x 2)} {get-x})",
+    "(def {outer y} {inner}) (def {inner} {* y 10}) (outer 4)",
+    "(if (> 2 1) {list 1 2} {list 3})",
+    "(if false 1 2)",
+    "(seq (def 'a 3) (def 'b 4) (+ a b))",
+    "(fold + 0 (filter (\\ {x} {> x 2}) {1 2 3 4 5}))",
+    "(def {count-down n acc} {if (< n 1) {id acc} {count-down (- n 1) (+ acc n)}}) (count-down 50 0)",
+    "(def {greet name &optional (greeting \"Hi\") &key (end \"!\")} {str greeting \" \" name end}) (list (greet \"a\") (greet \"b\" \"Yo\" 'end \".\"))",
+    "(def {swap (a b)} {list b a}) (map swap {(1 2) (3 4)})",
+    "(let {((a . rest) {1 2 3})} {list a rest})",
+    "((comp (partial * 2) +) 1 2)",
+    "(eval (list '+ 1 2))",
+    "(def {twice f x} {f (f x)}) (twice (\\ {n} {* n n}) 3)",
+    "(str \"a\" 1 #\\b)",
+    // Redefinitions under another name
+    "(def 'd def) (def {f x} {d 'x 5} {id x}) (f 1)",
+    "(def 'd def) (def {f x} {let {(y 2)} {seq (d 'y x) y}}) (f 1)",
+    "(def 'e eval) (def {f x} {e (list 'def ''x 5)} {id x}) (f 1)",
+    "(def {f d x} {d 'x 5} {id x}) (f def 1)",
+    "(def {f x} {let {(d def)} {seq (d 'x 5) x}}) (f 1)",
+    "(def {f x} {seq (g) x}) (def {g} {id 1}) (f 2)",
+    // Errors
+    "(undefined-function 1)",
+    "(+ 1 undefined-value)",
+    "(def {f x} {id x}) (f 1 2)",
+    "(+ 1 \"two\")",
+    "(seq () 1)",
+    "(1 2 3)",
+    "(def {swap (a b)} {list b a}) (swap {1 2 3})",
+    "(def {f &key k} {id k}) (f 'nope 1)",
+    "(if 1 {id 2} {id 3})",
+    "(def {f &key k} {id k}) (f 'k)",
+    "(def {f &optional (o (undefined))} {id o}) (f)",
+    "(def {f &key (k (+ 1 \"x\"))} {id k}) (f)",
+    "(map (\\ {x} {+ x \"y\"}) {1 2})",
+    "(fold (\\ {a b} {undefined}) 0 {1})",
+];
+
+fn run(backend: Backend, program: &str) -> String {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(backend);
+
+    let result = format!("{:?}", lisp.eval_raw(program));
+
+    // Every scope a call makes is left again, even if it fails
+    assert_eq!(lisp.scopes.len(), 1, "{} left scopes behind with {:?}", program, backend);
+
+    result
+}
+
+#[test]
+fn backends_agree() {
+    for program in PROGRAMS {
+        let walked = run(Backend::TreeWalker, program);
+        let compiled = run(Backend::Bytecode, program);
+
+        assert_eq!(walked, compiled, "the backends disagree on {}", program);
+    }
+}
+
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(Backend::Bytecode);
+
+    let result = lisp.eval_raw("(def {loop n acc} {if (< n 1) {id acc} {loop (- n 1) (+ acc 1)}}) (loop 100000 0)");
+    assert_eq!(format!("{:?}", result), "Ok(100000)");
+    assert_eq!(lisp.scopes.len(), 1);
+}
+
+#[test]
+fn forms_see_redefinitions() {
+    for &backend in BACKENDS.iter() {
+        let mut lisp = Lisp::new();
+        lisp.set_backend(backend);
+
+        // The same form is evaluated twice, so the second time it runs from the cached chunk
+        lisp.eval_raw("(def {f} {id 1}) (def 'form '(f))").unwrap();
+        let first: FuncResult = lisp.eval_raw("(eval form)");
+
+        lisp.eval_raw("(def {f} {id 2})").unwrap();
+        let second: FuncResult = lisp.eval_raw("(eval form)");
+
+        assert_eq!(format!("{:?} {:?}", first, second), "Ok(1) Ok(2)");
+    }
+}
+
+#[test]
+fn built_code_is_evaluated() {
+    for &backend in BACKENDS.iter() {
+        let mut lisp = Lisp::new();
+        lisp.set_backend(backend);
+
+        let result = lisp.eval_raw("(map (\\ {n} {eval (list '* n n)}) (range 0 2000))").unwrap();
+        assert_eq!(format!("{:?}", result).matches(' ').count(), 1999);
+    }
+}
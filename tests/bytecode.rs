@@ -0,0 +1,250 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, FuncError, Value, Symbol};
+use rlisp::compile::{Chunk, Op};
+use rlisp::bytecode;
+
+const PROGRAM: &'static str = r#"
+(def {fact n} {if (< n 2) 1 {* n (fact (- n 1))}})
+(def 'nums (map fact {1 2 3 4 5}))
+(let {(total (fold + 0 nums)) (text "done")} {list total text #\x #u8(1 2) nums})
+"#;
+
+fn run_source(code: &str) -> Value {
+    Lisp::new().eval_raw(code).unwrap()
+}
+
+fn chunk(constants: Vec<Value>, code: Vec<Op>) -> Chunk {
+    let mut chunk = Chunk::new();
+    chunk.constants = constants;
+    chunk.code = code;
+    chunk
+}
+
+// Writes the chunk with a valid header and checksum, so only validation can reject it
+fn load(chunk: &Chunk) -> Result<Value, FuncError> {
+    let bytes = bytecode::write(chunk, None).unwrap();
+    Lisp::new().eval_compiled(&bytes)
+}
+
+fn assert_invalid(chunk: Chunk) {
+    match load(&chunk) {
+        Err(FuncError::InvalidBytecode(_)) => (),
+        other => panic!("{:?} was not rejected, got {:?}", chunk.code, other),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let lisp = Lisp::new();
+    let bytes = lisp.compile(PROGRAM).unwrap();
+
+    assert_eq!(Lisp::new().eval_compiled(&bytes).unwrap(), run_source(PROGRAM));
+}
+
+#[test]
+fn round_trip_with_source() {
+    let bytes = Lisp::new().compile_with_source(PROGRAM).unwrap();
+    assert_eq!(Lisp::new().eval_compiled(&bytes).unwrap(), run_source(PROGRAM));
+}
+
+#[test]
+fn round_trip_chunk() {
+    let chunk = chunk(vec![Value::Number(1.5), Value::String("hi".to_string())], vec![Op::Const(1), Op::Return]);
+
+    match bytecode::read(&bytecode::write(&chunk, None).unwrap()).unwrap() {
+        bytecode::Compiled::Chunk(read) => assert_eq!(read, chunk),
+        bytecode::Compiled::Source(_) => panic!("expected a chunk"),
+    }
+}
+
+#[test]
+fn other_versions_fall_back_to_source() {
+    let mut bytes = Lisp::new().compile_with_source("(+ 1 2)").unwrap();
+    bytes[8] = bytes[8].wrapping_add(1);
+
+    assert_eq!(Lisp::new().eval_compiled(&bytes).unwrap(), Value::Number(3.0));
+
+    let mut bytes = Lisp::new().compile("(+ 1 2)").unwrap();
+    bytes[8] = bytes[8].wrapping_add(1);
+
+    match Lisp::new().eval_compiled(&bytes) {
+        Err(FuncError::BytecodeVersion { .. }) => (),
+        other => panic!("expected a version error, got {:?}", other),
+    }
+}
+
+#[test]
+fn corrupted_files() {
+    let bytes = Lisp::new().compile(PROGRAM).unwrap();
+
+    let mut flipped = bytes.clone();
+    let last = flipped.len() - 1;
+    flipped[last] ^= 0xff;
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[1] = b'x';
+
+    let corrupted = vec![
+        flipped,
+        bad_magic,
+        bytes[..bytes.len() - 1].to_vec(),
+        bytes[..10].to_vec(),
+        Vec::new(),
+        b"(+ 1 2)".to_vec(),
+    ];
+
+    for bytes in corrupted {
+        match Lisp::new().eval_compiled(&bytes) {
+            Err(FuncError::InvalidBytecode(_)) => (),
+            other => panic!("corrupted file was accepted: {:?}", other),
+        }
+    }
+
+    // Every truncation is caught, not just the ones tried above
+    for len in 0..bytes.len() {
+        assert!(Lisp::new().eval_compiled(&bytes[..len]).is_err());
+    }
+}
+
+#[test]
+fn valid_chunks_are_accepted() {
+    let plus = Value::Symbol(Symbol::intern("+"));
+
+    let call = chunk(vec![plus.clone(), Value::Number(1.0)], vec![
+        Op::LoadName(Symbol::intern("+")),
+        Op::PushScope,
+        Op::Const(1),
+        Op::Const(1),
+        Op::Call { args: 2, form: 0 },
+        Op::Return,
+    ]);
+    assert_eq!(load(&call).unwrap(), Value::Number(2.0));
+
+    let branch = chunk(vec![Value::Bool(false), Value::Number(1.0), Value::Number(2.0)], vec![
+        Op::PushScope,
+        Op::Const(0),
+        Op::JumpIfFalse(5),
+        Op::Const(1),
+        Op::Jump(6),
+        Op::Const(2),
+        Op::PopScope,
+        Op::Return,
+    ]);
+    assert_eq!(load(&branch).unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn stack_underflow_is_rejected() {
+    let plus = Symbol::intern("+");
+
+    // More arguments than the stack holds
+    assert_invalid(chunk(vec![Value::Nil], vec![Op::LoadName(plus), Op::PushScope, Op::Call { args: 100, form: 0 }, Op::Return]));
+    assert_invalid(chunk(vec![Value::Nil], vec![Op::PushScope, Op::TailCall { args: 0, form: 0 }, Op::Return]));
+
+    assert_invalid(chunk(vec![], vec![Op::JumpIfFalse(1), Op::Return]));
+    assert_invalid(chunk(vec![], vec![Op::Bind(plus), Op::Return]));
+    assert_invalid(chunk(vec![], vec![Op::Pop, Op::Return]));
+    assert_invalid(chunk(vec![], vec![Op::StoreLocal(0), Op::Return]));
+    assert_invalid(chunk(vec![], vec![Op::Return]));
+}
+
+#[test]
+fn unbalanced_scopes_are_rejected() {
+    let nil = vec![Value::Nil];
+
+    assert_invalid(chunk(nil.clone(), vec![Op::PopScope, Op::Const(0), Op::Return]));
+    assert_invalid(chunk(nil.clone(), vec![Op::PushScope, Op::PopScope, Op::PopScope, Op::Const(0), Op::Return]));
+    assert_invalid(chunk(nil.clone(), vec![Op::PushScope, Op::Const(0), Op::Return]));
+
+    // A call ends the scope its arguments were evaluated in, so it needs one to end
+    assert_invalid(chunk(nil.clone(), vec![Op::Const(0), Op::Call { args: 0, form: 0 }, Op::Return]));
+}
+
+#[test]
+fn inconsistent_paths_are_rejected() {
+    let consts = vec![Value::Bool(true), Value::Number(1.0)];
+
+    // Only one side of the branch pushes a value
+    assert_invalid(chunk(consts.clone(), vec![
+        Op::Const(0),
+        Op::JumpIfFalse(3),
+        Op::Const(1),
+        Op::Const(1),
+        Op::Return,
+    ]));
+
+    // One side pushes a scope the other doesn't
+    assert_invalid(chunk(consts.clone(), vec![
+        Op::Const(0),
+        Op::JumpIfFalse(3),
+        Op::PushScope,
+        Op::Const(1),
+        Op::PopScope,
+        Op::Return,
+    ]));
+
+    // A loop that grows the stack every time around
+    assert_invalid(chunk(consts.clone(), vec![Op::Const(1), Op::Jump(0), Op::Return]));
+}
+
+#[test]
+fn out_of_bounds_indices_are_rejected() {
+    assert_invalid(chunk(vec![], vec![Op::Const(0), Op::Return]));
+    assert_invalid(chunk(vec![Value::Nil], vec![Op::Const(0), Op::Jump(7), Op::Return]));
+    assert_invalid(chunk(vec![Value::Nil], vec![Op::LoadLocal(0), Op::Return]));
+    assert_invalid(chunk(vec![Value::Nil], vec![Op::Const(0)]));
+
+    let mut huge = chunk(vec![Value::Nil], vec![Op::Const(0), Op::Return]);
+    huge.locals = 1 << 30;
+    assert_invalid(huge);
+}
+
+// A file whose only constant is `nil` under `depth` quotes, built by hand since it couldn't be written
+fn nested_quotes(depth: usize) -> Vec<u8> {
+    let mut body = vec![0]; // No source
+    body.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0]); // No locals, one constant
+
+    body.extend(std::iter::repeat(8).take(depth)); // Quote tags
+    body.push(0); // Nil
+
+    body.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 0, 0, 11]); // Const(0), Return
+
+    let mut sum: u32 = 0x811c9dc5;
+    for &byte in &body {
+        sum = (sum ^ byte as u32).wrapping_mul(0x01000193);
+    }
+
+    let mut bytes = bytecode::MAGIC.to_vec();
+    bytes.extend_from_slice(&[bytecode::FORMAT_VERSION as u8, (bytecode::FORMAT_VERSION >> 8) as u8]);
+    bytes.extend_from_slice(&[sum as u8, (sum >> 8) as u8, (sum >> 16) as u8, (sum >> 24) as u8]);
+    bytes.extend(body);
+    bytes
+}
+
+#[test]
+fn deeply_nested_constants_are_rejected() {
+    match Lisp::new().eval_compiled(&nested_quotes(10_000_000)) {
+        Err(FuncError::InvalidBytecode(_)) => (),
+        other => panic!("deeply nested constant was accepted: {:?}", other),
+    }
+
+    match Lisp::new().eval_compiled(&nested_quotes(bytecode::MAX_DEPTH + 1)) {
+        Err(FuncError::InvalidBytecode(_)) => (),
+        other => panic!("deeply nested constant was accepted: {:?}", other),
+    }
+
+    // Right at the limit is fine
+    let expected = (0..bytecode::MAX_DEPTH).fold(Value::Nil, |inner, _| Value::Quote(Box::new(inner)));
+    assert_eq!(Lisp::new().eval_compiled(&nested_quotes(bytecode::MAX_DEPTH)).unwrap(), expected);
+}
+
+#[test]
+fn deeply_nested_constants_are_not_written() {
+    let deep = (0..bytecode::MAX_DEPTH + 1).fold(Value::Nil, |inner, _| Value::Quote(Box::new(inner)));
+
+    match bytecode::write(&chunk(vec![deep], vec![Op::Const(0), Op::Return]), None) {
+        Err(FuncError::InvalidBytecode(_)) => (),
+        other => panic!("deeply nested constant was written: {:?}", other.map(|bytes| bytes.len())),
+    }
+}
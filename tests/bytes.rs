@@ -0,0 +1,33 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value, FuncError, FuncResult};
+
+fn run(code: &str) -> FuncResult {
+    Lisp::new().eval_raw(code)
+}
+
+#[test]
+fn bytes_are_made_from_numbers() {
+    assert_eq!(run("(bytes 0 1 255)").unwrap(), Value::Bytes(vec![0, 1, 255]));
+    assert_eq!(run("(bytes)").unwrap(), Value::Bytes(vec![]));
+    assert_eq!(run("(bytes-ref (bytes 7 8) 1)").unwrap(), Value::Number(8.0));
+}
+
+#[test]
+fn numbers_that_arent_bytes_are_out_of_range() {
+    for &(code, number) in &[("(bytes 300)", 300.0), ("(bytes 1 -1)", -1.0), ("(bytes 1.5)", 1.5), ("(bytes 256)", 256.0)] {
+        match run(code) {
+            Err(FuncError::InvalidByte(byte)) => assert_eq!(byte, number, "{}", code),
+            result => panic!("{} gave {:?}", code, result),
+        }
+    }
+
+    let message = format!("{:?}", run("(bytes 300)").unwrap_err());
+    assert_eq!(message, "300 is not a byte, bytes are whole numbers from 0 to 255");
+
+    // Anything that isn't a number is still the wrong type
+    match run("(bytes \"a\")") {
+        Err(FuncError::InvalidType { .. }) => (),
+        result => panic!("gave {:?}", result),
+    }
+}
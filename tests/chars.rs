@@ -0,0 +1,95 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value, FuncError, ParseError};
+use rlisp::parse::parse_str;
+
+fn eval(code: &str) -> Value {
+    match Lisp::new().eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+#[test]
+fn literals() {
+    assert_eq!(eval("#\\a"), Value::Char('a'));
+    assert_eq!(eval("#\\space"), Value::Char(' '));
+    assert_eq!(eval("#\\newline"), Value::Char('\n'));
+    assert_eq!(eval("#\\tab"), Value::Char('\t'));
+    assert_eq!(eval("#\\u{1F600}"), Value::Char('\u{1F600}'));
+    assert_eq!(eval("#\\é"), Value::Char('é'));
+
+    // Delimiters can be chars too
+    assert_eq!(eval("(list #\\( #\\) #\\;)"), eval("(list (integer->char 40) (integer->char 41) (integer->char 59))"));
+}
+
+#[test]
+fn hex_literals_still_work() {
+    assert_eq!(eval("#ff"), Value::Number(255.0));
+    assert_eq!(eval("#10"), Value::Number(16.0));
+}
+
+#[test]
+fn invalid_literals() {
+    match parse_str("#\\nope") {
+        Err(ParseError::InvalidCharLiteral(ref lit)) if lit == "#\\nope" => (),
+        other => panic!("{:?}", other),
+    }
+
+    match parse_str("#\\u{110000}") {
+        Err(ParseError::InvalidCharLiteral(_)) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn printing_reads_back() {
+    for code in &["#\\a", "#\\space", "#\\newline", "#\\nul", "#\\u{1F600}", "#\\u{7F}"] {
+        let value = eval(code);
+        assert_eq!(eval(&format!("{:?}", value)), value, "{} printed as {:?}", code, value);
+    }
+
+    assert_eq!(format!("{:?}", eval("#\\space")), "#\\space");
+    assert_eq!(format!("{:?}", eval("(integer->char 127)")), "#\\u{7F}");
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(eval("(char->integer #\\A)"), Value::Number(65.0));
+    assert_eq!(eval("(integer->char 955)"), Value::Char('λ'));
+    assert_eq!(eval("(integer->char (char->integer #\\u{1F600}))"), Value::Char('\u{1F600}'));
+
+    for code in &["(integer->char -1)", "(integer->char 1.5)", "(integer->char 55296)"] {
+        match Lisp::new().eval_raw(code) {
+            Err(FuncError::InvalidCharCode(_)) => (),
+            other => panic!("{} gave {:?}", code, other),
+        }
+    }
+
+    assert!(Lisp::new().eval_raw("(char->integer \"a\")").is_err());
+}
+
+#[test]
+fn predicates() {
+    assert_eq!(eval("(list (alphabetic? #\\a) (alphabetic? #\\é) (alphabetic? #\\1))"), eval("(list true true false)"));
+    assert_eq!(eval("(list (whitespace? #\\space) (whitespace? #\\tab) (whitespace? #\\a))"), eval("(list true true false)"));
+    assert_eq!(eval("(list (digit? #\\7) (digit? #\\a))"), eval("(list true false)"));
+}
+
+#[test]
+fn chars_in_strings_and_comparisons() {
+    assert_eq!(eval("(str \"a\" #\\b 1)"), Value::String("ab1".to_string()));
+    assert_eq!(eval("(sort {#\\c #\\a #\\b})"), eval("(list #\\a #\\b #\\c)"));
+    assert_eq!(eval("(= #\\a #\\a)"), Value::Bool(true));
+    assert_eq!(eval("(= #\\a \"a\")"), Value::Bool(false));
+}
+
+#[test]
+fn rust_chars() {
+    let mut lisp = Lisp::new();
+    lisp.set_global("letter", 'x');
+
+    assert_eq!(lisp.eval::<char>("(id letter)").unwrap(), 'x');
+    assert_eq!(lisp.eval::<char>("(integer->char 65)").unwrap(), 'A');
+    assert!(lisp.eval::<char>("\"x\"").is_err());
+}
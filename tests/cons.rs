@@ -0,0 +1,89 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, List, Value, FromLisp, ToLisp};
+
+fn nums(nums: &[f32]) -> List {
+    nums.iter().map(|&num| Value::Number(num)).collect()
+}
+
+fn list(lisp: &mut Lisp, code: &str) -> List {
+    match lisp.eval_raw(code) {
+        Ok(Value::List(list)) => list,
+        other => panic!("{} gave {:?}", code, other),
+    }
+}
+
+#[test]
+fn cons_shares_its_tail() {
+    let tail = nums(&[2.0, 3.0]);
+    let list = tail.cons(Value::Number(1.0));
+
+    assert_eq!(list, nums(&[1.0, 2.0, 3.0]));
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.head(), Some(&Value::Number(1.0)));
+    assert_eq!(list.tail().unwrap().addr(), tail.addr());
+
+    // The original is untouched
+    assert_eq!(tail, nums(&[2.0, 3.0]));
+}
+
+#[test]
+fn clones_share_their_cells() {
+    let list = nums(&[1.0, 2.0]);
+    let value = Value::List(list.clone());
+
+    match value.clone() {
+        Value::List(cloned) => assert_eq!(cloned.addr(), list.addr()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn empty_lists() {
+    let empty = List::new();
+
+    assert!(empty.is_empty());
+    assert_eq!(empty.len(), 0);
+    assert_eq!(empty.head(), None);
+    assert!(empty.tail().is_none());
+    assert_eq!(empty.cons(Value::Nil).tail(), Some(List::new()));
+}
+
+#[test]
+fn builtins_share_rather_than_copy() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def 'xs (range 0 5)) (def 'ys (cons 9 xs))").unwrap();
+
+    let xs = list(&mut lisp, "xs");
+    let ys = list(&mut lisp, "ys");
+    assert_eq!(ys.tail().unwrap().addr(), xs.addr());
+    assert_eq!(list(&mut lisp, "(tail ys)").addr(), xs.addr());
+    assert_eq!(list(&mut lisp, "(tail xs)").addr(), xs.tail().unwrap().addr());
+
+    // Looking a list up doesn't copy it either
+    assert_eq!(list(&mut lisp, "xs").addr(), xs.addr());
+}
+
+#[test]
+fn long_lists() {
+    let mut lisp = Lisp::new();
+
+    let built = list(&mut lisp, "(cons -1 (range 0 100000))");
+    assert_eq!(built.len(), 100001);
+    assert_eq!(built.head(), Some(&Value::Number(-1.0)));
+
+    // Dropping a long list doesn't recurse once per cell
+    drop(built);
+    let long: List = (0..1000000).map(|n| Value::Number(n as f32)).collect();
+    drop(long);
+}
+
+#[test]
+fn vec_conversions() {
+    let value = vec![1.0f32, 2.0, 3.0].to_lisp();
+    assert_eq!(value, Value::List(nums(&[1.0, 2.0, 3.0])));
+    assert_eq!(Vec::<f32>::from_lisp(value).unwrap(), vec![1.0, 2.0, 3.0]);
+
+    assert_eq!(List::from(vec![Value::Nil]).to_vec(), vec![Value::Nil]);
+    assert!(Vec::<f32>::from_lisp(Value::Number(1.0)).is_err());
+}
@@ -0,0 +1,291 @@
+extern crate rlisp;
+
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+use rlisp::{Lisp, Backend, Hook, Value, FuncError};
+use rlisp::debug::{Debugger, Breakpoint};
+use rlisp::parse;
+
+// Records every hook call, as "kind form"
+#[derive(Clone)]
+struct Recorder {
+    log: Rc<RefCell<Vec<String>>>,
+    refuse: Option<&'static str>, // before_eval fails for forms that print as this
+}
+
+impl Hook for Recorder {
+    fn before_eval(&mut self, _: &mut Lisp, form: &Value) -> Result<(), FuncError> {
+        let form = format!("{:?}", form);
+        self.log.borrow_mut().push(format!("before {}", form));
+
+        match self.refuse {
+            Some(refused) if refused == form => Err(FuncError::StoppedByDebugger),
+            _ => Ok(()),
+        }
+    }
+
+    fn after_eval(&mut self, _: &mut Lisp, form: &Value, result: &Value) {
+        self.log.borrow_mut().push(format!("after {:?} => {:?}", form, result));
+    }
+
+    fn on_call(&mut self, _: &mut Lisp, _: &Value, args: &[Value]) -> Result<(), FuncError> {
+        self.log.borrow_mut().push(format!("call {:?}", args));
+        Ok(())
+    }
+
+    fn on_return(&mut self, _: &mut Lisp, _: &Value, result: &Value) {
+        self.log.borrow_mut().push(format!("return {:?}", result));
+    }
+
+    fn on_error(&mut self, _: &mut Lisp, form: &Value, _: &FuncError) {
+        self.log.borrow_mut().push(format!("error {:?}", form));
+    }
+
+    fn on_break(&mut self, _: &mut Lisp) -> Result<(), FuncError> {
+        self.log.borrow_mut().push("break".to_string());
+        Ok(())
+    }
+}
+
+fn recorded(lisp: &mut Lisp, code: &str, refuse: Option<&'static str>) -> (Result<Value, FuncError>, Vec<String>) {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    lisp.set_hook(Box::new(Recorder { log: log.clone(), refuse: refuse }));
+
+    let result = lisp.eval_raw(code);
+    let log = log.borrow().clone();
+    (result, log)
+}
+
+#[test]
+fn hooks_see_each_step() {
+    let (result, log) = recorded(&mut Lisp::new(), "(+ 1 (* 2 3))", None);
+
+    assert_eq!(result.unwrap(), Value::Number(7.0));
+    assert_eq!(log, vec![
+        "before (+ 1 (* 2 3))",
+        "before (* 2 3)",
+        "call [2, 3]",
+        "return 6",
+        "after (* 2 3) => 6",
+        "call [1, 6]",
+        "return 7",
+        "after (+ 1 (* 2 3)) => 7",
+    ]);
+}
+
+#[test]
+fn errors_pass_through_each_form() {
+    let (result, log) = recorded(&mut Lisp::new(), "(+ 1 (* 2 \"x\"))", None);
+
+    assert!(result.is_err());
+    assert_eq!(log.iter().filter(|line| line.starts_with("error ")).cloned().collect::<Vec<_>>(), vec![
+        "error (* 2 \"x\")",
+        "error (+ 1 (* 2 \"x\"))",
+    ]);
+    assert!(!log.iter().any(|line| line.starts_with("after ")));
+}
+
+#[test]
+fn before_eval_can_stop_a_form() {
+    let mut lisp = Lisp::new();
+    let (result, log) = recorded(&mut lisp, "(list 1 (print \"no\") 3)", Some("(print \"no\")"));
+
+    match result {
+        Err(FuncError::StoppedByDebugger) => (),
+        other => panic!("{:?}", other),
+    }
+    assert!(!log.iter().any(|line| line.starts_with("call [\"no\"]")), "{:?}", log);
+    assert_eq!(lisp.scopes.len(), 1);
+}
+
+#[test]
+fn hooks_run_whichever_backend_is_chosen() {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(Backend::Bytecode);
+
+    let (_, log) = recorded(&mut lisp, "(def {f x} {* x 2}) (f 4)", None);
+    assert!(log.contains(&"before (* x 2)".to_string()), "{:?}", log);
+}
+
+#[test]
+fn break_calls_the_hook() {
+    let (result, log) = recorded(&mut Lisp::new(), "(seq (break) 1)", None);
+    assert_eq!(result.unwrap(), Value::Number(1.0));
+    assert!(log.contains(&"break".to_string()));
+
+    // Without a hook it does nothing
+    assert_eq!(Lisp::new().eval_raw("(break)").unwrap(), Value::Nil);
+
+    let mut lisp = Lisp::new();
+    recorded(&mut lisp, "1", None);
+    assert!(lisp.take_hook().is_some());
+    assert!(!lisp.has_hook());
+}
+
+// Output the test can read back after the debugger has written to it
+#[derive(Clone)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Session {
+    lisp: Lisp,
+    debugger: Debugger,
+    output: Shared,
+}
+
+impl Session {
+    fn new(commands: &str) -> Session {
+        let output = Shared(Rc::new(RefCell::new(Vec::new())));
+        let debugger = Debugger::with_io(Box::new(Cursor::new(commands.to_string())), Box::new(output.clone()));
+
+        let mut lisp = Lisp::new();
+        debugger.attach(&mut lisp);
+
+        Session {
+            lisp: lisp,
+            debugger: debugger,
+            output: output,
+        }
+    }
+
+    fn output(&self) -> String {
+        String::from_utf8(self.output.0.borrow().clone()).unwrap()
+    }
+
+    fn pauses(&self) -> usize {
+        self.output().matches("Paused, ").count()
+    }
+}
+
+#[test]
+fn break_pauses_where_it_is() {
+    let mut session = Session::new("p (* x 2)\nlocals\nc\n");
+
+    let result = session.lisp.eval_raw("(let {(x 5)} {seq (break) x})");
+    assert_eq!(result.unwrap(), Value::Number(5.0));
+
+    let out = session.output();
+    assert!(out.contains("Paused, at (break)"), "{}", out);
+    assert!(out.contains("=> 10"), "{}", out);
+    assert!(out.contains("x = 5"), "{}", out);
+    assert_eq!(session.lisp.scopes.len(), 1);
+}
+
+#[test]
+fn function_breakpoints() {
+    let mut session = Session::new("bt\nc\n");
+    session.debugger.add_breakpoint(Breakpoint::Function(rlisp::Symbol::intern("sq")));
+
+    let result = session.lisp.eval_raw("(def {sq x} {* x x}) (+ 1 (sq 3))");
+    assert_eq!(result.unwrap(), Value::Number(10.0));
+
+    let out = session.output();
+    assert!(out.contains("Paused, at a breakpoint on sq, called with [3]"), "{}", out);
+    assert!(out.contains("#0 (sq 3)"), "{}", out);
+    assert!(out.contains("#1 (+ 1 (sq 3))"), "{}", out);
+    assert_eq!(session.pauses(), 1);
+}
+
+#[test]
+fn deleted_breakpoints_dont_pause() {
+    let mut session = Session::new("c\n");
+    let number = session.debugger.add_breakpoint(Breakpoint::parse("sq"));
+
+    assert_eq!(session.debugger.breakpoints(), vec![(number, Breakpoint::parse("sq"))]);
+    assert!(session.debugger.remove_breakpoint(number));
+    assert!(!session.debugger.remove_breakpoint(number));
+    assert!(session.debugger.breakpoints().is_empty());
+
+    session.lisp.eval_raw("(def {sq x} {* x x}) (sq 3)").unwrap();
+    assert_eq!(session.pauses(), 0);
+}
+
+#[test]
+fn line_breakpoints() {
+    let code = "(def 'a 1)\n(def 'b\n  (+ a 1))\n(def 'c (+ b 1))\n";
+    let mut session = Session::new("p a\nc\n");
+    session.debugger.add_breakpoint(Breakpoint::parse("script.lisp:3"));
+
+    let (nodes, _) = parse::parse_recovering(code);
+    for form in session.debugger.register("dir/script.lisp", code, &nodes) {
+        session.lisp.eval_token(form).unwrap();
+    }
+
+    let out = session.output();
+    assert_eq!(session.pauses(), 1, "{}", out);
+    assert!(out.contains("(+ a 1) at dir/script.lisp:3"), "{}", out);
+    assert!(out.contains("=> 1"), "{}", out);
+    assert_eq!(session.lisp.eval_raw("c").unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn stepping() {
+    // Paused before (+ 1 (* 2 3)), stepping pauses again before (* 2 3), and next runs to the end since nothing follows it
+    let mut session = Session::new("s\nn\nc\n");
+    session.debugger.step();
+
+    session.lisp.eval_raw("(+ 1 (* 2 3))").unwrap();
+    assert_eq!(session.pauses(), 2, "{}", session.output());
+
+    // Out of (* 2 3) runs until the form it's inside of returns
+    let mut session = Session::new("s\no\nc\n");
+    session.debugger.step();
+    session.lisp.eval_raw("(+ 1 (* 2 3))").unwrap();
+    assert!(session.output().contains("stepped out, returned 7"), "{}", session.output());
+}
+
+#[test]
+fn quitting_stops_evaluation() {
+    let mut session = Session::new("q\n");
+
+    match session.lisp.eval_raw("(seq (break) (print \"not reached\"))") {
+        Err(FuncError::StoppedByDebugger) => (),
+        other => panic!("{:?}", other),
+    }
+
+    // Only what was being evaluated is stopped
+    assert_eq!(session.lisp.eval_raw("(+ 1 2)").unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn errors_pause_once() {
+    let mut session = Session::new("c\n");
+
+    assert!(session.lisp.eval_raw("(+ 1 (* 2 \"x\"))").is_err());
+    assert_eq!(session.pauses(), 1);
+    assert!(session.output().contains("Paused, error: "), "{}", session.output());
+
+    session.debugger.set_pause_on_error(false);
+    assert!(session.lisp.eval_raw("(+ 1 (* 2 \"x\"))").is_err());
+    assert_eq!(session.pauses(), 1);
+}
+
+#[test]
+fn running_out_of_commands_continues() {
+    let mut session = Session::new("");
+
+    assert_eq!(session.lisp.eval_raw("(seq (break) 1)").unwrap(), Value::Number(1.0));
+    assert_eq!(session.pauses(), 1);
+}
+
+#[test]
+fn parsing_breakpoints() {
+    assert_eq!(Breakpoint::parse("12"), Breakpoint::Line { file: None, line: 12 });
+    assert_eq!(Breakpoint::parse("a.lisp:3"), Breakpoint::Line { file: Some("a.lisp".to_string()), line: 3 });
+    assert_eq!(Breakpoint::parse("my-fn"), Breakpoint::Function(rlisp::Symbol::intern("my-fn")));
+
+    assert_eq!(Breakpoint::parse("a.lisp:3").to_string(), "a.lisp:3");
+    assert_eq!(Breakpoint::parse("my-fn").to_string(), "calls to my-fn");
+}
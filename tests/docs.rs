@@ -0,0 +1,124 @@
+extern crate rlisp;
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+use rlisp::{Lisp, Value, Symbol};
+use rlisp::docs;
+use rlisp::value::Args;
+
+fn eval(lisp: &mut Lisp, code: &str) -> Value {
+    match lisp.eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+fn string(text: &str) -> Value {
+    Value::String(text.to_string())
+}
+
+#[test]
+fn builtin_docs() {
+    let mut lisp = Lisp::new();
+
+    let doc = docs::lookup(&lisp, Symbol::intern("assert")).unwrap();
+    assert_eq!(doc.usage, "(assert cond [message])");
+    assert_eq!(doc.text, Some("Fails with the message if cond is false.".to_string()));
+    assert_eq!(doc.args, Args::Multiple(vec![1, 2]));
+    assert!(doc.builtin);
+
+    assert_eq!(eval(&mut lisp, "(doc 'assert)"),
+               string("(assert cond [message])\nFails with the message if cond is false.\nTakes 1 or 2 arguments."));
+}
+
+#[test]
+fn docstrings_of_defined_functions() {
+    let mut lisp = Lisp::new();
+    eval(&mut lisp, "(def {double x} \"Twice x.\" {* x 2}) (def {greet name &optional (greeting \"hi\")} {str greeting name})");
+
+    let doc = docs::lookup(&lisp, Symbol::intern("double")).unwrap();
+    assert_eq!((&doc.usage[..], doc.text.as_ref().map(|text| &text[..]), doc.builtin), ("(double x)", Some("Twice x."), false));
+    assert_eq!(eval(&mut lisp, "(double 4)"), Value::Number(8.0));
+
+    // Without a docstring there's still the usage
+    assert_eq!(eval(&mut lisp, "(doc 'greet)"), string("(greet name &optional (greeting \"hi\"))\nTakes 1 or 2 arguments."));
+}
+
+#[test]
+fn things_that_arent_functions() {
+    let mut lisp = Lisp::new();
+    eval(&mut lisp, "(def 'x 1)");
+
+    assert_eq!(eval(&mut lisp, "(doc 'x)"), Value::Nil);
+    assert_eq!(eval(&mut lisp, "(doc 'not-defined)"), Value::Nil);
+    assert!(docs::lookup(&lisp, Symbol::intern("x")).is_none());
+    assert!(lisp.eval_raw("(doc 1)").is_err());
+}
+
+#[test]
+fn apropos() {
+    let mut lisp = Lisp::new();
+    eval(&mut lisp, "(def 'assert-thing 1)");
+
+    assert_eq!(eval(&mut lisp, "(apropos \"assert\")"), eval(&mut lisp, "(list 'assert 'assert-equal 'assert-error 'assert-thing)"));
+    assert_eq!(eval(&mut lisp, "(apropos \"no such name\")"), eval(&mut lisp, "(list)"));
+
+    // Names in inner scopes are found too, but only once
+    eval(&mut lisp, "(def {f assert-thing} {apropos \"assert-t\"})");
+    assert_eq!(eval(&mut lisp, "(f 1)"), eval(&mut lisp, "(list 'assert-thing)"));
+    assert!(lisp.eval_raw("(apropos 'assert)").is_err());
+}
+
+#[test]
+fn arity() {
+    let mut lisp = Lisp::new();
+    eval(&mut lisp, "(def {pair a b} {list a b}) (def {opts a &optional b &rest cs} {list a b cs})");
+
+    assert_eq!(eval(&mut lisp, "(arity 'pair)"), string("2 arguments"));
+    assert_eq!(eval(&mut lisp, "(arity pair)"), string("2 arguments"));
+    assert_eq!(eval(&mut lisp, "(arity 'opts)"), string("at least 1 argument"));
+    assert_eq!(eval(&mut lisp, "(arity 'list)"), string("any number of arguments"));
+    assert_eq!(eval(&mut lisp, "(arity (\\ {} {id 1}))"), string("0 arguments"));
+
+    assert!(lisp.eval_raw("(arity 1)").is_err());
+    assert!(lisp.eval_raw("(arity 'not-defined)").is_err());
+    assert_eq!(docs::arity(&Value::Nil), None);
+}
+
+#[test]
+fn markdown_reference() {
+    let mut lisp = Lisp::new();
+    eval(&mut lisp, "(def {double x} \"Twice x.\" {* x 2}) (def 'limit 10) (def 'pipe \"a|b\")");
+
+    let reference = docs::markdown(&lisp.scopes[0], "Reference");
+    assert!(reference.starts_with("# Reference\n\n## Functions\n"), "{}", reference);
+    assert!(reference.contains("\n### `double`\n\n```lisp\n(double x)\n```\n\nTwice x.\n\nTakes 1 argument.\n"), "{}", reference);
+    assert!(reference.contains("\n## Values\n\n| Name | Value |\n| --- | --- |\n"), "{}", reference);
+    assert!(reference.contains("| `limit` | `10` |\n"), "{}", reference);
+    assert!(reference.contains("| `pipe` | `\"a\\|b\"` |\n"), "{}", reference);
+
+    // Functions are sorted, and all come before the values
+    let functions = &reference[..reference.find("## Values").unwrap()];
+    assert!(functions.find("### `apropos`").unwrap() < functions.find("### `double`").unwrap());
+}
+
+#[test]
+fn docs_for_files() {
+    let dir = env::temp_dir().join(format!("rlisp-docs-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("lib.lisp"), "(def {double x} \"Twice x.\" {* x 2})").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlisp")).args(&["--docs", "lib.lisp"]).current_dir(&dir).output().unwrap();
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(out.starts_with("# rlisp reference\n"), "{}", out);
+    assert!(out.contains("### `double`"), "{}", out);
+    assert!(out.contains("### `map`"), "{}", out);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlisp")).args(&["--docs", "missing.lisp"]).current_dir(&dir).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,77 @@
+extern crate rlisp;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use rlisp::format::format_source;
+
+const WIDTH: usize = 80;
+
+fn fmt(code: &str) -> String {
+    format_source(code, WIDTH).unwrap()
+}
+
+const SOURCES: &[&str] = &[
+    "(def {f} (\\ {x} (+ x 1)))",
+    "(def {f} (\\ {x}\n  ; adds one\n  (+ x 1))) ; trailing\n\n\n(f 1)\n",
+    "(if (> x 1) (print \"a very long string that won't fit\" \"on one line with the if\") (seq (print 1) (print 2)))",
+    "{1 2 3 ; three\n 4}",
+    "' ; quoted\n  x",
+    "'  (a   b)",
+    "#u8 ; bytes\n(1 2 3)",
+    "(list #u8(1 2) '#u8(3) #\\a)",
+    "; just a comment",
+    "(a ; one\n ; two\n)",
+];
+
+#[test]
+fn comments_are_kept() {
+    assert_eq!(fmt("' ; quoted\n  x"), "' ; quoted\nx\n");
+    assert_eq!(fmt("#u8 ; bytes\n(1   2)"), "#u8 ; bytes\n(1 2)\n");
+    assert_eq!(fmt("(f ' ; a\n  ; b\n x y)"), "(f ' ; a\n   ; b\n   x\n   y)\n");
+
+    for code in SOURCES {
+        let formatted = fmt(code);
+
+        for comment in code.lines().filter_map(|line| line.find(';').map(|start| line[start..].trim())) {
+            assert!(formatted.contains(comment), "{:?} lost {:?} in {:?}", code, comment, formatted);
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_the_meaning() {
+    for code in SOURCES {
+        assert_eq!(rlisp::parse::parse_str(&fmt(code)), rlisp::parse::parse_str(code), "{:?}", code);
+    }
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for code in SOURCES {
+        let once = fmt(code);
+        assert_eq!(fmt(&once), once, "{:?}", code);
+    }
+}
+
+fn check(code: &str) -> i32 {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlisp-fmt"))
+        .arg("--check")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+    child.wait().unwrap().code().unwrap()
+}
+
+#[test]
+fn check_passes_only_formatted_code() {
+    for code in SOURCES {
+        assert_eq!(check(&fmt(code)), 0, "{:?}", code);
+    }
+
+    assert_eq!(check("(a  b)"), 1);
+    assert_eq!(check("(a b"), 2);
+}
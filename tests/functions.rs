@@ -0,0 +1,89 @@
+extern crate rlisp;
+
+use std::cell::Cell;
+
+use rlisp::{Lisp, Value, FuncResult};
+use rlisp::value::{func, Args};
+
+thread_local!(static CALLS: Cell<usize> = Cell::new(0));
+
+// Counts how many times it's been called, and gives back its argument
+fn counted(mut vals: Vec<Value>, _: &mut Lisp) -> FuncResult {
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+    Ok(vals.remove(0))
+}
+
+fn calls() -> usize {
+    CALLS.with(|calls| calls.get())
+}
+
+fn lisp() -> Lisp {
+    let mut lisp = Lisp::new();
+    lisp.set_global("counted", Value::HardFunc(func(counted, Args::Fixed(1))));
+
+    lisp
+}
+
+fn eval(lisp: &mut Lisp, code: &str) -> Value {
+    match lisp.eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+#[test]
+fn made_functions() {
+    let mut lisp = lisp();
+
+    assert_eq!(eval(&mut lisp, "((partial + 1 2) 3 4)"), Value::Number(10.0));
+    assert_eq!(eval(&mut lisp, "((comp (partial * 2) +) 1 2)"), Value::Number(6.0));
+    assert_eq!(eval(&mut lisp, "((constantly 5) 1 2 3)"), Value::Number(5.0));
+    assert_eq!(eval(&mut lisp, "((juxt + *) 2 3)"), eval(&mut lisp, "{5 6}"));
+}
+
+#[test]
+fn arguments_of_made_functions_are_hidden() {
+    let mut lisp = lisp();
+
+    // A function called by one made with partial can still see a global named args
+    eval(&mut lisp, "(def 'args 'global) (def {see-args x} {list x args})");
+    assert_eq!(eval(&mut lisp, "((partial see-args) 1)"), eval(&mut lisp, "{1 global}"));
+    assert_eq!(eval(&mut lisp, "((memoize see-args) 2)"), eval(&mut lisp, "{2 global}"));
+}
+
+#[test]
+fn memoized_results_are_keyed_by_equal_arguments() {
+    let mut lisp = lisp();
+    eval(&mut lisp, "(def 'f (memoize counted))");
+
+    eval(&mut lisp, "(f {1 (2 \"three\")})");
+    eval(&mut lisp, "(f (list 1 (list 2 \"three\")))");
+    assert_eq!(calls(), 1);
+
+    // Equal numbers that print differently are the same argument
+    eval(&mut lisp, "(f 0)");
+    eval(&mut lisp, "(f (* -1 0))");
+    assert_eq!(calls(), 2);
+
+    // Different values that print the same aren't
+    eval(&mut lisp, "(f \"a\")");
+    eval(&mut lisp, "(f 'a)");
+    eval(&mut lisp, "(f #\\a)");
+    assert_eq!(calls(), 5);
+
+    // Functions can't be hashed, so they're compared with ==
+    eval(&mut lisp, "(f +)");
+    eval(&mut lisp, "(f +)");
+    assert_eq!(calls(), 6);
+}
+
+#[test]
+fn failed_memoized_calls_are_made_again() {
+    let mut lisp = lisp();
+    eval(&mut lisp, "(def 'f (memoize (\\ {x} {+ x 1})))");
+
+    assert!(lisp.eval_raw("(f 'a)").is_err());
+    assert!(lisp.eval_raw("(f 'a)").is_err());
+    assert_eq!(eval(&mut lisp, "(f 1)"), Value::Number(2.0));
+    assert_eq!(lisp.scopes.len(), 1);
+}
@@ -0,0 +1,95 @@
+extern crate rlisp;
+
+use rlisp::Lisp;
+use rlisp::lint::{lint_source, LintKind};
+
+// The kind of each lint along with the code it's about
+fn lints(code: &str) -> Vec<(LintKind, &str)> {
+    lint_source(&Lisp::new(), code).into_iter()
+        .map(|lint| (lint.kind, &code[lint.span.start .. lint.span.end]))
+        .collect()
+}
+
+fn kinds(code: &str) -> Vec<LintKind> {
+    lints(code).into_iter().map(|(kind, _)| kind).collect()
+}
+
+#[test]
+fn clean_code_has_no_lints() {
+    let code = "(def {square x} {* x x})\n(def 'double (\\ {x} {+ x x}))\n(let {(a 1) (b (square a))} {print (double b)})\n(if (> 1 2) {print 1} {print 2})";
+    assert_eq!(lints(code), vec![]);
+}
+
+#[test]
+fn syntax_error() {
+    assert_eq!(lints("(print #\\nope) (a"), vec![
+        (LintKind::SyntaxError, "#\\nope"),
+        (LintKind::SyntaxError, "(a"),
+    ]);
+}
+
+#[test]
+fn arity() {
+    assert_eq!(lints("(head {1} {2})"), vec![(LintKind::Arity, "(head {1} {2})")]);
+    assert_eq!(lints("(def {f a b} {+ a b}) (f 1)"), vec![(LintKind::Arity, "(f 1)")]);
+    assert_eq!(lints("(def 'g (\\ {a} {id a})) (g)"), vec![(LintKind::Arity, "(g)")]);
+    assert_eq!(lints("(def {f a &optional b} {id a}) (f 1 2) (f 1 2 3)"), vec![(LintKind::Arity, "(f 1 2 3)")]);
+}
+
+#[test]
+fn unquoted_def() {
+    assert_eq!(lints("(def x 1)"), vec![(LintKind::UnquotedDef, "x")]);
+    assert_eq!(lints("(def (f x) {id x})"), vec![(LintKind::UnquotedDef, "(f x)")]);
+}
+
+#[test]
+fn unquoted_code() {
+    assert_eq!(lints("(if true (print 1) {print 2})"), vec![(LintKind::UnquotedCode, "(print 1)")]);
+    assert_eq!(lints("(def {f x} (print x))"), vec![(LintKind::UnquotedCode, "(print x)")]);
+    assert_eq!(lints("(\\ (x) {id x})"), vec![(LintKind::UnquotedCode, "(x)")]);
+    assert_eq!(lints("(let {(a 1)} (print a))"), vec![(LintKind::UnquotedCode, "(print a)")]);
+}
+
+#[test]
+fn unreachable() {
+    assert_eq!(lints("(print 1) (exit) (print 2) (print 3)"), vec![(LintKind::Unreachable, "(print 2) (print 3)")]);
+    assert_eq!(lints("(seq (exit 1) (print 2))"), vec![(LintKind::Unreachable, "(print 2)")]);
+}
+
+#[test]
+fn unused_binding() {
+    assert_eq!(lints("(let {(a 1) (b 2)} {print a})"), vec![(LintKind::UnusedBinding, "(b 2)")]);
+    assert_eq!(lints("(let {(_b 2)} {print 1})"), vec![]);
+    assert_eq!(lints("(let {(a 1) (b a)} {print b})"), vec![]);
+}
+
+#[test]
+fn shadowed_builtin() {
+    assert_eq!(lints("(def 'map 1)"), vec![(LintKind::ShadowedBuiltin, "map")]);
+    assert_eq!(lints("(def {f list} {len list})"), vec![(LintKind::ShadowedBuiltin, "list")]);
+    assert_eq!(kinds("(let {(head 1)} {print head})"), vec![LintKind::ShadowedBuiltin]);
+}
+
+#[test]
+fn bindings_only_cover_their_scope() {
+    // Calling a parameter named list can't be checked, but list is still checked everywhere else
+    let code = "(def {f list} {list 1 2 3})\n(def {g x} {head x x})\n(head {1} {2})";
+    assert_eq!(lints(code), vec![
+        (LintKind::ShadowedBuiltin, "list"),
+        (LintKind::Arity, "{head x x}"),
+        (LintKind::Arity, "(head {1} {2})"),
+    ]);
+
+    let code = "(map (\\ {head} {head 1 2}) {})\n(head 1 2)";
+    assert_eq!(lints(code), vec![
+        (LintKind::ShadowedBuiltin, "head"),
+        (LintKind::Arity, "(head 1 2)"),
+    ]);
+
+    let code = "(let {(exit print)} {exit 1 2})\n(print 3)";
+    assert_eq!(kinds(code), vec![LintKind::ShadowedBuiltin]);
+
+    // Let bindings are in scope for the values after them
+    assert_eq!(kinds("(let {(tail head) (x (tail 1 2))} {print x})"), vec![LintKind::ShadowedBuiltin]);
+    assert_eq!(kinds("(let {(x (tail 1 2)) (tail head)} {print x tail})"), vec![LintKind::Arity, LintKind::ShadowedBuiltin]);
+}
@@ -0,0 +1,100 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value};
+
+fn eval(code: &str) -> Value {
+    match Lisp::new().eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+fn check(code: &str, expected: &str) {
+    assert_eq!(eval(code), eval(expected), "{}", code);
+}
+
+fn check_err(code: &str) {
+    assert!(Lisp::new().eval_raw(code).is_err(), "{} should fail", code);
+}
+
+#[test]
+fn reorder_and_join() {
+    check("(reverse {1 2 3})", "{3 2 1}");
+    check("(reverse {})", "{}");
+    check("(append {1 2} {} {3})", "{1 2 3}");
+    check("(concat {1} {2 3})", "{1 2 3}");
+    check("(flatten {1 (2 (3 4)) () 5})", "{1 2 3 4 5}");
+    check("(zip {1 2 3} {a b})", "{(1 a) (2 b)}");
+}
+
+#[test]
+fn indexing() {
+    check("(nth {a b c} 1)", "'b");
+    check("(last {a b c})", "'c");
+    check("(index-of 'b {a b c b})", "1");
+    check("(index-of 'z {a b c})", "nil");
+    check_err("(nth {a b c} 3)");
+    check_err("(nth {a b c} -1)");
+}
+
+#[test]
+fn take_and_drop() {
+    check("(take 2 {1 2 3})", "{1 2}");
+    check("(take 5 {1 2 3})", "{1 2 3}");
+    check("(drop 2 {1 2 3})", "{3}");
+    check("(drop 5 {1 2 3})", "{}");
+    check("(take-while (\\ {x} {< x 3}) {1 2 3 1})", "{1 2}");
+    check("(drop-while (\\ {x} {< x 3}) {1 2 3 1})", "{3 1}");
+}
+
+#[test]
+fn searching() {
+    check("(find (\\ {x} {> x 1}) {1 2 3})", "2");
+    check("(find (\\ {x} {> x 5}) {1 2 3})", "nil");
+    check("(any? (\\ {x} {> x 2}) {1 2 3})", "true");
+    check("(any? (\\ {x} {> x 2}) {})", "false");
+    check("(every? (\\ {x} {> x 0}) {1 2 3})", "true");
+    check("(every? (\\ {x} {> x 1}) {1 2 3})", "false");
+    check("(count (\\ {x} {> x 1}) {1 2 3})", "2");
+}
+
+#[test]
+fn distinct_keeps_first_seen_order() {
+    check("(distinct {3 1 3 2 1})", "{3 1 2}");
+    check("(distinct {(1 2) (1 2) \"a\" \"a\" a #\\a})", "{(1 2) \"a\" a #\\a}");
+    check("(distinct (list 0 (- 0 0) + + -))", "(list 0 + -)");
+}
+
+#[test]
+fn grouping() {
+    check("(frequencies {a b a c a b})", "{(a 3) (b 2) (c 1)}");
+    check("(frequencies {})", "{}");
+    check("(group-by (\\ {x} {> x 2}) {1 3 2 4})", "(list (list false {1 2}) (list true {3 4}))");
+    check("(partition (\\ {x} {> x 2}) {1 3 2 4})", "{(3 4) (1 2)}");
+}
+
+#[test]
+fn large_lists_are_fast_enough() {
+    // Each of these would take seconds if every element was compared with every other one
+    check("(len (distinct (range 0 50000)))", "50000");
+    check("(len (frequencies (range 0 50000)))", "50000");
+    check("(len (group-by id (range 0 50000)))", "50000");
+    check("(len (distinct (map (\\ {x} {list x x}) (range 0 20000))))", "20000");
+}
+
+#[test]
+fn sorting() {
+    check("(sort {3 1 2})", "{1 2 3}");
+    check("(sort {\"b\" \"a\"})", "{\"a\" \"b\"}");
+    check("(sort > {3 1 2})", "{3 2 1}");
+    check("(sort-by (\\ {pair} {head pair}) {(2 a) (1 b) (2 c) (1 d)})", "{(1 b) (1 d) (2 a) (2 c)}");
+    check("(sort (\\ {a b} {< (head a) (head b)}) {(2 a) (1 b) (2 c) (1 d)})", "{(1 b) (1 d) (2 a) (2 c)}");
+    check_err("(sort {1 \"a\"})");
+}
+
+#[test]
+fn mapping_and_reducing() {
+    check("(map + {1 2 3} {10 20})", "{11 22}");
+    check("(map (\\ {x} {* x 2}) {1 2 3})", "{2 4 6}");
+    check("(reduce + {1 2 3 4})", "10");
+}
@@ -0,0 +1,164 @@
+extern crate rlisp;
+
+use std::io::{Cursor, ErrorKind, BufReader};
+use std::process::{Command, Stdio};
+
+use rlisp::json::{self, Json};
+use rlisp::lsp::{Server, read_message, write_message, MAX_MESSAGE_LENGTH};
+
+const URI: &'static str = "file:///nowhere/main.lisp";
+const TEXT: &'static str = "(def {square x}\n  \"Multiplies x by itself\"\n  {* x x})\n(square 3)\n(squ";
+
+fn request(id: usize, method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn at(line: usize, character: usize) -> Json {
+    Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", URI.into())])),
+        ("position", Json::object(vec![("line", line.into()), ("character", character.into())])),
+    ])
+}
+
+// Runs the server over the messages, giving back its exit code and everything it sent
+fn session(messages: &[Json]) -> (i32, Vec<Json>) {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+
+    let mut output = Vec::new();
+    let code = Server::new().run(Cursor::new(input), &mut output).unwrap();
+
+    let mut replies = Vec::new();
+    let mut output = Cursor::new(output);
+    while let Some(body) = read_message(&mut output).unwrap() {
+        replies.push(json::parse(&body).unwrap());
+    }
+
+    (code, replies)
+}
+
+fn reply(replies: &[Json], id: usize) -> &Json {
+    replies.iter()
+        .find(|reply| reply.get("id").and_then(Json::as_usize) == Some(id))
+        .unwrap_or_else(|| panic!("no reply to {}", id))
+}
+
+#[test]
+fn scripted_session() {
+    let open = Json::object(vec![
+        ("textDocument", Json::object(vec![
+            ("uri", URI.into()),
+            ("languageId", "rlisp".into()),
+            ("version", 1.into()),
+            ("text", TEXT.into()),
+        ])),
+    ]);
+
+    let (code, replies) = session(&[
+        request(1, "initialize", Json::object(vec![("capabilities", Json::object(vec![]))])),
+        notification("initialized", Json::object(vec![])),
+        notification("textDocument/didOpen", open),
+        request(2, "textDocument/hover", at(3, 2)),
+        request(3, "textDocument/definition", at(3, 2)),
+        request(4, "textDocument/completion", at(4, 4)),
+        request(5, "textDocument/unknown", Json::object(vec![])),
+        request(6, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+    ]);
+
+    assert_eq!(code, 0);
+
+    let capabilities = reply(&replies, 1).at(&["result", "capabilities"]).unwrap();
+    assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+    assert_eq!(capabilities.get("definitionProvider"), Some(&Json::Bool(true)));
+
+    // Opening the document publishes its diagnostics
+    let published = replies.iter()
+        .find(|reply| reply.get("method").and_then(Json::as_str) == Some("textDocument/publishDiagnostics"))
+        .unwrap();
+    assert_eq!(published.at(&["params", "uri"]).and_then(Json::as_str), Some(URI));
+    assert!(!published.at(&["params", "diagnostics"]).and_then(Json::as_array).unwrap().is_empty());
+
+    let hover = reply(&replies, 2).at(&["result", "contents", "value"]).and_then(Json::as_str).unwrap();
+    assert!(hover.contains("(square x)"), "{}", hover);
+    assert!(hover.contains("Multiplies x by itself"), "{}", hover);
+
+    let locations = reply(&replies, 3).get("result").and_then(Json::as_array).unwrap();
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].get("uri").and_then(Json::as_str), Some(URI));
+    assert_eq!(locations[0].at(&["range", "start", "line"]).and_then(Json::as_usize), Some(0));
+    assert_eq!(locations[0].at(&["range", "start", "character"]).and_then(Json::as_usize), Some(6));
+
+    let completions = reply(&replies, 4).at(&["result", "items"]).and_then(Json::as_array).unwrap();
+    let labels: Vec<&str> = completions.iter().filter_map(|item| item.get("label").and_then(Json::as_str)).collect();
+    assert!(labels.contains(&"square"), "{:?}", labels);
+    assert!(labels.iter().all(|label| label.starts_with("squ")), "{:?}", labels);
+
+    assert!(reply(&replies, 5).get("error").is_some());
+    assert_eq!(reply(&replies, 6).get("result"), Some(&Json::Null));
+}
+
+#[test]
+fn binary_serves_over_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlisp-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        write_message(stdin, &request(1, "initialize", Json::object(vec![]))).unwrap();
+        write_message(stdin, &request(2, "textDocument/hover", at(0, 0))).unwrap();
+        write_message(stdin, &request(3, "shutdown", Json::Null)).unwrap();
+        write_message(stdin, &notification("exit", Json::Null)).unwrap();
+    }
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut replies = Vec::new();
+    while let Some(body) = read_message(&mut stdout).unwrap() {
+        replies.push(json::parse(&body).unwrap());
+    }
+
+    assert_eq!(child.wait().unwrap().code(), Some(0));
+    assert!(reply(&replies, 1).at(&["result", "capabilities"]).is_some());
+    assert_eq!(reply(&replies, 2).get("result"), Some(&Json::Null));
+    assert_eq!(reply(&replies, 3).get("result"), Some(&Json::Null));
+}
+
+#[test]
+fn exit_without_shutdown_is_an_error() {
+    let (code, replies) = session(&[notification("exit", Json::Null)]);
+
+    assert_eq!(code, 1);
+    assert!(replies.is_empty());
+}
+
+#[test]
+fn oversized_messages_are_refused() {
+    let header = format!("Content-Length: {}\r\n\r\n{{}}", MAX_MESSAGE_LENGTH + 1);
+    let err = read_message(&mut Cursor::new(header)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // Nothing is allocated for a length that could never be sent
+    let header = format!("Content-Length: {}\r\n\r\n", usize::max_value());
+    assert!(read_message(&mut Cursor::new(header)).is_err());
+
+    let err = Server::new().run(Cursor::new(format!("Content-Length: {}\r\n\r\n", 1u64 << 40)), Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,119 @@
+extern crate rlisp;
+
+use std::f32;
+
+use rlisp::{Lisp, Value, FuncError};
+
+fn eval(code: &str) -> Value {
+    match Lisp::new().eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+fn num(code: &str) -> f32 {
+    match eval(code) {
+        Value::Number(num) => num,
+        other => panic!("{} gave {:?}", code, other),
+    }
+}
+
+fn close(code: &str, expected: f32) {
+    let found = num(code);
+    assert!((found - expected).abs() < 1e-5, "{} gave {}, expected {}", code, found, expected);
+}
+
+#[test]
+fn division_and_remainders() {
+    assert_eq!(num("(/ 7 2)"), 3.5);
+    assert_eq!(num("(/ 100 5 2)"), 10.0);
+
+    // mod takes the sign of the divisor, rem the sign of the dividend
+    assert_eq!(num("(mod 7 3)"), 1.0);
+    assert_eq!(num("(mod -7 3)"), 2.0);
+    assert_eq!(num("(mod 7 -3)"), -2.0);
+    assert_eq!(num("(rem -7 3)"), -1.0);
+    assert_eq!(num("(rem 7 -3)"), 1.0);
+    assert_eq!(num("(quot 7 2)"), 3.0);
+    assert_eq!(num("(quot -7 2)"), -3.0);
+}
+
+#[test]
+fn dividing_by_zero_is_an_error() {
+    for code in &["(/ 1 0)", "(/ 10 2 0)", "(mod 1 0)", "(rem 1 0)", "(quot 1 0)"] {
+        match Lisp::new().eval_raw(code) {
+            Err(FuncError::DivisionByZero) => (),
+            other => panic!("{} gave {:?}", code, other),
+        }
+    }
+
+    // Zero can still be divided
+    assert_eq!(num("(/ 0 5)"), 0.0);
+}
+
+#[test]
+fn rounding() {
+    assert_eq!(num("(floor -1.5)"), -2.0);
+    assert_eq!(num("(ceil -1.5)"), -1.0);
+    assert_eq!(num("(round 2.5)"), 3.0);
+    assert_eq!(num("(round -2.5)"), -3.0);
+    assert_eq!(num("(truncate -1.7)"), -1.0);
+    assert_eq!(num("(abs -3)"), 3.0);
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(num("(min 3 1 2)"), 1.0);
+    assert_eq!(num("(max 3 1 2)"), 3.0);
+    assert_eq!(num("(min 4)"), 4.0);
+    assert!(Lisp::new().eval_raw("(min)").is_err());
+    assert!(Lisp::new().eval_raw("(max 1 \"2\")").is_err());
+}
+
+#[test]
+fn powers_and_logs() {
+    assert_eq!(num("(sqrt 16)"), 4.0);
+    assert_eq!(num("(expt 2 10)"), 1024.0);
+    close("(expt 9 0.5)", 3.0);
+    close("(exp 1)", f32::consts::E);
+    close("(log e)", 1.0);
+    close("(log 8 2)", 3.0);
+    close("(log 1000 10)", 3.0);
+}
+
+#[test]
+fn trig() {
+    close("(sin 0)", 0.0);
+    close("(cos pi)", -1.0);
+    close("(tan (/ pi 4))", 1.0);
+    close("(atan2 1 1)", f32::consts::PI / 4.0);
+    close("(atan2 1 -1)", 3.0 * f32::consts::PI / 4.0);
+}
+
+#[test]
+fn constants() {
+    assert_eq!(num("pi"), f32::consts::PI);
+    assert_eq!(num("e"), f32::consts::E);
+}
+
+#[test]
+fn special_values() {
+    assert_eq!(eval("(nan? (sqrt -1))"), Value::Bool(true));
+    assert_eq!(eval("(nan? 1)"), Value::Bool(false));
+    assert_eq!(eval("(infinite? (expt 10 100))"), Value::Bool(true));
+    assert_eq!(eval("(finite? (expt 10 100))"), Value::Bool(false));
+    assert_eq!(eval("(finite? 1)"), Value::Bool(true));
+}
+
+#[test]
+fn variadic_comparisons() {
+    assert_eq!(eval("(list (< 1 2 3) (< 1 3 2) (< 1 1 2))"), eval("(list true false false)"));
+    assert_eq!(eval("(list (<= 1 1 2) (<= 1 2 1))"), eval("(list true false)"));
+    assert_eq!(eval("(list (> 3 2 1) (> 3 1 2))"), eval("(list true false)"));
+    assert_eq!(eval("(list (>= 2 2 1) (>= 1 2 2))"), eval("(list true false)"));
+    assert_eq!(eval("(list (= 1 1 1) (= 1 1 2))"), eval("(list true false)"));
+    assert_eq!(eval("(list (!= 1 2) (!= 1 1))"), eval("(list true false)"));
+
+    assert!(Lisp::new().eval_raw("(< 1)").is_err());
+    assert!(Lisp::new().eval_raw("(< 1 \"a\")").is_err());
+}
@@ -0,0 +1,135 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Backend, Value, FuncError, FuncResult};
+use rlisp::value::Args;
+
+const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
+
+// Runs the code with each backend, checking that they agree and that no scopes were left behind
+fn run(code: &str) -> FuncResult {
+    let mut results = Vec::new();
+
+    for &backend in BACKENDS.iter() {
+        let mut lisp = Lisp::new();
+        lisp.set_backend(backend);
+
+        let result = lisp.eval_raw(code);
+        assert_eq!(lisp.scopes.len(), 1, "{} left scopes behind with {:?}", code, backend);
+
+        results.push(result);
+    }
+
+    let compiled = results.pop().unwrap();
+    let walked = results.pop().unwrap();
+    assert_eq!(format!("{:?}", walked), format!("{:?}", compiled), "the backends disagree on {}", code);
+
+    walked
+}
+
+fn check(code: &str, expected: &str) {
+    let expected = Lisp::new().eval_raw(expected).unwrap();
+
+    match run(code) {
+        Ok(value) => assert_eq!(value, expected, "{}", code),
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+fn arity_error(code: &str) -> (Args, usize) {
+    match run(code) {
+        Err(FuncError::InvalidArguments { expected, got }) => (expected, got),
+        result => panic!("{} should have the wrong number of arguments, but gave {:?}", code, result),
+    }
+}
+
+#[test]
+fn optional() {
+    let f = "(def {f a &optional b (c 3)} {list a b c})";
+
+    check(&format!("{} (f 1)", f), "(list 1 nil 3)");
+    check(&format!("{} (f 1 2)", f), "(list 1 2 3)");
+    check(&format!("{} (f 1 2 4)", f), "(list 1 2 4)");
+
+    // Defaults can use the parameters before them
+    check("(def {f a &optional (b (* a 2))} {list a b}) (f 4)", "(list 4 8)");
+}
+
+#[test]
+fn rest() {
+    check("(def {f a &rest more} {list a more}) (f 1)", "(list 1 {})");
+    check("(def {f a &rest more} {list a more}) (f 1 2 3)", "(list 1 {2 3})");
+    check("(def {f &optional (a 1) &rest more} {list a more}) (f)", "(list 1 {})");
+}
+
+#[test]
+fn keys() {
+    let f = "(def {f text &key (sep \",\") end} {list text sep end})";
+
+    check(&format!("{} (f \"a\")", f), "(list \"a\" \",\" nil)");
+    check(&format!("{} (f \"a\" 'end \"!\")", f), "(list \"a\" \",\" \"!\")");
+    check(&format!("{} (f \"a\" 'end \"!\" 'sep \";\")", f), "(list \"a\" \";\" \"!\")");
+
+    // With &rest too, the rest gets the keys as well
+    check("(def {f &rest all &key k} {list all k}) (f 'k 1)", "(list {k 1} 1)");
+}
+
+#[test]
+fn arity_is_reported() {
+    assert_eq!(arity_error("(def {f a} {id a}) (f 1 2)"), (Args::Fixed(1), 2));
+    assert_eq!(arity_error("(def {f a &optional b} {id a}) (f)"), (Args::Multiple(vec![1, 2]), 0));
+    assert_eq!(arity_error("(def {f a &optional b} {id a}) (f 1 2 3)"), (Args::Multiple(vec![1, 2]), 3));
+    assert_eq!(arity_error("(def {f a b &rest c} {id a}) (f 1)"), (Args::Atleast(2), 1));
+    assert_eq!(arity_error("(def {f a &key k j} {id a}) (f 1 'k)"), (Args::Multiple(vec![1, 3, 5]), 2));
+    assert_eq!(arity_error("(def {f &optional a &key k} {id a}) (f 1 2 3 4 5)"), (Args::Multiple(vec![0, 1, 3]), 5));
+
+    // Patterns take one argument each
+    assert_eq!(arity_error("(def {f (a b) c} {id a}) (f {1 2})"), (Args::Multiple(vec![2]), 1));
+    assert_eq!(arity_error("((\\ {x} {id x}))"), (Args::Fixed(1), 0));
+}
+
+#[test]
+fn bad_keys() {
+    let f = "(def {f &key a} {id a})";
+
+    match run(&format!("{} (f 'b 1)", f)) {
+        Err(FuncError::UnknownKeyword(key)) => assert_eq!(format!("{:?}", key), "b"),
+        result => panic!("gave {:?}", result),
+    }
+
+    match run(&format!("{} (f \"a\" 1)", f)) {
+        Err(FuncError::UnknownKeyword(Value::String(ref key))) if key == "a" => (),
+        result => panic!("gave {:?}", result),
+    }
+
+    // Without &rest a key missing its value is caught by the arity check
+    match run("(def {f &rest r &key a} {id a}) (f 'a)") {
+        Err(FuncError::MissingKeywordValue(key)) => assert_eq!(key.as_str(), "a"),
+        result => panic!("gave {:?}", result),
+    }
+}
+
+#[test]
+fn failing_defaults() {
+    match run("(def {f &optional (a (undefined))} {id a}) (f)") {
+        Err(FuncError::UndeclaredSymbol(sym)) => assert_eq!(sym.as_str(), "undefined"),
+        result => panic!("gave {:?}", result),
+    }
+
+    match run("(def {f &key (k (+ 1 \"x\"))} {id k}) (f)") {
+        Err(FuncError::InvalidType { .. }) => (),
+        result => panic!("gave {:?}", result),
+    }
+
+    // A default isn't evaluated if its argument is given
+    check("(def {f &optional (a (undefined))} {id a}) (f 1)", "1");
+}
+
+#[test]
+fn bad_parameter_lists() {
+    for params in &["{&rest}", "{&key a &optional b}", "{&rest a b}", "{&optional a &optional b}", "{a a}", "{&optional (a 1 2)}", "{1}"] {
+        match run(&format!("(\\ {} {{id 1}})", params)) {
+            Err(FuncError::InvalidParameter(_)) | Err(FuncError::InvalidPattern(_)) => (),
+            result => panic!("{} gave {:?}", params, result),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+extern crate rlisp;
+
+use rlisp::{Value, ParseError};
+use rlisp::parse::{parse_str, parse_recovering, parse_cst, CstElement, Diagnostic, Span};
+
+fn string(code: &str) -> Result<Value, ParseError> {
+    parse_str(code).map(|mut values| values.remove(0))
+}
+
+fn diagnostics(code: &str) -> Vec<(ParseError, &str)> {
+    let (_, diagnostics) = parse_recovering(code);
+
+    diagnostics.into_iter()
+        .map(|Diagnostic { error, span }| (error, &code[span.start .. span.end]))
+        .collect()
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(string(r#""a\nb\tc\rd""#), Ok(Value::String("a\nb\tc\rd".to_string())));
+    assert_eq!(string(r#""C:\\dir\\""#), Ok(Value::String(r"C:\dir\".to_string())));
+    assert_eq!(string(r#""say \"hi\"""#), Ok(Value::String(r#"say "hi""#.to_string())));
+
+    // An escaped backslash isn't the start of another escape
+    assert_eq!(string(r#""\\n""#), Ok(Value::String(r"\n".to_string())));
+    assert_eq!(string(r#""\\\"""#), Ok(Value::String(r#"\""#.to_string())));
+    assert_eq!(string(r#""no escapes""#), Ok(Value::String("no escapes".to_string())));
+}
+
+#[test]
+fn unknown_escapes_are_kept() {
+    assert_eq!(string(r#""\q""#), Ok(Value::String(r"\q".to_string())));
+    assert_eq!(string(r#""\d+\s*""#), Ok(Value::String(r"\d+\s*".to_string())));
+    assert_eq!(string(r#""\é""#), Ok(Value::String(r"\é".to_string())));
+}
+
+#[test]
+fn escaped_quotes_dont_end_strings() {
+    assert_eq!(string(r#""ends in \""#), Err(ParseError::UnterminatedString));
+    assert_eq!(string(r#""ends in \"#), Err(ParseError::UnterminatedString));
+
+    let values = parse_str(r#"(list "a \" b" "c")"#).unwrap();
+    assert_eq!(values.len(), 1);
+    assert_eq!(format!("{:?}", values[0]), r#"(list "a \" b" "c")"#);
+}
+
+#[test]
+fn printed_strings_read_back() {
+    let value = Value::String("a \"quoted\" \\ string\n".to_string());
+    assert_eq!(string(&format!("{:?}", value)), Ok(value));
+}
+
+#[test]
+fn unterminated_strings_are_reported_with_their_span() {
+    let code = r#"(print "fine" "not \"done\")"#;
+
+    assert_eq!(diagnostics(code), vec![
+        (ParseError::UnclosedList, code),
+        (ParseError::UnterminatedString, r#""not \"done\")"#),
+    ]);
+}
+
+#[test]
+fn every_error_is_reported() {
+    let code = "(a #zz) )\n(b \"\\x\"\n";
+
+    assert_eq!(diagnostics(code), vec![
+        (ParseError::InvalidHexLiteral, "#zz"),
+        (ParseError::InvalidListDelimitter, ")"),
+        (ParseError::UnclosedList, "(b \"\\x\"\n"),
+    ]);
+}
+
+#[test]
+fn forms_around_errors_are_kept() {
+    let (nodes, diagnostics) = parse_recovering("(+ 1 2) #zzz (+ 3 4)");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].span, Span::new(8, 12));
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[2].to_value(), parse_str("(+ 3 4)").map(|mut values| values.remove(0)));
+}
+
+// Printing the CST gives back exactly the source it was parsed from, whether or not the source is valid
+fn round_trips(code: &str) -> bool {
+    let (cst, diagnostics) = parse_cst(code);
+    assert_eq!(cst.to_string(), code);
+
+    diagnostics.is_empty()
+}
+
+#[test]
+fn cst_round_trips() {
+    for code in VALID.iter() {
+        assert!(round_trips(code), "{:?} should parse", code);
+    }
+
+    for code in INVALID.iter() {
+        assert!(!round_trips(code), "{:?} shouldn't parse", code);
+    }
+}
+
+const VALID: &'static [&'static str] = &[
+    "",
+    "   \n\n",
+    "(+ 1 2)",
+    "; a comment\n(def {f x} ; trailing\n  {* x x})\n\n\n; after blank lines\n",
+    "; no newline at the end",
+    "(print \"a string\\n\" \"(not a list)\" \"; not a comment\")",
+    "#u8(1 2 #ff)  #u8 ; between\n(3)",
+    "(list #\\a #\\space #\\( #\\; #\\u{1F600})",
+    "'x ' ; quoted\n  (a . b) '{1 2}",
+    "(\u{e9}t\u{e9} \"\u{1F600}\")",
+    "(print \"say \\\"hi\\\" ; (not a comment)\" \"\\d+\")",
+    "(a\r\n  b) ; crlf\r\n\r\n(c)\r\n",
+    "\t(a\t\tb)\t",
+];
+
+const INVALID: &'static [&'static str] = &[
+    "(a (b c)",
+    "(a \"unterminated",
+    "{1 2",
+    "#u8(1 300)",
+    "#u8",
+    "'",
+    ")) (a)",
+    "(a }",
+    "(#zz \"\\q\")",
+    "(a \"escaped \\\")",
+    "(a\r\n (b\r\n",
+    "#\\nope (x)",
+];
+
+// Each element's span is the text it prints as, and the children of a node follow on from each other
+fn check_spans(code: &str, elements: &[CstElement], start: usize) -> usize {
+    let mut end = start;
+
+    for element in elements {
+        let span = element.span();
+        assert_eq!(span.start, end, "{:?} has a gap before {:?}", code, element);
+        assert_eq!(&code[span.start .. span.end], element.to_string());
+
+        if let CstElement::Node(ref node) = *element {
+            assert_eq!(check_spans(code, &node.children, span.start), span.end);
+        }
+
+        end = span.end;
+    }
+
+    end
+}
+
+#[test]
+fn cst_spans_cover_the_source() {
+    for code in VALID.iter().chain(INVALID.iter()) {
+        let (cst, _) = parse_cst(code);
+        assert_eq!(check_spans(code, &cst.elements, 0), code.len(), "{:?}", code);
+    }
+}
@@ -0,0 +1,111 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value, FuncError, FuncResult};
+
+fn run(code: &str) -> FuncResult {
+    Lisp::new().eval_raw(code)
+}
+
+fn eval(code: &str) -> Value {
+    match run(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+fn check(code: &str, expected: &str) {
+    assert_eq!(eval(code), eval(expected), "{}", code);
+}
+
+// The pattern and value of the mismatch, along with its message
+fn mismatch(code: &str) -> (String, Value, String) {
+    match run(code) {
+        Err(err @ FuncError::PatternMismatch { .. }) => {
+            let message = format!("{:?}", err);
+            match err {
+                FuncError::PatternMismatch { pattern, value } => (pattern, value, message),
+                _ => unreachable!(),
+            }
+        },
+        result => panic!("{} should fail to match, but gave {:?}", code, result),
+    }
+}
+
+fn invalid(code: &str) {
+    match run(code) {
+        Err(FuncError::InvalidPattern(_)) | Err(FuncError::InvalidParameter(_)) => (),
+        result => panic!("{} should be rejected, but gave {:?}", code, result),
+    }
+}
+
+#[test]
+fn lists() {
+    check("(let {((a b . rest) {1 2 3 4})} {list a b rest})", "{1 2 (3 4)}");
+    check("(let {((x (y) . more) {1 (2) 3 4})} {list x y more})", "{1 2 (3 4)}");
+    check("(let {((a &rest b) {1})} {list a b})", "{1 ()}");
+    check("(seq (def {(first _ third)} {1 2 3}) {list first third})", "{1 3}");
+    check("(seq (def {swap (a b)} {list b a}) {swap {1 2}})", "{2 1}");
+}
+
+#[test]
+fn keys() {
+    check("(let {((&keys x y) {(y 2) (x 1)})} {list x y})", "{1 2}");
+    check("(let {((&keys (n \"count\") missing) {(\"count\" 3)})} {list n missing})", "(list 3 nil)");
+    check("(let {((&keys (a 1) (b 1)) {(1 first) (1 second)})} {list a b})", "{first first}");
+    check("(let {((&keys x) {})} {list x})", "(list nil)");
+
+    // Key patterns can be nested inside list patterns, and used as parameters
+    check("(let {((name (&keys age)) {ann ((age 30))})} {list name age})", "{ann 30}");
+    check("(seq (def {age-of (&keys age)} {list age}) {age-of (frequencies {age age})})", "{2}");
+}
+
+#[test]
+fn underscore_can_be_repeated() {
+    check("(seq (def {second _ x _} {list x}) {second 1 2 3})", "{2}");
+    check("((\\ {_ (_ y)} {list y}) 1 {2 3})", "{3}");
+    check("(let {((_ _ z) {1 2 3})} {list z})", "{3}");
+}
+
+#[test]
+fn repeated_names_are_rejected() {
+    invalid("(let {((a a) {1 2})} {a})");
+    invalid("(\\ {x x} {x})");
+    invalid("(\\ {x (y x)} {x})");
+    invalid("(let {((&keys a a) {})} {a})");
+}
+
+#[test]
+fn malformed_patterns_are_rejected() {
+    invalid("(let {((a .) {1})} {a})");
+    invalid("(let {((a . b c) {1})} {a})");
+    invalid("(let {((1 b) {1 2})} {b})");
+    invalid("(let {((&keys 1) {})} {1})");
+    invalid("(let {((&keys (a b c)) {})} {a})");
+}
+
+#[test]
+fn mismatches_say_why() {
+    let (pattern, value, message) = mismatch("(let {((a b) {1 2 3})} {a})");
+    assert_eq!(pattern, "(a b)");
+    assert_eq!(value, eval("{1 2 3}"));
+    assert!(message.contains("it has 3 items"), "{}", message);
+
+    let (pattern, _, message) = mismatch("(let {((a b . c) {1})} {a})");
+    assert_eq!(pattern, "(a b . c)");
+    assert!(message.contains("it has 1 items"), "{}", message);
+
+    let (pattern, value, message) = mismatch("(let {((a (b c)) {1 2})} {a})");
+    assert_eq!(pattern, "(b c)");
+    assert_eq!(value, Value::Number(2.0));
+    assert!(message.contains("rather than a list"), "{}", message);
+
+    let (pattern, _, message) = mismatch("(let {((&keys (n \"n\") x) {(x 1) (y)})} {x})");
+    assert_eq!(pattern, "(&keys (n \"n\") x)");
+    assert!(message.contains("(key value) pairs"), "{}", message);
+
+    let (_, _, message) = mismatch("(seq (def {f (&keys x)} {x}) (f 5))");
+    assert!(message.contains("(key value) pairs"), "{}", message);
+
+    mismatch("(def {(a b)} {1})");
+    mismatch("((\\ {(a b)} {a}) {1 2 3})");
+}
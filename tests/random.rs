@@ -0,0 +1,66 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value, FuncError, FuncResult};
+
+fn run(lisp: &mut Lisp, code: &str) -> FuncResult {
+    lisp.eval_raw(code)
+}
+
+fn numbers(seed: &str) -> Value {
+    run(&mut Lisp::new(), &format!("(seq (seed-random! {}) {{map (\\ {{_}} {{random-int 1000000}}) (range 0 5)}})", seed)).unwrap()
+}
+
+fn inexact(code: &str) -> f32 {
+    match run(&mut Lisp::new(), code) {
+        Err(FuncError::InexactInteger(num)) => num,
+        result => panic!("{} gave {:?}", code, result),
+    }
+}
+
+#[test]
+fn seeds_repeat_the_same_numbers() {
+    assert_eq!(numbers("42"), numbers("42"));
+    assert!(numbers("42") != numbers("43"));
+    assert!(numbers("16777215") != numbers("16777216"));
+    assert!(numbers("-16777216") != numbers("16777216"));
+}
+
+#[test]
+fn seeds_that_would_collide_are_refused() {
+    // 16777217 can't be written as an f32, it's read as 16777216
+    assert_eq!(inexact("(seed-random! 16777218)"), 16777218.0);
+    assert_eq!(inexact("(seed-random! -20000000)"), -20000000.0);
+    assert_eq!(inexact("(seed-random! 1.5)"), 1.5);
+}
+
+#[test]
+fn random_ints_stay_in_range() {
+    let mut lisp = Lisp::new();
+    run(&mut lisp, "(seed-random! 1)").unwrap();
+
+    for code in &["(random-int 10)", "(random-int -5 5)", "(random-int 16777215 16777216)", "(random-int -16777216 16777216)"] {
+        for _ in 0..100 {
+            let num = match run(&mut lisp, code).unwrap() {
+                Value::Number(num) => num,
+                other => panic!("{} gave {:?}", code, other),
+            };
+
+            assert_eq!(num.fract(), 0.0);
+            assert!(num >= -16777216.0 && num < 16777216.0, "{} gave {}", code, num);
+        }
+    }
+
+    assert_eq!(run(&mut lisp, "(random-int 16777215 16777216)").unwrap(), Value::Number(16777215.0));
+    assert_eq!(run(&mut lisp, "(random-int 2.5 3.5)").unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn ranges_too_big_to_be_exact_are_refused() {
+    assert_eq!(inexact("(random-int 20000000)"), 20000000.0);
+    assert_eq!(inexact("(random-int -20000000 0)"), -20000000.0);
+
+    match run(&mut Lisp::new(), "(random-int 5 5)") {
+        Err(FuncError::EmptyRange { .. }) => (),
+        result => panic!("gave {:?}", result),
+    }
+}
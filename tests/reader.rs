@@ -0,0 +1,129 @@
+extern crate rlisp;
+
+use std::io::Cursor;
+
+use rlisp::{Reader, Value, ParseError};
+use rlisp::parse::parse_str;
+use rlisp::reader::Next;
+
+fn value(code: &str) -> Value {
+    parse_str(code).unwrap().remove(0)
+}
+
+// Feeds all of `code` at once and reads every form out of it
+fn read_all(code: &str) -> Vec<Result<Value, ParseError>> {
+    let mut reader = Reader::new();
+    reader.feed(code);
+    reader.finish();
+
+    let mut forms = Vec::new();
+    loop {
+        match reader.next_form() {
+            Ok(Next::Form(form)) => forms.push(Ok(form)),
+            Ok(Next::NeedMoreInput) => return forms,
+            Err(err) => forms.push(Err(err)),
+        }
+    }
+}
+
+#[test]
+fn forms_are_handed_out_once_complete() {
+    let mut reader = Reader::new();
+
+    reader.feed("(+ 1");
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.feed(" 2) (list");
+    assert_eq!(reader.next_form(), Ok(Next::Form(value("(+ 1 2)"))));
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.feed(" 3)\n");
+    assert_eq!(reader.next_form(), Ok(Next::Form(value("(list 3)"))));
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn atoms_wait_for_a_delimiter() {
+    let mut reader = Reader::new();
+
+    reader.feed("12");
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.feed("34 ");
+    assert_eq!(reader.next_form(), Ok(Next::Form(Value::Number(1234.0))));
+
+    reader.feed("abc");
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.finish();
+    assert_eq!(reader.next_form(), Ok(Next::Form(value("abc"))));
+}
+
+#[test]
+fn unclosed_forms_are_errors_once_finished() {
+    assert_eq!(read_all("(+ 1 2"), vec![Err(ParseError::UnclosedList)]);
+    assert_eq!(read_all("\"abc"), vec![Err(ParseError::UnterminatedString)]);
+}
+
+#[test]
+fn non_ascii_atoms() {
+    assert_eq!(read_all("x à"), vec![Ok(value("x")), Ok(value("à"))]);
+    assert_eq!(read_all("'à"), vec![Ok(value("'à"))]);
+    assert_eq!(read_all("(list 'é 'ü) naïve"), vec![Ok(value("(list 'é 'ü)")), Ok(value("naïve"))]);
+    assert_eq!(read_all("#\\é #\\λ"), vec![Ok(Value::Char('é')), Ok(Value::Char('λ'))]);
+}
+
+#[test]
+fn non_ascii_atoms_split_across_feeds() {
+    let mut reader = Reader::new();
+
+    reader.feed("x à");
+    assert_eq!(reader.next_form(), Ok(Next::Form(value("x"))));
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.feed("é ");
+    assert_eq!(reader.next_form(), Ok(Next::Form(value("àé"))));
+}
+
+#[test]
+fn non_ascii_strings() {
+    assert_eq!(read_all("\"héllo wörld\" \"à\""), vec![
+        Ok(Value::String("héllo wörld".to_string())),
+        Ok(Value::String("à".to_string())),
+    ]);
+}
+
+#[test]
+fn non_ascii_comments() {
+    assert_eq!(read_all("; ünïcode comment\n(list 1) ; à\n2"), vec![Ok(value("(list 1)")), Ok(value("2"))]);
+    assert_eq!(read_all("(list ; ü\n 1)"), vec![Ok(value("(list 1)"))]);
+}
+
+#[test]
+fn non_ascii_whitespace_separates_forms() {
+    // The same whitespace the parser accepts, such as a no-break space
+    assert_eq!(read_all("a\u{a0}b\u{2003}c"), vec![Ok(value("a")), Ok(value("b")), Ok(value("c"))]);
+}
+
+#[test]
+fn forms_from_a_bufread() {
+    let input = Cursor::new("(+ 1\n 2)\n'à ; done\n");
+    let forms: Vec<_> = Reader::forms(input).map(|form| form.unwrap()).collect();
+
+    assert_eq!(forms, vec![value("(+ 1 2)"), value("'à")]);
+}
+
+#[test]
+fn escaped_quotes_dont_end_strings() {
+    assert_eq!(read_all(r#""a \" b" c"#), vec![Ok(Value::String("a \" b".to_string())), Ok(value("c"))]);
+    assert_eq!(read_all(r#"(list "\\" "\d+")"#), vec![Ok(value(r#"(list "\\" "\d+")"#))]);
+
+    let mut reader = Reader::new();
+
+    reader.feed(r#"(print "say \"hi"#);
+    assert_eq!(reader.next_form(), Ok(Next::NeedMoreInput));
+
+    reader.feed(r#"\" (not yet)")"#);
+    assert_eq!(reader.next_form(), Ok(Next::Form(value(r#"(print "say \"hi\" (not yet)")"#))));
+}
@@ -0,0 +1,49 @@
+extern crate rlisp;
+
+use rlisp::{Lisp, Value};
+
+fn eval(lisp: &mut Lisp, code: &str) -> Value {
+    match lisp.eval_raw(code) {
+        Ok(value) => value,
+        Err(err) => panic!("{} failed: {:?}", code, err),
+    }
+}
+
+#[test]
+fn regexes() {
+    let mut lisp = Lisp::new();
+
+    assert_eq!(eval(&mut lisp, "(re-match? \"^a+$\" \"aaa\")"), Value::Bool(true));
+    assert_eq!(eval(&mut lisp, "(re-find \"[0-9]+\" \"ab 12 cd 34\")"), Value::String("12".to_string()));
+    assert_eq!(eval(&mut lisp, "(re-replace \"o\" \"foo\" \"0\")"), Value::String("f00".to_string()));
+    assert!(lisp.eval_raw("(re-match? \"(\" \"a\")").is_err());
+}
+
+// Backslashes in string literals that aren't escapes are kept, so patterns can be written as they would be anywhere else
+#[test]
+fn character_classes() {
+    let mut lisp = Lisp::new();
+
+    assert_eq!(eval(&mut lisp, r#"(re-find "\d+" "ab 12 cd 34")"#), Value::String("12".to_string()));
+    assert_eq!(eval(&mut lisp, r#"(re-split "\s+" "a  b\tc")"#), eval(&mut lisp, r#"(list "a" "b" "c")"#));
+    assert_eq!(eval(&mut lisp, r#"(re-match? "^\w+@\w+\.com$" "me@example.com")"#), Value::Bool(true));
+
+    // A doubled backslash is one backslash in the string, so the regex needs four to match a literal one
+    assert_eq!(eval(&mut lisp, r#"(re-match? "^a\\\\d$" "a\\d")"#), Value::Bool(true));
+    assert_eq!(eval(&mut lisp, r#"(re-match? "^a\\\\d$" "a1")"#), Value::Bool(false));
+    assert_eq!(eval(&mut lisp, r#"(re-match? "^a\\d$" "a1")"#), Value::Bool(true));
+}
+
+// Patterns built at runtime are each compiled, but the cache of them is only kept so big
+#[test]
+fn many_patterns_can_be_used() {
+    let mut lisp = Lisp::new();
+
+    let code = "(map (\\ {n} {re-match? (str \"^\" n \"$\") (str n)}) (range 0 2000))";
+    let matched = eval(&mut lisp, code);
+    assert_eq!(matched, eval(&mut lisp, "(map (\\ {n} {id true}) (range 0 2000))"));
+
+    // Patterns that were dropped from the cache are compiled again
+    assert_eq!(eval(&mut lisp, "(re-match? \"^0$\" \"0\")"), Value::Bool(true));
+    assert_eq!(eval(&mut lisp, "(re-match? \"^0$\" \"1\")"), Value::Bool(false));
+}
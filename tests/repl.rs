@@ -0,0 +1,199 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+
+// A directory of its own for each test, which is also used as $HOME so the history file ends up there
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rlisp-repl-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+struct Output {
+    status: i32,
+    stdout: String,
+    stderr: String,
+}
+
+fn rlisp(home: &PathBuf, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .args(args)
+        .env("HOME", home)
+        .current_dir(home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    Output {
+        status: output.status.code().unwrap(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+fn repl(home: &PathBuf, input: &str) -> String {
+    let output = rlisp(home, &["-i"], input);
+    assert_eq!(output.status, 0, "{}", output.stderr);
+
+    output.stdout
+}
+
+#[test]
+fn forms_span_lines() {
+    let home = scratch_dir("lines");
+    let out = repl(&home, "(def {sq x}\n  {* x x})\n(sq 4) (sq 5)\n");
+
+    assert!(out.contains(">>> ... "), "{}", out);
+    assert!(out.contains("=> 16"), "{}", out);
+    assert!(out.contains("=> 25"), "{}", out);
+}
+
+#[test]
+fn errors_dont_end_the_session() {
+    let home = scratch_dir("errors");
+    let out = repl(&home, "(undefined-thing)\n)\n(+ 1 2)\n");
+
+    assert_eq!(out.matches("Error: ").count(), 2, "{}", out);
+    assert!(out.contains("=> 3"), "{}", out);
+}
+
+#[test]
+fn help_and_unknown_commands() {
+    let home = scratch_dir("help");
+    let out = repl(&home, ":help\n:nope\n");
+
+    for command in &[":help", ":doc", ":load", ":reset", ":env", ":quit"] {
+        assert!(out.contains(command), "{} isn't in {}", command, out);
+    }
+
+    assert!(out.contains("Unknown command :nope"), "{}", out);
+}
+
+#[test]
+fn env_and_reset() {
+    let home = scratch_dir("env");
+    let out = repl(&home, ":env\n(def 'x 5)\n(def 'map 1)\n:env\n:reset\n:env\nx\n");
+    let sections: Vec<&str> = out.split("Nothing has been defined yet").collect();
+
+    // Empty at the start and again after the reset, with the definitions in between
+    assert_eq!(sections.len(), 3, "{}", out);
+    assert!(sections[1].contains("x = 5"), "{}", out);
+    assert!(sections[1].contains("map = 1"), "{}", out);
+    assert!(!sections[1].contains("filter ="), "{}", out);
+    assert!(sections[1].contains("The environment has been reset"), "{}", out);
+
+    // x went with the reset
+    assert!(sections[2].contains("Error: "), "{}", out);
+}
+
+#[test]
+fn load_and_doc() {
+    let home = scratch_dir("load");
+    fs::write(home.join("lib.lisp"), "(def {double x}\n  \"Twice x.\"\n  {* x 2})\n(double 2)\n").unwrap();
+
+    let out = repl(&home, ":load lib.lisp\n(double 21)\n:doc double\n:load missing.lisp\n:load\n");
+
+    assert!(out.contains("=> 4"), "{}", out);
+    assert!(out.contains("=> 42"), "{}", out);
+    assert!(out.contains("(double x)"), "{}", out);
+    assert!(out.contains("Twice x."), "{}", out);
+    assert!(out.contains("Error: missing.lisp: "), "{}", out);
+    assert!(out.contains("usage: :load FILE"), "{}", out);
+}
+
+#[test]
+fn quit_stops_reading() {
+    let home = scratch_dir("quit");
+    let out = repl(&home, "(+ 1 1)\n:quit\n(+ 2 2)\n");
+
+    assert!(out.contains("=> 2"), "{}", out);
+    assert!(!out.contains("=> 4"), "{}", out);
+}
+
+#[test]
+fn history_is_kept() {
+    let home = scratch_dir("history");
+    repl(&home, "(+ 1 1)\n\n(+ 1 1)\n:env\n");
+    repl(&home, "(+ 2 2)\n");
+
+    // Blank lines and repeats aren't kept
+    let history = fs::read_to_string(home.join(".rlisp_history")).unwrap();
+    assert_eq!(history, "(+ 1 1)\n:env\n(+ 2 2)\n");
+}
+
+#[test]
+fn scripts() {
+    let home = scratch_dir("scripts");
+    fs::write(home.join("ok.lisp"), "(def 'x 2)\n(print (* x 21))\n").unwrap();
+    fs::write(home.join("bad.lisp"), "(def 'x 2)\n\n  (+ x \"y\")\n(print \"not reached\")\n").unwrap();
+    fs::write(home.join("syntax.lisp"), "(print 1)\n(print (+ 1 2)\n").unwrap();
+
+    let ok = rlisp(&home, &["ok.lisp"], "");
+    assert_eq!((ok.status, &ok.stdout[..]), (0, "42\n"));
+
+    // Errors point at the form that failed
+    let bad = rlisp(&home, &["bad.lisp"], "");
+    assert_eq!(bad.status, 1);
+    assert!(bad.stderr.starts_with("bad.lisp:3:3: "), "{}", bad.stderr);
+    assert_eq!(bad.stdout, "");
+
+    // Syntax errors are found before anything runs
+    let syntax = rlisp(&home, &["syntax.lisp"], "");
+    assert_eq!(syntax.status, 1);
+    assert!(syntax.stderr.starts_with("syntax.lisp:2:1: "), "{}", syntax.stderr);
+    assert_eq!(syntax.stdout, "");
+
+    assert_eq!(rlisp(&home, &["missing.lisp"], "").status, 2);
+}
+
+#[test]
+fn scripts_from_stdin() {
+    let home = scratch_dir("stdin");
+
+    for args in &[&[][..], &["-"][..]] {
+        let out = rlisp(&home, args, "(print \"piped\")\n(+ 1 undefined)\n");
+        assert_eq!(out.status, 1);
+        assert_eq!(out.stdout, "piped\n");
+        assert!(out.stderr.starts_with("<stdin>:2:1: "), "{}", out.stderr);
+    }
+}
+
+#[test]
+fn expressions() {
+    let home = scratch_dir("expressions");
+
+    let out = rlisp(&home, &["-e", "(def 'x 20) (+ x 22)"], "");
+    assert_eq!((out.status, &out.stdout[..]), (0, "42\n"));
+
+    // Nothing is printed for nil
+    let out = rlisp(&home, &["-e", "(print \"hi\")"], "");
+    assert_eq!((out.status, &out.stdout[..]), (0, "hi\n"));
+
+    let out = rlisp(&home, &["-e", "(+ 1"], "");
+    assert_eq!(out.status, 1);
+    assert!(out.stderr.starts_with("-e:1:1: "), "{}", out.stderr);
+}
+
+#[test]
+fn bad_arguments() {
+    let home = scratch_dir("usage");
+
+    for args in &[&["-e"][..], &["--nope"][..], &["a.lisp", "b.lisp"][..]] {
+        let out = rlisp(&home, args, "");
+        assert_eq!(out.status, 2, "{:?}", args);
+        assert!(out.stderr.starts_with("usage: "), "{:?}", args);
+    }
+
+    let help = rlisp(&home, &["--help"], "");
+    assert_eq!(help.status, 0);
+    assert!(help.stdout.starts_with("usage: "));
+}
@@ -0,0 +1,32 @@
+extern crate rlisp;
+
+use rlisp::{Symbol, Value, ParseError};
+use rlisp::symbol::MAX_SYMBOL_BYTES;
+use rlisp::parse::parse_str;
+
+#[test]
+fn symbols_compare_by_name() {
+    assert_eq!(Symbol::intern("abc"), Symbol::intern("abc"));
+    assert!(Symbol::intern("abc") != Symbol::intern("abd"));
+    assert_eq!(Symbol::intern("abc").as_str(), "abc");
+    assert_eq!(parse_str("abc"), Ok(vec![Value::Symbol(Symbol::intern("abc"))]));
+}
+
+// Each test runs on its own thread, so this one gets a table to itself
+#[test]
+fn parsing_stops_interning_new_names_once_the_table_is_full() {
+    let existing = Symbol::intern("existing");
+    let long = "x".repeat(64 * 1024);
+
+    let mut count = 0;
+    while Symbol::try_intern(&format!("{}{}", long, count)).is_some() {
+        count += 1;
+    }
+
+    assert!(count <= MAX_SYMBOL_BYTES / long.len());
+    assert_eq!(parse_str(&format!("{}-new", long)), Err(ParseError::TooManySymbols));
+
+    // Names that have already been interned are still fine
+    assert_eq!(parse_str("existing"), Ok(vec![Value::Symbol(existing)]));
+    assert_eq!(Symbol::try_intern(&format!("{}0", long)), Some(Symbol::intern(&format!("{}0", long))));
+}
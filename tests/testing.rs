@@ -0,0 +1,220 @@
+extern crate rlisp;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use rlisp::{Lisp, Value, FuncError};
+use rlisp::testing::{self, Outcome, TestResult};
+
+const SOURCE: &'static str = "\
+(def {square x} {* x x})
+
+(deftest 'squares
+  {assert-equal 9 (square 3)}
+  {assert-error {square \"3\"}})
+
+(deftest \"square of a negative\" {assert (> (square -2) 0) \"squares are positive\"})
+
+(deftest 'wrong {assert-equal (list 1 2 3) (list 1 5 3)})
+
+(deftest 'broken {undefined-function 1})
+
+; Tests can't see each other's definitions
+(deftest 'defines {def 'leaked 1})
+(deftest 'uses {assert-error {id leaked}})
+";
+
+fn outcome(results: &[TestResult], name: &str) -> String {
+    match results.iter().find(|result| result.name == name) {
+        Some(result) => match result.outcome {
+            Outcome::Passed => "passed".to_string(),
+            Outcome::Failed(_) => "failed".to_string(),
+            Outcome::Errored(_) => "errored".to_string(),
+        },
+        None => panic!("{} wasn't run", name),
+    }
+}
+
+#[test]
+fn deftest_registers_tests() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(deftest 'a {assert false}) (deftest \"b\" {id 1} {id 2}) (deftest 'a {id 3})").unwrap();
+
+    // Defining a test again replaces it
+    let tests = lisp.tests();
+    assert_eq!(tests.iter().map(|test| &test.name[..]).collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(tests[1].body.len(), 2);
+    assert_eq!(lisp.eval_raw("(list (deftest 'c {id 1}))").unwrap(), lisp.eval_raw("(list nil)").unwrap());
+
+    assert!(lisp.eval_raw("(deftest 1 {id 1})").is_err());
+}
+
+#[test]
+fn assertions() {
+    let mut lisp = Lisp::new();
+
+    assert_eq!(lisp.eval_raw("(assert true)").unwrap(), Value::Nil);
+    match lisp.eval_raw("(assert (= 1 2) \"one isn't two\")") {
+        Err(FuncError::AssertionFailed(ref message)) if message == "one isn't two" => (),
+        other => panic!("{:?}", other),
+    }
+
+    match lisp.eval_raw("(assert-equal 1 2)") {
+        Err(FuncError::NotEqual { ref expected, ref actual }) => {
+            assert_eq!((expected, actual), (&Value::Number(1.0), &Value::Number(2.0)));
+        },
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(lisp.eval_raw("(assert-error {/ 1 0} \"zero\")").unwrap(), Value::Nil);
+    assert!(lisp.eval_raw("(assert-error {+ 1 1})").is_err());
+    assert!(lisp.eval_raw("(assert-error {/ 1 0} \"not in the message\")").is_err());
+    assert_eq!(lisp.scopes.len(), 1);
+}
+
+#[test]
+fn running_a_file() {
+    let results = testing::run_source("squares.lisp", SOURCE, None);
+
+    assert_eq!(results.len(), 6);
+    assert_eq!(outcome(&results, "squares"), "passed");
+    assert_eq!(outcome(&results, "square of a negative"), "passed");
+    assert_eq!(outcome(&results, "wrong"), "failed");
+    assert_eq!(outcome(&results, "broken"), "errored");
+    assert_eq!(outcome(&results, "defines"), "passed");
+    assert_eq!(outcome(&results, "uses"), "passed");
+
+    for result in &results {
+        assert_eq!(result.file, "squares.lisp");
+    }
+
+    let lines: Vec<Option<usize>> = results.iter().map(|result| result.line).collect();
+    assert_eq!(lines, vec![Some(3), Some(7), Some(9), Some(11), Some(14), Some(15)]);
+}
+
+#[test]
+fn failures_show_a_diff() {
+    let results = testing::run_source("squares.lisp", SOURCE, Some("wrong"));
+    let failure = results[0].failure().unwrap();
+
+    assert!(failure.starts_with("values aren't equal"), "{}", failure);
+    assert!(failure.contains("- (1 2 3)\n+ (1 5 3)"), "{}", failure);
+    assert!(testing::run_source("squares.lisp", SOURCE, Some("squares"))[0].failure().is_none());
+}
+
+#[test]
+fn filtering() {
+    let names = |filter| testing::run_source("squares.lisp", SOURCE, Some(filter)).into_iter()
+        .map(|result| result.name)
+        .collect::<Vec<_>>();
+
+    assert_eq!(names("square"), vec!["squares", "square of a negative"]);
+    assert_eq!(names("negative"), vec!["square of a negative"]);
+    assert!(names("nothing matches").is_empty());
+}
+
+#[test]
+fn files_that_dont_load() {
+    let results = testing::run_source("bad.lisp", "(deftest 'a {id 1})\n(undefined)\n", None);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "(load)");
+    assert!(!results[0].passed());
+
+    assert!(!testing::run_source("bad.lisp", "(deftest 'a", None)[0].passed());
+}
+
+#[test]
+fn diffs() {
+    assert_eq!(testing::diff(&Value::Number(1.0), &Value::Number(2.0)), "- 1\n+ 2\n");
+
+    // Long lists are compared an item at a time
+    let mut lisp = Lisp::new();
+    let expected = lisp.eval_raw("(list \"a fairly long string\" \"another long string\" \"the third one\")").unwrap();
+    let actual = lisp.eval_raw("(list \"a fairly long string\" \"a different string\" \"the third one\")").unwrap();
+    assert_eq!(testing::diff(&expected, &actual), "  \"a fairly long string\"\n- \"another long string\"\n\
+                                                  + \"a different string\"\n  \"the third one\"\n");
+}
+
+#[test]
+fn junit_reports() {
+    let mut results = testing::run_source("a<b>.lisp", SOURCE, None);
+    results.extend(testing::run_source("other.lisp", "(deftest 'ok {id 1})", None));
+    let xml = testing::junit_xml(&results);
+
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites tests=\"7\" failures=\"1\" errors=\"1\""), "{}", xml);
+    assert!(xml.contains("<testsuite name=\"a&lt;b&gt;.lisp\" tests=\"6\" failures=\"1\" errors=\"1\""), "{}", xml);
+    assert!(xml.contains("<testsuite name=\"other.lisp\" tests=\"1\" failures=\"0\" errors=\"0\""), "{}", xml);
+    assert!(xml.contains("<testcase name=\"square of a negative\" classname=\"a&lt;b&gt;.lisp\""), "{}", xml);
+    assert!(xml.contains("<failure message=\"values aren&apos;t equal (- expected, + actual)\">"), "{}", xml);
+    assert!(xml.contains("<error message=\""), "{}", xml);
+    assert!(xml.ends_with("  </testsuite>\n</testsuites>\n"), "{}", xml);
+}
+
+// A directory of its own for each test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rlisp-testing-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+#[test]
+fn finding_test_files() {
+    for name in &["test_a.lisp", "test-a.lisp", "a_test.lisp", "a-test.lisp"] {
+        assert!(testing::is_test_file(Path::new(name)), "{}", name);
+    }
+    for name in &["a.lisp", "testing.lisp", "test_a.txt", "test_"] {
+        assert!(!testing::is_test_file(Path::new(name)), "{}", name);
+    }
+
+    let dir = scratch_dir("discover");
+    for path in &["b/test_b.lisp", "a_test.lisp", "lib.lisp", ".hidden/test_c.lisp", "target/test_d.lisp"] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    // Files given directly are kept whatever they're called
+    let found = testing::discover(&[dir.clone(), dir.join("lib.lisp")]);
+    assert_eq!(found, vec![dir.join("a_test.lisp"), dir.join("b/test_b.lisp"), dir.join("lib.lisp")]);
+}
+
+fn rlisp_test(dir: &PathBuf, args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlisp"))
+        .arg("--test")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn the_runner() {
+    let dir = scratch_dir("runner");
+    fs::write(dir.join("test_squares.lisp"), SOURCE).unwrap();
+    fs::write(dir.join("test_ok.lisp"), "(deftest 'fine {assert-equal 2 (+ 1 1)})").unwrap();
+
+    let (status, out) = rlisp_test(&dir, &[]);
+    assert_eq!(status, 1, "{}", out);
+    assert!(out.contains("test ./test_ok.lisp fine ... ok"), "{}", out);
+    assert!(out.contains("test ./test_squares.lisp wrong ... FAILED"), "{}", out);
+    assert!(out.contains("---- ./test_squares.lisp:9 wrong ----"), "{}", out);
+    assert!(out.ends_with("test result: FAILED. 5 passed; 2 failed\n"), "{}", out);
+
+    let (status, out) = rlisp_test(&dir, &["--filter", "square"]);
+    assert_eq!(status, 0, "{}", out);
+    assert!(out.ends_with("test result: ok. 2 passed; 0 failed\n"), "{}", out);
+
+    let (status, out) = rlisp_test(&dir, &["--junit", "report.xml", "test_ok.lisp"]);
+    assert_eq!(status, 0, "{}", out);
+    assert!(fs::read_to_string(dir.join("report.xml")).unwrap().contains("<testsuite name=\"test_ok.lisp\" tests=\"1\""));
+
+    assert_eq!(rlisp_test(&dir, &["missing_test.lisp"]).0, 2);
+    assert_eq!(rlisp_test(&dir, &["--filter"]).0, 2);
+}
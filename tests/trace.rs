@@ -0,0 +1,243 @@
+extern crate rlisp;
+
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::{self, Command};
+use std::rc::Rc;
+use std::time::Duration;
+
+use rlisp::{Lisp, Backend, Value, Symbol};
+use rlisp::profile::{Profile, FunctionProfile, SortBy};
+
+// Output the test can read back after the tracer has written to it
+#[derive(Clone)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn traced(lisp: &mut Lisp, code: &str) -> String {
+    let output = Shared(Rc::new(RefCell::new(Vec::new())));
+    lisp.set_trace_output(Box::new(output.clone()));
+
+    let _ = lisp.eval_raw(code);
+    let out = String::from_utf8(output.0.borrow().clone()).unwrap();
+    out
+}
+
+#[test]
+fn traces_are_indented_by_depth() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {fact n} {if (<= n 1) 1 {* n (fact (- n 1))}})").unwrap();
+    lisp.trace(Symbol::intern("fact"));
+
+    assert_eq!(traced(&mut lisp, "(fact 3)"), "\
+(fact 3)
+| (fact 2)
+| | (fact 1)
+| | => 1
+| => 2
+=> 6
+");
+}
+
+#[test]
+fn only_traced_functions_are_logged() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {sq x} {* x x}) (def {sum-sq a b} {+ (sq a) (sq b)})").unwrap();
+    lisp.trace(Symbol::intern("sq"));
+
+    assert_eq!(traced(&mut lisp, "(sum-sq 2 3)"), "(sq 2)\n=> 4\n(sq 3)\n=> 9\n");
+}
+
+#[test]
+fn functions_passed_as_values_are_traced() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {inc x} {+ x 1})").unwrap();
+    lisp.trace(Symbol::intern("inc"));
+
+    let out = traced(&mut lisp, "(map inc {1 2})");
+    assert_eq!(out, "(inc 1)\n=> 2\n(inc 2)\n=> 3\n");
+}
+
+#[test]
+fn errors_are_traced() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {half x} {/ x 2})").unwrap();
+    lisp.trace(Symbol::intern("half"));
+
+    let out = traced(&mut lisp, "(half \"x\")");
+    assert!(out.starts_with("(half \"x\")\n!! "), "{}", out);
+
+    // The depth is back to normal after the error
+    assert_eq!(traced(&mut lisp, "(half 4)"), "(half 4)\n=> 2\n");
+}
+
+#[test]
+fn tracing_uses_the_tree_walker() {
+    let mut lisp = Lisp::new();
+    lisp.set_backend(Backend::Bytecode);
+    lisp.eval_raw("(def {sq x} {* x x})").unwrap();
+    lisp.trace(Symbol::intern("sq"));
+
+    assert_eq!(traced(&mut lisp, "(sq 5)"), "(sq 5)\n=> 25\n");
+}
+
+#[test]
+fn trace_and_untrace_builtins() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {f x} {id x}) (def {g x} {id x})").unwrap();
+
+    assert_eq!(lisp.eval_raw("(trace 'g 'f)").unwrap(), lisp.eval_raw("(list 'f 'g)").unwrap());
+    assert_eq!(lisp.traced(), vec![Symbol::intern("f"), Symbol::intern("g")]);
+
+    assert_eq!(lisp.eval_raw("(untrace 'f)").unwrap(), Value::Nil);
+    assert_eq!(lisp.traced(), vec![Symbol::intern("g")]);
+    assert_eq!(traced(&mut lisp, "(f (g 1))"), "(g 1)\n=> 1\n");
+
+    // Without names everything is untraced
+    lisp.eval_raw("(trace 'f)").unwrap();
+    lisp.eval_raw("(untrace)").unwrap();
+    assert!(lisp.traced().is_empty());
+    assert_eq!(traced(&mut lisp, "(f (g 1))"), "");
+
+    assert!(lisp.eval_raw("(trace 1)").is_err());
+    assert!(!lisp.untrace(Symbol::intern("f")));
+}
+
+fn function<'a>(profile: &'a Profile, name: &str) -> &'a FunctionProfile {
+    match profile.functions.iter().find(|function| function.name == name) {
+        Some(function) => function,
+        None => panic!("{} isn't in {:?}", name, profile),
+    }
+}
+
+#[test]
+fn profiling_counts_calls() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {fib n} {if (< n 2) n {+ (fib (- n 1)) (fib (- n 2))}})").unwrap();
+
+    assert!(lisp.profile().is_none());
+    lisp.start_profiling();
+    assert_eq!(lisp.eval_raw("(fib 6)").unwrap(), Value::Number(8.0));
+    let profile = lisp.stop_profiling().unwrap();
+    assert!(lisp.stop_profiling().is_none());
+
+    let fib = function(&profile, "fib");
+    assert_eq!(fib.calls, 25);
+    assert!(fib.self_time <= fib.total);
+    assert_eq!(function(&profile, "+").calls, 12);
+
+    // Recursive calls aren't counted twice in the total
+    let self_times = profile.functions.iter().fold(Duration::new(0, 0), |sum, function| sum + function.self_time);
+    assert!(fib.total <= self_times, "{:?}", profile);
+}
+
+#[test]
+fn starting_again_starts_over() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {f x} {id x})").unwrap();
+
+    lisp.start_profiling();
+    lisp.eval_raw("(f 1) (f 2)").unwrap();
+    assert_eq!(function(&lisp.profile().unwrap(), "f").calls, 2);
+
+    lisp.start_profiling();
+    lisp.eval_raw("(f 3)").unwrap();
+    assert_eq!(function(&lisp.stop_profiling().unwrap(), "f").calls, 1);
+}
+
+#[test]
+fn unnamed_functions() {
+    let mut lisp = Lisp::new();
+    lisp.eval_raw("(def {inc x} {+ x 1})").unwrap();
+
+    lisp.start_profiling();
+    lisp.eval_raw("(map inc {1 2 3}) (map (\\ {x} {* x 2}) {1 2})").unwrap();
+    let profile = lisp.stop_profiling().unwrap();
+
+    assert_eq!(function(&profile, "inc").calls, 3);
+    assert_eq!(function(&profile, "<lambda>").calls, 2);
+}
+
+fn made_up() -> Profile {
+    let ms = |ms| Duration::from_millis(ms);
+    let function = |name: &str, calls, total, self_time| FunctionProfile {
+        name: name.to_string(),
+        calls: calls,
+        total: ms(total),
+        self_time: ms(self_time),
+    };
+
+    Profile {
+        functions: vec![function("a", 1, 10, 2), function("b", 5, 8, 3), function("c", 2, 5, 5)],
+        stacks: vec![("a".to_string(), ms(2)), ("a;b".to_string(), ms(3)), ("a;b;c".to_string(), ms(5)),
+                     ("a;d".to_string(), Duration::new(0, 10))],
+    }
+}
+
+fn names(profile: &Profile) -> Vec<&str> {
+    profile.functions.iter().map(|function| function.name.as_str()).collect()
+}
+
+#[test]
+fn sorting() {
+    let mut profile = made_up();
+
+    profile.sort_by(SortBy::SelfTime);
+    assert_eq!(names(&profile), vec!["c", "b", "a"]);
+    profile.sort_by(SortBy::Total);
+    assert_eq!(names(&profile), vec!["a", "b", "c"]);
+    profile.sort_by(SortBy::Calls);
+    assert_eq!(names(&profile), vec!["b", "c", "a"]);
+    profile.sort_by(SortBy::Name);
+    assert_eq!(names(&profile), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn table_and_folded() {
+    let mut profile = made_up();
+    profile.sort_by(SortBy::Name);
+
+    let table = profile.table();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", table);
+    assert!(lines[0].contains("calls") && lines[0].contains("self ms"), "{}", table);
+    assert_eq!(lines[1].split_whitespace().collect::<Vec<_>>(), vec!["1", "10.000", "2.000", "a"]);
+
+    // Stacks under a microsecond are left out
+    assert_eq!(profile.folded(), "a 2000\na;b 3000\na;b;c 5000\n");
+}
+
+#[test]
+fn profiling_scripts() {
+    let dir = env::temp_dir().join(format!("rlisp-profile-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("fib.lisp");
+    fs::write(&script, "(def {fib n} {if (< n 2) n {+ (fib (- n 1)) (fib (- n 2))}})\n(print (fib 8))\n").unwrap();
+
+    let table = Command::new(env!("CARGO_BIN_EXE_rlisp")).arg("--profile").arg(&script).output().unwrap();
+    assert_eq!(String::from_utf8(table.stdout).unwrap(), "21\n");
+    let stderr = String::from_utf8(table.stderr).unwrap();
+    assert!(stderr.lines().any(|line| line.starts_with("        67 ") && line.ends_with(" fib")), "{}", stderr);
+
+    let folded = Command::new(env!("CARGO_BIN_EXE_rlisp")).arg("--folded").arg(&script).output().unwrap();
+    let stderr = String::from_utf8(folded.stderr).unwrap();
+    for line in stderr.lines() {
+        let micros = line.rsplit(' ').next().unwrap();
+        assert!(micros.parse::<u64>().is_ok(), "{}", stderr);
+    }
+    assert!(stderr.contains("\nfib;if;fib;if "), "{}", stderr);
+
+    let _ = fs::remove_dir_all(&dir);
+}